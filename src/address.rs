@@ -0,0 +1,408 @@
+// ADDRESSES OF SCRIPT PUB KEYS, ENCODED AND DECODED FOR EACH NETWORK
+// BASE58CHECK FOR P2PKH AND P2SH, BECH32 (BIP173) FOR SEGWIT V0 AND BECH32M (BIP350) FOR V1+
+
+use failure::format_err;
+
+use crate::{
+    block_mine::serialise_tx::double_sha256,
+    error::Result,
+    network::Network,
+    script::{Script, ScriptType, OP_0, OP_CHECKSIG, OP_DUP, OP_EQUAL, OP_EQUALVERIFY, OP_HASH160},
+};
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+// CHECKSUM CONSTANTS: BECH32 FOR WITNESS V0, BECH32M FOR EVERYTHING NEWER
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+pub fn base58_encode(data: &[u8]) -> String {
+    // BASE 58 DIGITS, LEAST SIGNIFICANT FIRST
+    let mut digits: Vec<u8> = Vec::new();
+
+    for byte in data {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+
+    // EVERY LEADING ZERO BYTE IS WRITTEN AS A '1'
+    let leading_zeros = data.iter().take_while(|byte| **byte == 0).count();
+
+    std::iter::repeat_n(b'1', leading_zeros)
+        .chain(
+            digits
+                .iter()
+                .rev()
+                .map(|digit| BASE58_ALPHABET[*digit as usize]),
+        )
+        .map(char::from)
+        .collect()
+}
+
+// VERSION BYTE + PAYLOAD + FIRST 4 BYTES OF THEIR DOUBLE SHA256
+pub fn base58check_encode(version: u8, payload: &[u8]) -> String {
+    let mut data = vec![version];
+    data.extend_from_slice(payload);
+
+    let checksum = double_sha256(&data);
+    data.extend_from_slice(&checksum[..4]);
+
+    base58_encode(&data)
+}
+
+pub fn base58_decode(encoded: &str) -> Result<Vec<u8>> {
+    // BASE 256 BYTES, LEAST SIGNIFICANT FIRST
+    let mut bytes: Vec<u8> = Vec::new();
+
+    for c in encoded.bytes() {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or_else(|| format_err!("INVALID BASE58 CHARACTER: {}", c as char))?
+            as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = (carry & 0xff) as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push((carry & 0xff) as u8);
+            carry >>= 8;
+        }
+    }
+
+    let leading_ones = encoded.bytes().take_while(|c| *c == b'1').count();
+
+    Ok(std::iter::repeat_n(0, leading_ones)
+        .chain(bytes.into_iter().rev())
+        .collect())
+}
+
+// (VERSION BYTE, PAYLOAD) AFTER CHECKING THE 4 BYTE CHECKSUM
+pub fn base58check_decode(encoded: &str) -> Result<(u8, Vec<u8>)> {
+    let data = base58_decode(encoded)?;
+    if data.len() < 5 {
+        return Err(format_err!("BASE58CHECK STRING TOO SHORT: {}", encoded));
+    }
+
+    let (body, checksum) = data.split_at(data.len() - 4);
+    if double_sha256(body)[..4] != *checksum {
+        return Err(format_err!("BAD BASE58CHECK CHECKSUM: {}", encoded));
+    }
+
+    Ok((body[0], body[1..].to_vec()))
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+    let mut checksum: u32 = 1;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ *value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+fn bech32_hrp_expand(hrp: &str) -> Vec<u8> {
+    let mut expanded: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    expanded.push(0);
+    expanded.extend(hrp.bytes().map(|c| c & 31));
+    expanded
+}
+
+// REGROUPS BITS, E.G. 8-BIT BYTES INTO 5-BIT BECH32 CHARACTERS
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut acc: u32 = 0;
+    let mut bits: u32 = 0;
+    let max_value: u32 = (1 << to) - 1;
+    let mut converted: Vec<u8> = Vec::new();
+
+    for value in data {
+        if (*value as u32) >> from != 0 {
+            return None;
+        }
+        acc = (acc << from) | *value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push(((acc >> bits) & max_value) as u8);
+        }
+    }
+
+    if pad {
+        if bits > 0 {
+            converted.push(((acc << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max_value) != 0 {
+        return None;
+    }
+
+    Some(converted)
+}
+
+// SEGWIT ADDRESS OF A WITNESS PROGRAM
+pub fn segwit_encode(hrp: &str, version: u8, program: &[u8]) -> String {
+    let mut data = vec![version];
+    data.extend(convert_bits(program, 8, 5, true).unwrap_or_default());
+
+    let constant = if version == 0 {
+        BECH32_CONST
+    } else {
+        BECH32M_CONST
+    };
+    bech32_encode(hrp, data, constant)
+}
+
+// HRP, SEPARATOR, 5-BIT DATA AND THE CHECKSUM FOR `constant` (BIP173 OR BIP350)
+fn bech32_encode(hrp: &str, data: Vec<u8>, constant: u32) -> String {
+    let mut values = bech32_hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    values.extend_from_slice(&[0u8; 6]);
+    let polymod = bech32_polymod(&values) ^ constant;
+
+    let checksum = (0..6).map(|i| ((polymod >> (5 * (5 - i))) & 31) as u8);
+
+    let mut address = format!("{}1", hrp);
+    address.extend(
+        data.into_iter()
+            .chain(checksum)
+            .map(|value| BECH32_CHARSET[value as usize] as char),
+    );
+    address
+}
+
+// (WITNESS VERSION, PROGRAM) OF A SEGWIT ADDRESS WITH THE GIVEN HRP
+pub fn segwit_decode(hrp: &str, address: &str) -> Result<(u8, Vec<u8>)> {
+    // MIXED CASE IS NOT ALLOWED, EITHER CASE ON ITS OWN IS
+    if address.to_lowercase() != address && address.to_uppercase() != address {
+        return Err(format_err!("MIXED CASE SEGWIT ADDRESS: {}", address));
+    }
+    let address = address.to_lowercase();
+
+    if address.len() > 90 {
+        return Err(format_err!("SEGWIT ADDRESS TOO LONG: {}", address));
+    }
+
+    let (address_hrp, data_part) = address
+        .rsplit_once('1')
+        .ok_or_else(|| format_err!("MISSING BECH32 SEPARATOR: {}", address))?;
+    if address_hrp != hrp {
+        return Err(format_err!("WRONG HRP {}, EXPECTED {}", address_hrp, hrp));
+    }
+    if data_part.len() < 7 {
+        return Err(format_err!("SEGWIT ADDRESS TOO SHORT: {}", address));
+    }
+
+    let data: Vec<u8> = data_part
+        .bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|a| *a == c).map(|i| i as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| format_err!("INVALID BECH32 CHARACTER IN {}", address))?;
+
+    let version = data[0];
+    if version > 16 {
+        return Err(format_err!("INVALID WITNESS VERSION {}", version));
+    }
+
+    let constant = if version == 0 {
+        BECH32_CONST
+    } else {
+        BECH32M_CONST
+    };
+
+    let mut values = bech32_hrp_expand(hrp);
+    values.extend_from_slice(&data);
+    if bech32_polymod(&values) != constant {
+        return Err(format_err!("BAD BECH32 CHECKSUM: {}", address));
+    }
+
+    let program = convert_bits(&data[1..data.len() - 6], 5, 8, false)
+        .ok_or_else(|| format_err!("INVALID PADDING IN {}", address))?;
+
+    if program.len() < 2 || program.len() > 40 {
+        return Err(format_err!(
+            "INVALID WITNESS PROGRAM LENGTH {}",
+            program.len()
+        ));
+    }
+    if version == 0 && program.len() != 20 && program.len() != 32 {
+        return Err(format_err!("INVALID V0 PROGRAM LENGTH {}", program.len()));
+    }
+
+    Ok((version, program))
+}
+
+// THE SCRIPT PUB KEY THAT PAYS TO AN ADDRESS OF THE GIVEN NETWORK
+pub fn address_to_script(address: &str, network: Network) -> Result<Script> {
+    let hrp = network.bech32_hrp();
+
+    if address.to_lowercase().starts_with(&format!("{}1", hrp)) {
+        let (version, program) = segwit_decode(hrp, address)?;
+
+        let mut script = vec![if version == 0 { OP_0 } else { 0x50 + version }];
+        script.push(program.len() as u8);
+        script.extend(program);
+        return Ok(Script::new(script));
+    }
+
+    // ANYTHING ELSE HAS TO BE BASE58, INCLUDING SEGWIT ADDRESSES OF ANOTHER NETWORK
+    let (prefix, hash) = base58check_decode(address)
+        .map_err(|e| format_err!("{} IS NOT A {:?} ADDRESS: {}", address, network, e))?;
+    if hash.len() != 20 {
+        return Err(format_err!("INVALID BASE58 ADDRESS LENGTH: {}", address));
+    }
+
+    let mut script = Vec::new();
+    if prefix == network.p2pkh_prefix() {
+        script.extend([OP_DUP, OP_HASH160, 0x14]);
+        script.extend(hash);
+        script.extend([OP_EQUALVERIFY, OP_CHECKSIG]);
+    } else if prefix == network.p2sh_prefix() {
+        script.extend([OP_HASH160, 0x14]);
+        script.extend(hash);
+        script.push(OP_EQUAL);
+    } else {
+        return Err(format_err!("ADDRESS {} IS NOT FOR {:?}", address, network));
+    }
+
+    Ok(Script::new(script))
+}
+
+// NONE FOR SCRIPTS WITHOUT AN ADDRESS FORM: P2PK, BARE MULTISIG, OP_RETURN, NONSTANDARD
+pub fn script_address(script: &Script, network: Network) -> Option<String> {
+    let bytes = script.as_bytes();
+
+    match script.script_type() {
+        ScriptType::P2pkh => Some(base58check_encode(network.p2pkh_prefix(), &bytes[3..23])),
+        ScriptType::P2sh => Some(base58check_encode(network.p2sh_prefix(), &bytes[2..22])),
+        ScriptType::P2wpkh | ScriptType::P2wsh | ScriptType::P2tr | ScriptType::WitnessUnknown => {
+            let (version, program) = script.witness_program()?;
+            Some(segwit_encode(network.bech32_hrp(), version, program))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::error::Result;
+
+    use super::*;
+
+    #[test]
+    fn script_address_test() -> Result<()> {
+        let cases = [
+            // GENESIS COINBASE PUBKEY HASH
+            (
+                "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac",
+                "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa",
+            ),
+            (
+                "a914748284390f9e263a4b766a75d0633c50426eb87587",
+                "3CK4fEwbMP7heJarmU4eqA3sMbVJyEnU3V",
+            ),
+            // BIP173 AND BIP350 TEST VECTORS
+            (
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            ),
+            (
+                "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+                "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr",
+            ),
+        ];
+
+        for (script_hex, address) in cases {
+            let script = Script::from_hex(script_hex)?;
+            assert_eq!(
+                script_address(&script, Network::Mainnet).as_deref(),
+                Some(address)
+            );
+        }
+
+        // REGTEST USES ITS OWN HRP
+        let script = Script::from_hex(cases[2].0)?;
+        assert_eq!(
+            script_address(&script, Network::Regtest).unwrap(),
+            "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"
+        );
+
+        assert_eq!(
+            script_address(&Script::from_hex("6a00")?, Network::Mainnet),
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn address_to_script_test() -> Result<()> {
+        let p2pkh = Script::from_hex("76a914edf10a7fac6b32e24daa5305c723f3de58db1bc888ac")?;
+        let p2tr = Script::from_hex(
+            "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+        )?;
+
+        // EVERY NETWORK ROUND TRIPS, AND REJECTS THE ADDRESSES OF THE OTHERS
+        for network in [
+            Network::Mainnet,
+            Network::Testnet,
+            Network::Signet,
+            Network::Regtest,
+        ] {
+            for script in [&p2pkh, &p2tr] {
+                let address = script_address(script, network).unwrap();
+                assert_eq!(&address_to_script(&address, network)?, script);
+            }
+        }
+        let testnet_address = script_address(&p2tr, Network::Testnet).unwrap();
+        assert!(address_to_script(&testnet_address, Network::Mainnet).is_err());
+        let testnet_address = script_address(&p2pkh, Network::Testnet).unwrap();
+        assert!(address_to_script(&testnet_address, Network::Mainnet).is_err());
+
+        // UPPERCASE BECH32 IS FINE, MIXED CASE AND BAD CHECKSUMS ARE NOT
+        let address = "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4";
+        assert!(address_to_script(address, Network::Mainnet).is_ok());
+        let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xW7kv8f3t4";
+        assert!(address_to_script(address, Network::Mainnet).is_err());
+        let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5";
+        assert!(address_to_script(address, Network::Mainnet).is_err());
+        let address = "1Nh7uHdvY6fNwtQtM1G5EZAFPLC33B59rC";
+        assert!(address_to_script(address, Network::Mainnet).is_err());
+
+        // BIP350: V0 TAKES THE BECH32 CHECKSUM AND V1+ THE BECH32M ONE, NEVER THE OTHER WAY ROUND
+        let address = "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr";
+        assert_eq!(address_to_script(address, Network::Mainnet)?, p2tr);
+        let program = &p2tr.as_bytes()[2..];
+        let mut data = vec![1];
+        data.extend(convert_bits(program, 8, 5, true).unwrap());
+        let address = bech32_encode("bc", data.clone(), BECH32_CONST);
+        assert!(segwit_decode("bc", &address).is_err());
+        assert_eq!(
+            segwit_decode("bc", &bech32_encode("bc", data, BECH32M_CONST))?,
+            (1, program.to_vec())
+        );
+
+        let mut data = vec![0];
+        data.extend(convert_bits(&program[..20], 8, 5, true).unwrap());
+        let address = bech32_encode("bc", data, BECH32M_CONST);
+        assert!(segwit_decode("bc", &address).is_err());
+
+        Ok(())
+    }
+}
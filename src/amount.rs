@@ -0,0 +1,92 @@
+// AMOUNTS IN SATOSHIS, LIKE BITCOIN CORE'S CAmount
+// ARITHMETIC IS CHECKED, AND AN AMOUNT IS ONLY VALID UP TO MAX_MONEY (MoneyRange)
+
+use std::fmt;
+
+pub const COIN: u64 = 100_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    // 21M BTC, NO OUTPUT, INPUT OR SUM OF THEM CAN BE MORE
+    pub const MAX_MONEY: Amount = Amount(21_000_000 * COIN);
+
+    pub const fn from_sat(sats: u64) -> Self {
+        Amount(sats)
+    }
+
+    pub fn to_sat(self) -> u64 {
+        self.0
+    }
+
+    // MoneyRange: AMOUNTS ARE UNSIGNED, SO ONLY THE UPPER BOUND CAN BE BROKEN
+    pub fn is_money_range(self) -> bool {
+        self <= Amount::MAX_MONEY
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    // NONE IF `other` IS MORE, I.E. THE RESULT WOULD BE NEGATIVE
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+
+    pub fn saturating_add(self, other: Amount) -> Amount {
+        Amount(self.0.saturating_add(other.0))
+    }
+
+    // SUMS LIKE CheckTransaction AND CheckTxInputs DO
+    // NONE AS SOON AS AN AMOUNT OR THE RUNNING TOTAL LEAVES MoneyRange
+    pub fn money_sum(amounts: impl IntoIterator<Item = Amount>) -> Option<Amount> {
+        amounts.into_iter().try_fold(Amount::ZERO, |total, amount| {
+            if !amount.is_money_range() {
+                return None;
+            }
+            total
+                .checked_add(amount)
+                .filter(|total| total.is_money_range())
+        })
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} SATS", self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn amount_test() {
+        let one = Amount::from_sat(1);
+        let max = Amount::MAX_MONEY;
+        assert_eq!(max.to_sat(), 2_100_000_000_000_000);
+
+        assert!(Amount::ZERO.is_money_range() && max.is_money_range());
+        assert!(!max.saturating_add(one).is_money_range());
+
+        // NO PANIC OR WRAP WHEN THE OUTPUTS ARE MORE THAN THE INPUTS
+        assert_eq!(one.checked_sub(max), None);
+        assert_eq!(Amount::from_sat(u64::MAX).checked_add(one), None);
+        assert_eq!(
+            Amount::from_sat(u64::MAX).saturating_add(one).to_sat(),
+            u64::MAX
+        );
+
+        // EVERY AMOUNT AND EVERY PARTIAL SUM MUST STAY IN RANGE
+        assert_eq!(Amount::money_sum([]), Some(Amount::ZERO));
+        assert_eq!(Amount::money_sum([max, Amount::ZERO]), Some(max));
+        assert_eq!(Amount::money_sum([max, one]), None);
+        assert_eq!(Amount::money_sum([Amount::from_sat(u64::MAX), one]), None);
+
+        assert_eq!(Amount::from_sat(1500).to_string(), "1500 SATS");
+    }
+}
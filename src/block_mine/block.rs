@@ -1,126 +1,76 @@
+use std::collections::HashSet;
+use std::env;
 use std::time::{SystemTime, UNIX_EPOCH};
-use std::{fs::File, io::Write};
+use std::{
+    fs::{self, File},
+    io::Write,
+};
 
+use failure::format_err;
 use num_bigint::BigUint;
 use num_traits::Num;
 
-use crate::{block_mine::serialise_tx::double_sha256, error::Result};
-
-use super::{merkle_root::generate_roots, serialise_tx::create_txid_tx_map};
-
-// COVERTS THE HEX REPRESENTATION TO COMPACT REPRESENTATION
-fn target_to_compact(target_hex: &str) -> u32 {
-    // Parse the target from a hex string to a big number
-    let target_bytes = hex::decode(target_hex).expect("Invalid hex string");
-    let mut target_bytes = target_bytes.as_slice();
-
-    // Trim leading zeros
-    while let Some(&0) = target_bytes.first() {
-        target_bytes = &target_bytes[1..];
-    }
-
-    // Prepare the compact format
-    let size = target_bytes.len() as u32;
-    let (exp, significant) = if size <= 3 {
-        (
-            size,
-            u32::from_be_bytes(
-                [0; 1]
-                    .iter()
-                    .chain(target_bytes.iter().chain(std::iter::repeat(&0)))
-                    .take(4)
-                    .cloned()
-                    .collect::<Vec<u8>>()
-                    .try_into()
-                    .unwrap(),
-            ),
-        )
-    } else {
-        let significant_bytes = &target_bytes[0..3]; // Take the first three significant bytes
-        let significant = u32::from_be_bytes(
-            [0; 1]
-                .iter()
-                .chain(significant_bytes.iter())
-                .cloned()
-                .collect::<Vec<u8>>()
-                .try_into()
-                .unwrap(),
-        );
-        (size, significant)
-    };
-
-    // Adjust for Bitcoin's compact format specification
-    let compact = if significant & 0x00800000 != 0 {
-        (significant >> 8) | ((exp + 1) << 24)
-    } else {
-        significant | (exp << 24)
-    };
-
-    compact
+use crate::{
+    address::address_to_script,
+    block_mine::serialise_tx::double_sha256,
+    error::Result,
+    hashes::{BlockHash, Txid},
+    network::Network,
+    script::Script,
+    transaction::Transaction,
+    utxo::{UtxoSet, MEMPOOL_TIP},
+    validation_checks::locktime::BlockContext,
+};
+
+use super::{
+    difficulty::{compact_to_target, target_to_compact},
+    merkle_root::generate_roots,
+    serialise_tx::{compact_size, create_txid_tx_map, serialise_tx, MempoolEntry},
+};
+
+// DIFFICULTY TARGET OF THE CHALLENGE
+pub const TARGET: &str = "0000ffff00000000000000000000000000000000000000000000000000000000";
+
+// HEIGHT COMMITTED TO IN THE COINBASE OF THE FIRST MINED BLOCK
+pub const BLOCK_HEIGHT: u32 = 900000;
+
+pub const BLOCK_VERSION: u32 = 4;
+
+// WHERE THE COINBASE SENDS THE SUBSIDY AND FEES UNLESS THE PAYOUT_ADDRESS ENV VAR IS SET
+pub const PAYOUT_ADDRESS: &str = "1Nh7uHdvY6fNwtQtM1G5EZAFPLC33B59rB";
+
+// SCRIPT PUB KEY OF THE CONFIGURED MAINNET PAYOUT ADDRESS
+pub fn payout_script() -> Result<Script> {
+    let address = env::var("PAYOUT_ADDRESS").unwrap_or_else(|_| PAYOUT_ADDRESS.to_string());
+    address_to_script(&address, Network::Mainnet)
 }
 
 // CREATE A VAALID BLOCK HEADER USING PROOF OF WORK
 pub fn valid_block_header() -> Result<()> {
-    // VERSION
-    let version_int: u32 = 4;
-    let version = hex::encode(version_int.to_le_bytes());
-
     // PREVIOUS BLOCK HASH
-    let prev_block_hash =
-        "0000000000000000000000000000000000000000000000000000000000000000".to_string();
-
-    let map = create_txid_tx_map()?;
-    let (merkel_root, coinbase_tx, _, txids) = generate_roots(map.clone())?;
+    let prev_block_hash = BlockHash::all_zeros();
 
     // TIME STAMP
-    let current_time = SystemTime::now();
-    let since_epoch = current_time.duration_since(UNIX_EPOCH).unwrap();
-    let time_stamp_int = since_epoch.as_secs() as u32;
-    let time_stamp = hex::encode(time_stamp_int.to_le_bytes());
-
-    // TARGET BITS
-    let target = "0000ffff00000000000000000000000000000000000000000000000000000000";
-    let target_int = BigUint::from_str_radix(target, 16).expect("INVALID HEX IN THE BLOCK");
-    let bits = target_to_compact(target);
-    let bits_hex = format!("{:08x}", bits);
-    let mut bits_in_bytes = hex::decode(&bits_hex)?;
-    bits_in_bytes.reverse();
-    let bits_le = hex::encode(bits_in_bytes);
-
-    // NONCE
-    let mut nonce: u32 = 0;
-
-    let valid_block_header: String;
-
-    // POW LOGIC
-    loop {
-        let nonce_hex = hex::encode(nonce.to_le_bytes());
-
-        let mut block_header: String = String::new();
-
-        block_header.push_str(&version);
-        block_header.push_str(&prev_block_hash);
-        block_header.push_str(&merkel_root);
-        block_header.push_str(&time_stamp);
-        block_header.push_str(&bits_le);
-        block_header.push_str(&nonce_hex);
-
-        let mut block_hash_bytes = double_sha256(&hex::decode(&block_header)?);
-        block_hash_bytes.reverse();
+    let time_stamp = current_time();
 
-        let block_hash = hex::encode(block_hash_bytes);
+    // NO EARLIER BLOCKS ARE KNOWN, SO TIME LOCKS ARE CHECKED AGAINST THE BLOCK'S OWN TIME
+    let context = BlockContext::new(BLOCK_HEIGHT, time_stamp);
 
-        let block_hash_int =
-            BigUint::from_str_radix(&block_hash, 16).expect("Invalid hex in block hash");
+    let map = create_txid_tx_map()?;
+    let utxos = UtxoSet::from_mempool(
+        map.iter().map(|(txid, tx, _, _, _)| (txid, tx)),
+        MEMPOOL_TIP,
+    );
+    let (merkel_root, coinbase_tx, _, txids) =
+        generate_roots(map.clone(), &context, &utxos, &payout_script()?)?;
+    let (block_txs, _) = take_block_txs(map, &txids);
 
-        if block_hash_int <= target_int {
-            println!("Valid nonce found: {}", nonce);
-            valid_block_header = block_header;
-            break;
-        }
+    // TARGET BITS
+    let target_int = BigUint::from_str_radix(TARGET, 16).expect("INVALID HEX IN THE BLOCK");
+    let bits = target_to_compact(&target_int);
 
-        nonce += 1;
-    }
+    let (valid_block_header, _) =
+        mine_block_header(&prev_block_hash, &merkel_root, time_stamp, bits)?;
 
     // BLOCK HEADER
     // COINBASE TX
@@ -139,5 +89,140 @@ pub fn valid_block_header() -> Result<()> {
         writeln!(block_file, "{}", txid)?;
     }
 
+    // THE FULL BLOCK NEXT TO OUTPUT.TXT, AS HEX AND AS RAW BYTES
+    let raw_block = serialise_block(&valid_block_header, &coinbase_tx, &block_txs)?;
+
+    fs::write("./block.hex", hex::encode(&raw_block))?;
+    fs::write("./block.dat", raw_block)?;
+
     Ok(())
 }
+
+// SPLITS THE MEMPOOL INTO THE TXS CONFIRMED BY A BLOCK (IN BLOCK ORDER) AND THE REMAINING ENTRIES
+pub fn take_block_txs(
+    map: Vec<MempoolEntry>,
+    txids: &[Txid],
+) -> (Vec<Transaction>, Vec<MempoolEntry>) {
+    // THE FIRST TXID IS THE COINBASE
+    let confirmed: HashSet<&Txid> = txids.iter().skip(1).collect();
+
+    let (block_txs, remaining): (Vec<_>, Vec<_>) = map
+        .into_iter()
+        .partition(|(txid, _, _, _, _)| confirmed.contains(txid));
+
+    let txs = block_txs.into_iter().map(|(_, tx, _, _, _)| tx).collect();
+
+    (txs, remaining)
+}
+
+// RUNS THE POW LOOP OVER THE NONCE UNTIL THE HEADER HASH MEETS THE TARGET ENCODED IN BITS
+// MERKEL ROOT IS IN HEADER BYTE ORDER
+// RETURNS THE HEADER AND ITS BLOCK HASH
+pub fn mine_block_header(
+    prev_block_hash: &BlockHash,
+    merkel_root: &str,
+    time_stamp: u32,
+    bits: u32,
+) -> Result<(String, BlockHash)> {
+    let target_int = compact_to_target(bits).ok_or(format_err!("INVALID BITS: {:08x}", bits))?;
+
+    // EVERYTHING EXCEPT THE NONCE STAYS THE SAME DURING THE LOOP
+    let mut header_prefix: String = String::new();
+
+    header_prefix.push_str(&hex::encode(BLOCK_VERSION.to_le_bytes()));
+    header_prefix.push_str(&prev_block_hash.to_internal_hex());
+    header_prefix.push_str(merkel_root);
+    header_prefix.push_str(&hex::encode(time_stamp.to_le_bytes()));
+    header_prefix.push_str(&hex::encode(bits.to_le_bytes()));
+
+    // POW LOGIC
+    for nonce in 0..=u32::MAX {
+        let nonce_hex = hex::encode(nonce.to_le_bytes());
+
+        let block_header = format!("{}{}", header_prefix, nonce_hex);
+
+        let block_hash = BlockHash::from_hash(&double_sha256(&hex::decode(&block_header)?))?;
+
+        let block_hash_int = BigUint::from_bytes_be(&block_hash.to_display_bytes());
+
+        if block_hash_int <= target_int {
+            println!("Valid nonce found: {}", nonce);
+            return Ok((block_header, block_hash));
+        }
+    }
+
+    Err(format_err!("NONCE SPACE EXHAUSTED"))
+}
+
+// CONSENSUS SERIALISATION OF A FULL BLOCK: HEADER, TX COUNT, COINBASE AND ALL TXS WITH WITNESSES
+pub fn serialise_block(
+    block_header: &str,
+    coinbase_tx: &str,
+    txs: &[Transaction],
+) -> Result<Vec<u8>> {
+    let mut raw_block: Vec<u8> = Vec::new();
+
+    raw_block.extend(hex::decode(block_header)?);
+    raw_block.extend(compact_size(txs.len() as u64 + 1));
+    raw_block.extend(hex::decode(coinbase_tx)?);
+
+    for tx in txs {
+        let (result, _, raw_wtx, _, _) = serialise_tx(tx)?;
+        if !result {
+            return Err(format_err!("SERIALISATION: FAILED"));
+        }
+        raw_block.extend(raw_wtx);
+    }
+
+    Ok(raw_block)
+}
+
+// UNIX TIME IN SECONDS
+pub fn current_time() -> u32 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    since_epoch.as_secs() as u32
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::hashes::Wtxid;
+
+    use super::*;
+
+    #[test]
+    fn serialise_block_test() -> Result<()> {
+        let path =
+            "./valid-mempool/001035505afbf143e51bd667099190943a38eee20092bb691e72eaa44992b2f7.json";
+        let transaction: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let (_, _, raw_wtx, _, _) = serialise_tx(&transaction)?;
+
+        let map = vec![(
+            Txid::all_zeros(),
+            transaction.clone(),
+            Wtxid::all_zeros(),
+            0,
+            0,
+        )];
+        let context = BlockContext::new(BLOCK_HEIGHT, current_time());
+        let utxos = UtxoSet::from_mempool([(&map[0].0, &map[0].1)], MEMPOOL_TIP);
+        let (_, coinbase_tx, _, txids) =
+            generate_roots(map.clone(), &context, &utxos, &payout_script()?)?;
+        let (block_txs, remaining) = take_block_txs(map, &txids);
+        assert_eq!(block_txs.len(), 1);
+        assert!(remaining.is_empty());
+
+        let header = "00".repeat(80);
+        let raw_block = serialise_block(&header, &coinbase_tx, &block_txs)?;
+
+        // HEADER, TX COUNT, COINBASE, THEN THE TX WITH ITS WITNESS
+        let coinbase_len = coinbase_tx.len() / 2;
+        assert_eq!(raw_block.len(), 80 + 1 + coinbase_len + raw_wtx.len());
+        assert_eq!(raw_block[80], 2);
+        assert_eq!(hex::encode(&raw_block[81..81 + coinbase_len]), coinbase_tx);
+        assert_eq!(&raw_block[81 + coinbase_len..], &raw_wtx[..]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use std::fs::{self, File};
+use std::io::Write;
+
+use num_bigint::BigUint;
+use num_traits::Num;
+
+use crate::error::Result;
+use crate::hashes::BlockHash;
+use crate::network::{ConsensusParams, Network};
+use crate::utxo::{UtxoSet, MEMPOOL_TIP};
+use crate::validation_checks::locktime::BlockContext;
+
+use super::{
+    block::{
+        current_time, mine_block_header, payout_script, serialise_block, take_block_txs,
+        BLOCK_HEIGHT, TARGET,
+    },
+    difficulty::{next_work_required, target_to_compact, HeaderSummary},
+    merkle_root::generate_roots,
+    serialise_tx::create_txid_tx_map,
+};
+
+// WHERE THE MINED BLOCKS AND THE HEADERS FILE ARE WRITTEN
+pub const CHAIN_DIR: &str = "./chain";
+
+// NUMBER OF BLOCKS THE MEDIAN TIME PAST IS TAKEN OVER
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+// MAINNET RETARGETING, BUT THE CHALLENGE TARGET IS THE EASIEST ALLOWED
+pub fn chain_params() -> ConsensusParams {
+    let target_int = BigUint::from_str_radix(TARGET, 16).expect("INVALID HEX IN THE BLOCK");
+
+    ConsensusParams {
+        pow_limit_bits: target_to_compact(&target_int),
+        ..ConsensusParams::for_network(Network::Mainnet)
+    }
+}
+
+// MEDIAN TIMESTAMP OF THE LAST 11 BLOCKS, A NEW BLOCK'S TIMESTAMP MUST BE GREATER
+pub fn median_time_past(chain: &[HeaderSummary]) -> u32 {
+    let mut times: Vec<u32> = chain
+        .iter()
+        .rev()
+        .take(MEDIAN_TIME_SPAN)
+        .map(|header| header.time)
+        .collect();
+
+    if times.is_empty() {
+        return 0;
+    }
+
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+// BIP113: TIME LOCKS ARE CHECKED AGAINST THE MEDIAN TIME PAST
+// WITH NO ANCESTORS TO TAKE A MEDIAN OF, THE BLOCK'S OWN TIMESTAMP IS USED
+pub fn lock_time_cutoff(chain: &[HeaderSummary], time_stamp: u32) -> u32 {
+    if chain.is_empty() {
+        return time_stamp;
+    }
+    median_time_past(chain)
+}
+
+// MINES `n_blocks` CONSECUTIVE BLOCKS, EACH ONE LINKED TO THE HASH OF THE PREVIOUS ONE
+// WRITES <CHAIN_DIR>/<HEIGHT>.dat FOR EVERY BLOCK AND ONE HEADER PER LINE IN headers.txt
+pub fn mine_chain(n_blocks: u32) -> Result<()> {
+    let params = chain_params();
+
+    let mut mempool = create_txid_tx_map()?;
+    let mut chain: Vec<HeaderSummary> = Vec::new();
+
+    // TXS OF EARLIER BLOCKS IN THE CHAIN BECOME CONFIRMED COINS FOR THEIR CHILDREN
+    let mut utxos = UtxoSet::from_mempool(
+        mempool.iter().map(|(txid, tx, _, _, _)| (txid, tx)),
+        MEMPOOL_TIP,
+    );
+
+    let mut prev_block_hash = BlockHash::all_zeros();
+    let payout_script = payout_script()?;
+
+    fs::create_dir_all(CHAIN_DIR)?;
+    let mut headers_file = File::create(format!("{}/headers.txt", CHAIN_DIR))?;
+
+    for height in BLOCK_HEIGHT..BLOCK_HEIGHT + n_blocks {
+        // THE TIMESTAMP HAS TO BE STRICTLY AFTER THE MEDIAN TIME PAST
+        let time_stamp = current_time().max(median_time_past(&chain) + 1);
+        let bits = next_work_required(&chain, time_stamp, &params);
+
+        let context = BlockContext::new(height, lock_time_cutoff(&chain, time_stamp));
+        let (merkel_root, coinbase_tx, _, txids) =
+            generate_roots(mempool.clone(), &context, &utxos, &payout_script)?;
+
+        let (block_header, block_hash) =
+            mine_block_header(&prev_block_hash, &merkel_root, time_stamp, bits)?;
+
+        // CONFIRMED TXS LEAVE THE MEMPOOL
+        let (block_txs, remaining) = take_block_txs(mempool, &txids);
+        mempool = remaining;
+
+        for (txid, tx) in txids.iter().skip(1).zip(block_txs.iter()) {
+            utxos.connect_tx(txid, tx, context);
+        }
+
+        let raw_block = serialise_block(&block_header, &coinbase_tx, &block_txs)?;
+
+        fs::write(format!("{}/{}.dat", CHAIN_DIR, height), raw_block)?;
+        writeln!(headers_file, "{}", block_header)?;
+
+        println!("BLOCK {}: {} ({} TXS)", height, block_hash, txids.len());
+
+        prev_block_hash = block_hash;
+
+        chain.push(HeaderSummary {
+            height,
+            time: time_stamp,
+            bits,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn median_time_past_test() {
+        assert_eq!(median_time_past(&[]), 0);
+
+        // ONLY THE LAST 11 BLOCKS COUNT, OUT OF ORDER TIMESTAMPS ARE SORTED
+        let chain: Vec<HeaderSummary> = [5, 1, 9, 3, 7, 2, 8, 4, 6, 10, 11, 100]
+            .iter()
+            .enumerate()
+            .map(|(height, time)| HeaderSummary {
+                height: height as u32,
+                time: *time,
+                bits: 0x1f00ffff,
+            })
+            .collect();
+
+        assert_eq!(median_time_past(&chain), 7);
+        assert_eq!(median_time_past(&chain[..3]), 5);
+    }
+
+    #[test]
+    fn chain_params_test() {
+        assert_eq!(chain_params().pow_limit_bits, 0x1f00ffff);
+    }
+}
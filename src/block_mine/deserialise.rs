@@ -0,0 +1,257 @@
+use failure::format_err;
+
+use crate::{
+    error::Result,
+    hashes::{OutPoint, Txid, Wtxid},
+};
+
+use super::serialise_tx::double_sha256;
+
+#[derive(Debug, Clone)]
+pub struct RawInput {
+    pub previous_output: OutPoint,
+    pub script_sig: Vec<u8>,
+    pub sequence: u32,
+    pub witness: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RawOutput {
+    pub value: u64,
+    pub script_pubkey: Vec<u8>,
+}
+
+// A TRANSACTION DECODED FROM ITS CONSENSUS SERIALISATION
+#[derive(Debug, Clone)]
+pub struct RawTransaction {
+    pub version: i32,
+    pub inputs: Vec<RawInput>,
+    pub outputs: Vec<RawOutput>,
+    pub locktime: u32,
+    pub txid: Txid,
+    pub wtxid: Wtxid,
+    pub weight: usize,
+    // FULL SERIALISATION INCLUDING THE WITNESS
+    pub raw: Vec<u8>,
+}
+
+impl RawTransaction {
+    pub fn is_coinbase(&self) -> bool {
+        self.inputs.len() == 1 && self.inputs[0].previous_output.is_null()
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+}
+
+// CURSOR OVER THE RAW BYTES
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes, position: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    pub(crate) fn read(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(n)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(format_err!("UNEXPECTED END OF DATA"))?;
+
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read(1)?[0])
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read(4)?.try_into()?))
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.read(8)?.try_into()?))
+    }
+
+    pub(crate) fn read_compact_size(&mut self) -> Result<u64> {
+        let n = match self.read_u8()? {
+            0xfd => u16::from_le_bytes(self.read(2)?.try_into()?) as u64,
+            0xfe => self.read_u32()? as u64,
+            0xff => self.read_u64()?,
+            byte => byte as u64,
+        };
+        Ok(n)
+    }
+
+    // A LENGTH THAT CAN'T POSSIBLY FIT IN THE REMAINING BYTES IS REJECTED BEFORE ALLOCATING
+    pub(crate) fn read_length(&mut self) -> Result<usize> {
+        let n = self.read_compact_size()?;
+        if n > (self.bytes.len() - self.position) as u64 {
+            return Err(format_err!("LENGTH OUT OF RANGE: {}", n));
+        }
+        Ok(n as usize)
+    }
+
+    pub(crate) fn read_var_bytes(&mut self) -> Result<Vec<u8>> {
+        let n = self.read_length()?;
+        Ok(self.read(n)?.to_vec())
+    }
+}
+
+// DECODES A SINGLE SERIALISED TRANSACTION, WITH OR WITHOUT WITNESS
+pub fn deserialise_tx(bytes: &[u8]) -> Result<RawTransaction> {
+    let mut reader = ByteReader { bytes, position: 0 };
+    let tx = read_tx(&mut reader)?;
+
+    if reader.position != bytes.len() {
+        return Err(format_err!("TRAILING BYTES AFTER TRANSACTION"));
+    }
+
+    Ok(tx)
+}
+
+// DECODES A SERIALISED BLOCK INTO ITS HEADER (HEX) AND ITS TRANSACTIONS
+pub fn deserialise_block(bytes: &[u8]) -> Result<(String, Vec<RawTransaction>)> {
+    let mut reader = ByteReader { bytes, position: 0 };
+
+    let block_header = hex::encode(reader.read(80)?);
+
+    let tx_count = reader.read_length()?;
+    let mut txs = Vec::with_capacity(tx_count);
+    for _ in 0..tx_count {
+        txs.push(read_tx(&mut reader)?);
+    }
+
+    if reader.position != bytes.len() {
+        return Err(format_err!("TRAILING BYTES AFTER BLOCK"));
+    }
+
+    Ok((block_header, txs))
+}
+
+fn read_tx(reader: &mut ByteReader) -> Result<RawTransaction> {
+    let start = reader.position;
+
+    // VERSION
+    let version = reader.read_u32()? as i32;
+
+    // MARKER AND FLAG ONLY IN SEGWIT TXS
+    let segwit = reader.bytes.get(reader.position) == Some(&0x00)
+        && reader.bytes.get(reader.position + 1) == Some(&0x01);
+    if segwit {
+        reader.read(2)?;
+    }
+    let body_start = reader.position;
+
+    // INPUTS
+    let input_count = reader.read_length()?;
+    let mut inputs = Vec::with_capacity(input_count);
+    for _ in 0..input_count {
+        let txid = Txid::from_hash(reader.read(32)?)?;
+
+        inputs.push(RawInput {
+            previous_output: OutPoint::new(txid, reader.read_u32()?),
+            script_sig: reader.read_var_bytes()?,
+            sequence: reader.read_u32()?,
+            witness: Vec::new(),
+        });
+    }
+
+    // OUTPUTS
+    let output_count = reader.read_length()?;
+    let mut outputs = Vec::with_capacity(output_count);
+    for _ in 0..output_count {
+        outputs.push(RawOutput {
+            value: reader.read_u64()?,
+            script_pubkey: reader.read_var_bytes()?,
+        });
+    }
+    let body_end = reader.position;
+
+    // WITNESS
+    if segwit {
+        for input in inputs.iter_mut() {
+            let item_count = reader.read_length()?;
+            for _ in 0..item_count {
+                input.witness.push(reader.read_var_bytes()?);
+            }
+        }
+    }
+
+    // LOCKTIME
+    let locktime = reader.read_u32()?;
+
+    let raw = reader.bytes[start..reader.position].to_vec();
+
+    // THE TXID COMMITS TO THE TX WITHOUT MARKER, FLAG AND WITNESS
+    let mut stripped: Vec<u8> = Vec::new();
+    stripped.extend(&raw[..4]);
+    stripped.extend(&reader.bytes[body_start..body_end]);
+    stripped.extend(locktime.to_le_bytes());
+
+    let txid = Txid::from_hash(&double_sha256(&stripped))?;
+    let wtxid = Wtxid::from_hash(&double_sha256(&raw))?;
+
+    Ok(RawTransaction {
+        version,
+        inputs,
+        outputs,
+        locktime,
+        txid,
+        wtxid,
+        weight: stripped.len() * 3 + raw.len(),
+        raw,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::block_mine::serialise_tx::serialise_tx;
+    use crate::transaction::Transaction;
+
+    use super::*;
+
+    #[test]
+    fn deserialise_roundtrip_test() -> Result<()> {
+        for path in [
+            "./valid-mempool/001035505afbf143e51bd667099190943a38eee20092bb691e72eaa44992b2f7.json",
+            "./mempool/01f16e8312f9c882e869d31a3ab386b94a38f6091f7e947c6f2ed2b3389f4406.json",
+        ] {
+            let transaction: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+            let (_, raw_tx, raw_wtx, weight, _) = serialise_tx(&transaction)?;
+
+            let tx = deserialise_tx(&raw_wtx)?;
+
+            assert_eq!(tx.txid, Txid::from_hash(&double_sha256(&raw_tx))?);
+            assert_eq!(tx.weight, weight);
+            assert_eq!(tx.inputs.len(), transaction.vin.len());
+            assert_eq!(tx.inputs[0].previous_output, transaction.vin[0].outpoint());
+            assert_eq!(tx.outputs[0].value, transaction.vout[0].value);
+            assert_eq!(tx.locktime, transaction.locktime);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn deserialise_truncated_test() {
+        let coinbase = hex::decode("01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0100ffffffff0100f2052a010000000000000000").unwrap();
+
+        assert!(deserialise_tx(&coinbase).unwrap().is_coinbase());
+        assert!(deserialise_tx(&coinbase[..coinbase.len() - 1]).is_err());
+        assert!(deserialise_block(&coinbase).is_err());
+    }
+}
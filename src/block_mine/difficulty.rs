@@ -0,0 +1,335 @@
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+
+use crate::hashes::BlockHash;
+use crate::network::ConsensusParams;
+
+// THE PART OF A BLOCK HEADER THE RETARGET RULES LOOK AT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderSummary {
+    pub height: u32,
+    pub time: u32,
+    pub bits: u32,
+}
+
+// TESTNET4 (BIP94): FIRST BLOCK OF A PERIOD MAY NOT BE OLDER THAN ITS PARENT BY MORE THAN THIS
+pub const MAX_TIMEWARP: u32 = 600;
+
+// DECODES THE COMPACT "BITS" FIELD INTO THE FULL 256 BIT TARGET
+// RETURNS NONE FOR NEGATIVE OR OVERFLOWING ENCODINGS, WHICH ARE NEVER VALID TARGETS
+pub fn compact_to_target(bits: u32) -> Option<BigUint> {
+    let size = bits >> 24;
+    let word = bits & 0x007fffff;
+
+    let target = if size <= 3 {
+        BigUint::from(word >> (8 * (3 - size)))
+    } else {
+        BigUint::from(word) << (8 * (size - 3))
+    };
+
+    let negative = word != 0 && (bits & 0x00800000) != 0;
+    let overflow =
+        word != 0 && (size > 34 || (word > 0xff && size > 33) || (word > 0xffff && size > 32));
+
+    if negative || overflow {
+        return None;
+    }
+
+    Some(target)
+}
+
+// ENCODES A FULL TARGET INTO THE COMPACT "BITS" REPRESENTATION
+pub fn target_to_compact(target: &BigUint) -> u32 {
+    let mut size = target.to_bytes_be().len() as u32;
+    if target.is_zero() {
+        size = 0;
+    }
+
+    let mut compact = if size <= 3 {
+        (target.to_u64().unwrap_or(0) << (8 * (3 - size))) as u32
+    } else {
+        (target >> (8 * (size - 3))).to_u32().unwrap_or(0)
+    };
+
+    // THE SIGN BIT IS SET, SHIFT ONE BYTE TO KEEP THE TARGET POSITIVE
+    if compact & 0x00800000 != 0 {
+        compact >>= 8;
+        size += 1;
+    }
+
+    compact | (size << 24)
+}
+
+// BITS EXPECTED FOR THE BLOCK THAT EXTENDS `chain`
+// `chain` IS ORDERED BY HEIGHT AND ENDS AT THE CURRENT TIP
+pub fn next_work_required(
+    chain: &[HeaderSummary],
+    new_block_time: u32,
+    params: &ConsensusParams,
+) -> u32 {
+    let last = match chain.last() {
+        Some(last) => last,
+        None => return params.pow_limit_bits,
+    };
+
+    let interval = params.difficulty_adjustment_interval();
+
+    // ONLY CHANGE ONCE PER DIFFICULTY ADJUSTMENT INTERVAL
+    if (last.height + 1) % interval != 0 {
+        if params.pow_allow_min_difficulty_blocks {
+            // A BLOCK MORE THAN 20 MINUTES AFTER ITS PARENT MAY BE MINED AT THE POW LIMIT
+            if new_block_time as u64 > last.time as u64 + 2 * params.pow_target_spacing as u64 {
+                return params.pow_limit_bits;
+            }
+
+            // OTHERWISE RETURN THE BITS OF THE LAST BLOCK THAT WAS NOT A MIN DIFFICULTY BLOCK
+            for (index, header) in chain.iter().enumerate().rev() {
+                if index == 0
+                    || header.height % interval == 0
+                    || header.bits != params.pow_limit_bits
+                {
+                    return header.bits;
+                }
+            }
+        }
+        return last.bits;
+    }
+
+    // GO BACK 2015 BLOCKS, NOT 2016: THE SATOSHI OFF-BY-ONE IS PART OF CONSENSUS
+    let first = match ancestor(chain, last.height.saturating_sub(interval - 1)) {
+        Some(first) => first,
+        None => return last.bits,
+    };
+
+    calculate_next_work_required(last, first, params)
+}
+
+// THE RETARGET ITSELF, BETWEEN THE FIRST AND LAST BLOCK OF A PERIOD
+pub fn calculate_next_work_required(
+    last: &HeaderSummary,
+    first: &HeaderSummary,
+    params: &ConsensusParams,
+) -> u32 {
+    if params.pow_no_retargeting {
+        return last.bits;
+    }
+
+    // LIMIT THE ADJUSTMENT TO A FACTOR OF 4 IN EITHER DIRECTION
+    let timespan = params.pow_target_timespan as i64;
+    let actual_timespan = (last.time as i64 - first.time as i64).clamp(timespan / 4, timespan * 4);
+
+    // BIP94 RETARGETS FROM THE FIRST BLOCK OF THE PERIOD SO A MIN DIFFICULTY TIP CAN'T RESET IT
+    let base_bits = if params.enforce_bip94 {
+        first.bits
+    } else {
+        last.bits
+    };
+
+    let pow_limit = compact_to_target(params.pow_limit_bits).unwrap_or_default();
+    let mut target = compact_to_target(base_bits).unwrap_or_else(|| pow_limit.clone());
+
+    target *= actual_timespan as u64;
+    target /= timespan as u64;
+
+    if target > pow_limit {
+        target = pow_limit;
+    }
+
+    target_to_compact(&target)
+}
+
+// BIP94: THE FIRST BLOCK OF A RETARGET PERIOD MAY NOT GO BACK MORE THAN 600 SECONDS
+pub fn check_timewarp(
+    chain: &[HeaderSummary],
+    new_block_time: u32,
+    params: &ConsensusParams,
+) -> bool {
+    let last = match chain.last() {
+        Some(last) => last,
+        None => return true,
+    };
+
+    if !params.enforce_bip94 || (last.height + 1) % params.difficulty_adjustment_interval() != 0 {
+        return true;
+    }
+
+    new_block_time as u64 + MAX_TIMEWARP as u64 >= last.time as u64
+}
+
+// CHECKS THAT A BLOCK HASH MEETS ITS BITS AND THE BITS ARE IN RANGE
+pub fn check_proof_of_work(block_hash: &BlockHash, bits: u32, params: &ConsensusParams) -> bool {
+    let target = match compact_to_target(bits) {
+        Some(target) => target,
+        None => return false,
+    };
+
+    let pow_limit = compact_to_target(params.pow_limit_bits).unwrap_or_default();
+    if target.is_zero() || target > pow_limit {
+        return false;
+    }
+
+    // COMPARED AS A NUMBER, THE DISPLAY ORDER IS BIG ENDIAN
+    BigUint::from_bytes_be(&block_hash.to_display_bytes()) <= target
+}
+
+// CHECKS THE DIFFICULTY OF A NEW HEADER AGAINST THE CHAIN IT EXTENDS
+pub fn check_header_difficulty(
+    chain: &[HeaderSummary],
+    block_hash: &BlockHash,
+    time: u32,
+    bits: u32,
+    params: &ConsensusParams,
+) -> bool {
+    bits == next_work_required(chain, time, params)
+        && check_timewarp(chain, time, params)
+        && check_proof_of_work(block_hash, bits, params)
+}
+
+fn ancestor(chain: &[HeaderSummary], height: u32) -> Option<&HeaderSummary> {
+    let tip = chain.last()?;
+    let depth = tip.height.checked_sub(height)? as usize;
+
+    chain
+        .len()
+        .checked_sub(depth + 1)
+        .map(|index| &chain[index])
+        .filter(|header| header.height == height)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::network::{ConsensusParams, Network};
+
+    use super::*;
+
+    // A FULL PERIOD ENDING AT `last_height` WHERE EVERY BLOCK TOOK `spacing` SECONDS
+    fn period(last_height: u32, spacing: u32, bits: u32) -> Vec<HeaderSummary> {
+        (last_height - 2015..=last_height)
+            .map(|height| HeaderSummary {
+                height,
+                time: 1_600_000_000 + (height - (last_height - 2015)) * spacing,
+                bits,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compact_roundtrip_test() {
+        let target = BigUint::parse_bytes(
+            b"0000ffff00000000000000000000000000000000000000000000000000000000",
+            16,
+        )
+        .unwrap();
+
+        assert_eq!(target_to_compact(&target), 0x1f00ffff);
+        assert_eq!(compact_to_target(0x1f00ffff), Some(target));
+
+        assert_eq!(compact_to_target(0x01803456), None);
+        assert_eq!(compact_to_target(0xff123456), None);
+        assert_eq!(
+            compact_to_target(0x05009234),
+            Some(BigUint::from(0x92340000u64))
+        );
+        assert_eq!(target_to_compact(&BigUint::from(0x12u32)), 0x01120000);
+    }
+
+    #[test]
+    fn mainnet_retarget_test() {
+        let params = ConsensusParams::for_network(Network::Mainnet);
+
+        // BLOCK 32255 -> 32256, THE FIRST REAL DIFFICULTY INCREASE
+        let first = HeaderSummary {
+            height: 30240,
+            time: 1261130161,
+            bits: 0x1d00ffff,
+        };
+        let last = HeaderSummary {
+            height: 32255,
+            time: 1262152739,
+            bits: 0x1d00ffff,
+        };
+        assert_eq!(
+            calculate_next_work_required(&last, &first, &params),
+            0x1d00d86a
+        );
+
+        // NO CHANGE IN THE MIDDLE OF A PERIOD
+        let chain = period(4030, 6000, 0x1c05a3f4);
+        assert_eq!(next_work_required(&chain, 0, &params), 0x1c05a3f4);
+    }
+
+    #[test]
+    fn mainnet_clamp_test() {
+        let params = ConsensusParams::for_network(Network::Mainnet);
+
+        // BLOCKS 10 TIMES TOO FAST ARE CLAMPED TO A 4X INCREASE
+        let fast = period(4031, 60, 0x1c05a3f4);
+        let expected = target_to_compact(&(compact_to_target(0x1c05a3f4).unwrap() / 4u32));
+        assert_eq!(next_work_required(&fast, 0, &params), expected);
+
+        // BLOCKS TOO SLOW ARE CAPPED AT THE POW LIMIT
+        let slow = period(4031, 6000, 0x1d00ffff);
+        assert_eq!(next_work_required(&slow, 0, &params), 0x1d00ffff);
+    }
+
+    #[test]
+    fn testnet_min_difficulty_test() {
+        let params = ConsensusParams::for_network(Network::Testnet);
+        let mut chain = period(2100, 600, 0x1c05a3f4);
+        let tip_time = chain.last().unwrap().time;
+
+        // MORE THAN 20 MINUTES LATE: POW LIMIT
+        assert_eq!(
+            next_work_required(&chain, tip_time + 1201, &params),
+            0x1d00ffff
+        );
+
+        // AFTER A MIN DIFFICULTY BLOCK, THE REAL DIFFICULTY COMES BACK
+        chain.push(HeaderSummary {
+            height: 2101,
+            time: tip_time + 1201,
+            bits: 0x1d00ffff,
+        });
+        assert_eq!(
+            next_work_required(&chain, tip_time + 1300, &params),
+            0x1c05a3f4
+        );
+    }
+
+    #[test]
+    fn testnet4_bip94_test() {
+        let params = ConsensusParams::for_network(Network::Testnet4);
+        let mut chain = period(4031, 600, 0x1c05a3f4);
+
+        // THE PERIOD ENDS ON A MIN DIFFICULTY BLOCK, BIP94 STILL RETARGETS FROM THE FIRST BLOCK
+        chain.last_mut().unwrap().bits = 0x1d00ffff;
+        let bits = next_work_required(&chain, 0, &params);
+        let expected = compact_to_target(0x1c05a3f4).unwrap() * 2015u32 / 2016u32;
+        assert_eq!(bits, target_to_compact(&expected));
+
+        // TIMEWARP: FIRST BLOCK OF THE PERIOD MAY NOT BE OLDER THAN ITS PARENT BY > 600s
+        let tip_time = chain.last().unwrap().time;
+        assert!(check_timewarp(&chain, tip_time - 600, &params));
+        assert!(!check_timewarp(&chain, tip_time - 601, &params));
+    }
+
+    #[test]
+    fn proof_of_work_test() {
+        let params = ConsensusParams::for_network(Network::Regtest);
+        let mut hash = [0xffu8; 32];
+        let high = BlockHash::from_internal_bytes(hash);
+        assert!(!check_proof_of_work(&high, 0x207fffff, &params));
+
+        // THE MOST SIGNIFICANT BYTE IS THE LAST ONE IN INTERNAL ORDER
+        hash[31] = 0x00;
+        let low = BlockHash::from_internal_bytes(hash);
+        assert!(check_proof_of_work(&low, 0x207fffff, &params));
+
+        // BITS EASIER THAN THE POW LIMIT ARE INVALID
+        assert!(!check_proof_of_work(
+            &BlockHash::all_zeros(),
+            0x2100ffff,
+            &params
+        ));
+    }
+}
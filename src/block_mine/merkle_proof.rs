@@ -0,0 +1,398 @@
+use failure::format_err;
+
+use crate::{error::Result, hashes::Txid};
+
+use super::{
+    deserialise::{deserialise_block, ByteReader},
+    merkle_tree::{hash_pair, MerkleTree},
+    serialise_tx::compact_size,
+    verify_block::MAX_BLOCK_WEIGHT,
+};
+
+// SMALLEST POSSIBLE TX IS 60 BYTES, SO NO BLOCK CAN HOLD MORE TXS THAN THIS
+const MAX_BLOCK_TXS: u32 = (MAX_BLOCK_WEIGHT / (60 * 4)) as u32;
+
+// PATH FROM A TXID TO THE MERKEL ROOT, SIBLING HASHES IN INTERNAL ORDER
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerkleBranch {
+    pub txid: Txid,
+    pub index: u32,
+    pub hashes: Vec<[u8; 32]>,
+}
+
+// BIP37 PARTIAL MERKEL TREE, AS CARRIED IN A `merkleblock` MESSAGE
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartialMerkleTree {
+    pub total_txs: u32,
+    // INTERNAL ORDER
+    pub hashes: Vec<[u8; 32]>,
+    pub flags: Vec<bool>,
+}
+
+// BUILDS THE BRANCH FOR `txid` FROM THE BLOCK'S TXIDS (COINBASE FIRST)
+pub fn merkle_branch(txids: &[Txid], txid: &Txid) -> Result<MerkleBranch> {
+    let position = txids
+        .iter()
+        .position(|block_txid| block_txid == txid)
+        .ok_or(format_err!("TXID NOT IN BLOCK: {}", txid))?;
+
+    let hashes = MerkleTree::from_txids(txids)?.branch(position)?;
+
+    Ok(MerkleBranch {
+        txid: *txid,
+        index: position as u32,
+        hashes,
+    })
+}
+
+// HASHES THE TXID UP THE BRANCH AND COMPARES WITH THE MERKEL ROOT (HEADER BYTE ORDER HEX)
+pub fn verify_merkle_branch(branch: &MerkleBranch, merkel_root: &str) -> bool {
+    let mut hash = *branch.txid.as_internal_bytes();
+    let mut index = branch.index;
+
+    for sibling in branch.hashes.iter() {
+        // THE LOWEST BIT OF THE INDEX SAYS WHICH SIDE WE ARE ON AT EACH LEVEL
+        hash = if index & 1 == 0 {
+            hash_pair(&hash, sibling)
+        } else {
+            hash_pair(sibling, &hash)
+        };
+        index >>= 1;
+    }
+
+    // AN INDEX WITH BITS LEFT OVER CLAIMS A POSITION THE BRANCH DOESN'T REACH
+    index == 0 && hex::encode(hash) == merkel_root
+}
+
+impl PartialMerkleTree {
+    // NUMBER OF NODES AT `height`, WHERE THE TXIDS ARE AT HEIGHT 0
+    fn width(&self, height: u32) -> u32 {
+        (self.total_txs + (1 << height) - 1) >> height
+    }
+
+    fn tree_height(&self) -> u32 {
+        let mut height = 0;
+        while self.width(height) > 1 {
+            height += 1;
+        }
+        height
+    }
+
+    // CPartialMerkleTree CONSTRUCTOR: KEEPS ONLY WHAT IS NEEDED TO PROVE THE MATCHED TXIDS
+    pub fn build(txids: &[Txid], matches: &[bool]) -> Result<Self> {
+        if txids.is_empty() || txids.len() != matches.len() {
+            return Err(format_err!(
+                "TXIDS AND MATCHES MUST BE NON-EMPTY AND OF EQUAL LENGTH"
+            ));
+        }
+
+        let leaves: Vec<[u8; 32]> = txids.iter().map(|txid| *txid.as_internal_bytes()).collect();
+
+        let mut tree = PartialMerkleTree {
+            total_txs: leaves.len() as u32,
+            hashes: Vec::new(),
+            flags: Vec::new(),
+        };
+
+        let height = tree.tree_height();
+        tree.traverse_and_build(height, 0, &leaves, matches);
+
+        Ok(tree)
+    }
+
+    fn calc_hash(&self, height: u32, pos: u32, leaves: &[[u8; 32]]) -> [u8; 32] {
+        if height == 0 {
+            return leaves[pos as usize];
+        }
+
+        let left = self.calc_hash(height - 1, pos * 2, leaves);
+        let right = if pos * 2 + 1 < self.width(height - 1) {
+            self.calc_hash(height - 1, pos * 2 + 1, leaves)
+        } else {
+            left
+        };
+
+        hash_pair(&left, &right)
+    }
+
+    fn traverse_and_build(&mut self, height: u32, pos: u32, leaves: &[[u8; 32]], matches: &[bool]) {
+        // DOES THIS NODE HAVE A MATCHED TXID BELOW IT
+        let start = (pos << height) as usize;
+        let end = (((pos + 1) << height) as usize).min(leaves.len());
+        let parent_of_match = matches[start..end].iter().any(|matched| *matched);
+
+        self.flags.push(parent_of_match);
+
+        if height == 0 || !parent_of_match {
+            let hash = self.calc_hash(height, pos, leaves);
+            self.hashes.push(hash);
+        } else {
+            self.traverse_and_build(height - 1, pos * 2, leaves, matches);
+            if pos * 2 + 1 < self.width(height - 1) {
+                self.traverse_and_build(height - 1, pos * 2 + 1, leaves, matches);
+            }
+        }
+    }
+
+    // RETURNS THE MERKEL ROOT (HEADER BYTE ORDER HEX) AND THE MATCHED (INDEX, TXID) PAIRS
+    pub fn extract_matches(&self) -> Result<(String, Vec<(u32, Txid)>)> {
+        if self.total_txs == 0 || self.total_txs > MAX_BLOCK_TXS {
+            return Err(format_err!("INVALID TX COUNT: {}", self.total_txs));
+        }
+
+        // EVERY HASH NEEDS AT LEAST ONE FLAG BIT, AND THERE ARE NEVER MORE HASHES THAN TXS
+        if self.hashes.len() > self.total_txs as usize || self.flags.len() < self.hashes.len() {
+            return Err(format_err!("TOO MANY HASHES IN PARTIAL MERKEL TREE"));
+        }
+
+        let mut bits_used = 0;
+        let mut hashes_used = 0;
+        let mut matches: Vec<(u32, Txid)> = Vec::new();
+
+        let root = self.traverse_and_extract(
+            self.tree_height(),
+            0,
+            &mut bits_used,
+            &mut hashes_used,
+            &mut matches,
+        )?;
+
+        // ALL FLAG BYTES AND ALL HASHES MUST BE CONSUMED
+        if bits_used.div_ceil(8) != self.flags.len().div_ceil(8) || hashes_used != self.hashes.len()
+        {
+            return Err(format_err!("UNUSED DATA IN PARTIAL MERKEL TREE"));
+        }
+
+        Ok((hex::encode(root), matches))
+    }
+
+    fn traverse_and_extract(
+        &self,
+        height: u32,
+        pos: u32,
+        bits_used: &mut usize,
+        hashes_used: &mut usize,
+        matches: &mut Vec<(u32, Txid)>,
+    ) -> Result<[u8; 32]> {
+        let parent_of_match = *self
+            .flags
+            .get(*bits_used)
+            .ok_or(format_err!("PARTIAL MERKEL TREE RAN OUT OF FLAGS"))?;
+        *bits_used += 1;
+
+        if height == 0 || !parent_of_match {
+            let hash = *self
+                .hashes
+                .get(*hashes_used)
+                .ok_or(format_err!("PARTIAL MERKEL TREE RAN OUT OF HASHES"))?;
+            *hashes_used += 1;
+
+            if height == 0 && parent_of_match {
+                matches.push((pos, Txid::from_internal_bytes(hash)));
+            }
+            return Ok(hash);
+        }
+
+        let left =
+            self.traverse_and_extract(height - 1, pos * 2, bits_used, hashes_used, matches)?;
+        let right = if pos * 2 + 1 < self.width(height - 1) {
+            let right = self.traverse_and_extract(
+                height - 1,
+                pos * 2 + 1,
+                bits_used,
+                hashes_used,
+                matches,
+            )?;
+            // IDENTICAL SIBLINGS WOULD LET TWO DIFFERENT TREES SHARE A ROOT (CVE-2012-2459)
+            if right == left {
+                return Err(format_err!("DUPLICATE HASHES IN PARTIAL MERKEL TREE"));
+            }
+            right
+        } else {
+            left
+        };
+
+        Ok(hash_pair(&left, &right))
+    }
+
+    // TX COUNT, HASHES AND FLAG BITS PACKED LEAST SIGNIFICANT BIT FIRST
+    pub fn serialise(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        bytes.extend(self.total_txs.to_le_bytes());
+
+        bytes.extend(compact_size(self.hashes.len() as u64));
+        for hash in self.hashes.iter() {
+            bytes.extend(hash);
+        }
+
+        let mut flag_bytes = vec![0u8; self.flags.len().div_ceil(8)];
+        for (i, flag) in self.flags.iter().enumerate() {
+            flag_bytes[i / 8] |= (*flag as u8) << (i % 8);
+        }
+        bytes.extend(compact_size(flag_bytes.len() as u64));
+        bytes.extend(flag_bytes);
+
+        bytes
+    }
+
+    fn read(reader: &mut ByteReader) -> Result<Self> {
+        let total_txs = reader.read_u32()?;
+
+        let hash_count = reader.read_length()?;
+        let mut hashes: Vec<[u8; 32]> = Vec::with_capacity(hash_count);
+        for _ in 0..hash_count {
+            hashes.push(reader.read(32)?.try_into()?);
+        }
+
+        let flag_bytes = reader.read_var_bytes()?;
+        let flags = (0..flag_bytes.len() * 8)
+            .map(|i| flag_bytes[i / 8] & (1 << (i % 8)) != 0)
+            .collect();
+
+        Ok(PartialMerkleTree {
+            total_txs,
+            hashes,
+            flags,
+        })
+    }
+}
+
+// `merkleblock` PAYLOAD: THE 80 BYTE HEADER FOLLOWED BY THE PARTIAL MERKEL TREE
+pub fn merkle_block(block_header: &str, txids: &[Txid], matched: &[Txid]) -> Result<Vec<u8>> {
+    let matches: Vec<bool> = txids.iter().map(|txid| matched.contains(txid)).collect();
+    let tree = PartialMerkleTree::build(txids, &matches)?;
+
+    let mut bytes = hex::decode(block_header)?;
+    if bytes.len() != 80 {
+        return Err(format_err!("BLOCK HEADER MUST BE 80 BYTES"));
+    }
+    bytes.extend(tree.serialise());
+
+    Ok(bytes)
+}
+
+// PARSES A `merkleblock` PAYLOAD AND CHECKS THE TREE AGAINST THE HEADER'S MERKEL ROOT
+// RETURNS THE HEADER (HEX) AND THE MATCHED (INDEX, TXID) PAIRS
+pub fn parse_merkle_block(bytes: &[u8]) -> Result<(String, Vec<(u32, Txid)>)> {
+    let mut reader = ByteReader::new(bytes);
+
+    let block_header = reader.read(80)?;
+    let tree = PartialMerkleTree::read(&mut reader)?;
+
+    if !reader.is_empty() {
+        return Err(format_err!("TRAILING BYTES AFTER MERKLEBLOCK"));
+    }
+
+    let (root, matches) = tree.extract_matches()?;
+    if root != hex::encode(&block_header[36..68]) {
+        return Err(format_err!("PARTIAL MERKEL TREE DOES NOT MATCH THE HEADER"));
+    }
+
+    Ok((hex::encode(block_header), matches))
+}
+
+// ENTRY POINT OF `merkle-proof <TXID> [PATH]`: PRINTS THE BRANCH AND THE MERKLEBLOCK FOR ONE TX
+pub fn print_merkle_proof(raw_block: &[u8], txid: &Txid) -> Result<()> {
+    let (block_header, txs) = deserialise_block(raw_block)?;
+    let txids: Vec<Txid> = txs.into_iter().map(|tx| tx.txid).collect();
+
+    let branch = merkle_branch(&txids, txid)?;
+    let merkel_root = &block_header[72..136];
+    if !verify_merkle_branch(&branch, merkel_root) {
+        return Err(format_err!("MERKEL BRANCH DOES NOT MATCH THE HEADER"));
+    }
+
+    println!("TXID: {}", branch.txid);
+    println!("INDEX: {}", branch.index);
+    for hash in branch.hashes.iter() {
+        println!("BRANCH: {}", hex::encode(hash));
+    }
+    println!(
+        "MERKLEBLOCK: {}",
+        hex::encode(merkle_block(&block_header, &txids, &[*txid])?)
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::{merkle_root::merkel_root, serialise_tx::double_sha256};
+    use super::*;
+
+    fn test_txids(n: u8) -> Vec<Txid> {
+        (0..n)
+            .map(|i| Txid::from_hash(&double_sha256(&[i])).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn merkle_branch_test() -> Result<()> {
+        for n in [1, 2, 5, 8, 11] {
+            let txids = test_txids(n);
+            let root = merkel_root(&txids)?;
+
+            for txid in txids.iter() {
+                let branch = merkle_branch(&txids, txid)?;
+                assert!(verify_merkle_branch(&branch, &root));
+
+                // WRONG POSITION, UNLESS THE SIBLING IS THE DUPLICATED LAST TXID ITSELF
+                let mut moved = branch.clone();
+                moved.index ^= 1;
+                if moved.index < n as u32 {
+                    assert!(!verify_merkle_branch(&moved, &root));
+                }
+            }
+        }
+
+        assert!(merkle_branch(&test_txids(3), &Txid::all_zeros()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn partial_merkle_tree_test() -> Result<()> {
+        let txids = test_txids(7);
+        let root = merkel_root(&txids)?;
+
+        let matches = vec![false, true, false, false, true, false, true];
+        let tree = PartialMerkleTree::build(&txids, &matches)?;
+
+        let bytes = tree.serialise();
+        let parsed = PartialMerkleTree::read(&mut ByteReader::new(&bytes))?;
+
+        let (extracted_root, matched) = parsed.extract_matches()?;
+        assert_eq!(extracted_root, root);
+        assert_eq!(matched, vec![(1, txids[1]), (4, txids[4]), (6, txids[6])]);
+
+        // ONE HASH TOO MANY
+        let mut padded = parsed.clone();
+        padded.hashes.push([0u8; 32]);
+        assert!(padded.extract_matches().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn merkle_block_test() -> Result<()> {
+        let txids = test_txids(4);
+        let root = merkel_root(&txids)?;
+
+        let mut header = vec![0u8; 80];
+        header[36..68].copy_from_slice(&hex::decode(&root)?);
+
+        let bytes = merkle_block(&hex::encode(&header), &txids, &[txids[2]])?;
+        let (parsed_header, matched) = parse_merkle_block(&bytes)?;
+
+        assert_eq!(parsed_header, hex::encode(&header));
+        assert_eq!(matched, vec![(2, txids[2])]);
+
+        // HEADER COMMITTING TO ANOTHER ROOT
+        let mut tampered = bytes.clone();
+        tampered[36] ^= 1;
+        assert!(parse_merkle_block(&tampered).is_err());
+
+        Ok(())
+    }
+}
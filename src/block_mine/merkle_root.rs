@@ -1,89 +1,149 @@
-use crate::error::Result;
+use std::collections::HashSet;
 
-use crate::transaction::Transaction;
+use failure::format_err;
 
-use super::serialise_tx::double_sha256;
+use crate::{
+    error::Result,
+    hashes::{Txid, Wtxid},
+    script::Script,
+    utxo::UtxoSource,
+    validation_checks::locktime::{is_final_tx, sequence_locks, BlockContext},
+};
+
+use super::{
+    merkle_tree::MerkleTree,
+    serialise_tx::{compact_size, double_sha256, MempoolEntry},
+};
 
 // RETURNS THE MERKEL ROOT, COINBASE TX, COINBASE TXID AND TXIDS TO BE INCLUDED IN THE BLOCK
+// TXS THAT ARE NOT FINAL OR STILL SEQUENCE LOCKED IN THE BLOCK OF `context`, AND THEIR
+// DESCENDANTS, ARE LEFT OUT
 pub fn generate_roots(
-    map: Vec<(String, Transaction, String, usize, u64)>,
-) -> Result<(String, String, String, Vec<String>)> {
+    map: Vec<MempoolEntry>,
+    context: &BlockContext,
+    utxos: &impl UtxoSource,
+    payout_script: &Script,
+) -> Result<(String, String, Txid, Vec<Txid>)> {
     let tx_weight_limit = 3993000;
     let mut current_tx_weight = 0;
-    let mut txids: Vec<String> = Vec::new();
-    let mut wtxids: Vec<String> = Vec::new();
-    let mut block_subsidy = 0;
-
-    wtxids.push("0000000000000000000000000000000000000000000000000000000000000000".to_string());
-
-    for (txid, _, wtxid, weight, fees) in map {
-        if current_tx_weight >= tx_weight_limit {
-            break;
+    let mut txids: Vec<Txid> = Vec::new();
+    let mut wtxids: Vec<Wtxid> = Vec::new();
+    let mut total_fees = 0;
+    let mut skipped: HashSet<Txid> = HashSet::new();
+
+    // THE COINBASE IS NOT BUILT YET, ITS LEAF IS FILLED IN ONCE IT IS
+    txids.push(Txid::all_zeros());
+    wtxids.push(Wtxid::all_zeros());
+
+    for (txid, tx, wtxid, weight, fees) in map {
+        // PARENTS COME FIRST, SO A SKIPPED PARENT IS ALREADY KNOWN WHEN ITS CHILD SHOWS UP
+        // A TX THAT WOULD TAKE THE BLOCK OVER THE LIMIT IS SKIPPED, A LIGHTER ONE CAN STILL FIT
+        let spends_skipped = tx.vin.iter().any(|input| skipped.contains(&input.txid));
+        let is_locked = !is_final_tx(&tx, context)
+            || !sequence_locks(&tx, utxos, context).is_satisfied(context);
+        let too_heavy = current_tx_weight + weight > tx_weight_limit;
+        if spends_skipped || is_locked || too_heavy {
+            skipped.insert(txid);
+            continue;
         }
         current_tx_weight += weight;
-        block_subsidy += fees;
+        total_fees += fees;
 
         txids.push(txid);
         wtxids.push(wtxid);
     }
 
-    let witness_root_hash = merkel_root(wtxids)?;
+    let witness_root_hash = MerkleTree::from_wtxids(&wtxids)?.root_hex();
+    let mut tx_tree = MerkleTree::from_txids(&txids)?;
 
-    let (coinbase_tx, txid_coinbase_tx) = create_coinbase(witness_root_hash, block_subsidy)?;
+    let (coinbase_tx, txid_coinbase_tx) =
+        create_coinbase(witness_root_hash, context.height, total_fees, payout_script)?;
 
-    let mut coinbase_txid_bytes = double_sha256(&hex::decode(&txid_coinbase_tx)?);
-    coinbase_txid_bytes.reverse();
+    let coinbase_txid = Txid::from_hash(&double_sha256(&hex::decode(&txid_coinbase_tx)?))?;
 
-    let coinbase_txid = hex::encode(coinbase_txid_bytes);
+    tx_tree.update_coinbase(&coinbase_txid)?;
+    txids[0] = coinbase_txid;
 
-    txids.insert(0, coinbase_txid.clone());
-
-    let merkel_root = merkel_root(txids.clone())?;
+    // A TEMPLATE WHOSE ROOT COULD BE REUSED BY A SHORTER TX LIST IS NEVER MINED
+    if tx_tree.is_mutated() {
+        return Err(format_err!(
+            "MUTATED MERKEL TREE: DUPLICATE TXIDS IN TEMPLATE"
+        ));
+    }
 
-    Ok((merkel_root, coinbase_tx, coinbase_txid, txids))
+    Ok((tx_tree.root_hex(), coinbase_tx, coinbase_txid, txids))
 }
 
 // FUNCTION TO CREATE THE MERKEL ROOT FOR A VECTOR OF TXIDS
-fn merkel_root(txids: Vec<String>) -> Result<String> {
-    let mut txids_natural: Vec<String> = Vec::new();
+pub fn merkel_root(txids: &[Txid]) -> Result<String> {
+    Ok(MerkleTree::from_txids(txids)?.root_hex())
+}
 
-    for txid in txids.iter() {
-        let mut txid_bytes = hex::decode(txid)?;
-        txid_bytes.reverse();
+// ComputeMerkleRoot: THE ROOT PLUS A FLAG SET WHEN TWO IDENTICAL HASHES ARE PAIRED AT ANY LEVEL
+// SUCH A LIST HAS THE SAME ROOT AS THE LIST WITHOUT ITS DUPLICATED TAIL (CVE-2012-2459)
+pub fn compute_merkel_root(txids: &[Txid]) -> Result<(String, bool)> {
+    let tree = MerkleTree::from_txids(txids)?;
+    Ok((tree.root_hex(), tree.is_mutated()))
+}
 
-        txids_natural.push(hex::encode(txid_bytes));
+// NEW COINS CREATED BY A BLOCK AT THIS HEIGHT, HALVING EVERY 210000 BLOCKS
+pub fn block_subsidy(block_height: u32) -> u64 {
+    let halvings = block_height / 210000;
+    if halvings >= 64 {
+        return 0;
     }
 
-    while txids_natural.len() > 1 {
-        let mut next_level = Vec::new();
+    5_000_000_000 >> halvings
+}
 
-        // IF ODD NUMBER OF TXID, DUPLICATE THE LAST ONE 
-        if txids_natural.len() % 2 != 0 {
-            txids_natural.push(txids_natural.last().unwrap().clone());
-        }
+// BIP34: THE COINBASE SCRIPT SIG STARTS WITH THE BLOCK HEIGHT AS A MINIMAL SCRIPT NUMBER PUSH
+fn coinbase_script_sig(block_height: u32) -> String {
+    let mut script_sig: Vec<u8> = Vec::new();
+
+    match block_height {
+        0 => script_sig.push(0x00),
+        1..=16 => script_sig.push(0x50 + block_height as u8),
+        _ => {
+            let mut height_bytes: Vec<u8> = Vec::new();
+            let mut height = block_height;
+            while height > 0 {
+                height_bytes.push((height & 0xff) as u8);
+                height >>= 8;
+            }
 
-        for chunk in txids_natural.chunks(2) {
-            match chunk {
-                [one, two] => {
-                    let concat = one.to_owned() + two;
-                    next_level.push(hex::encode(double_sha256(&hex::decode(&concat)?)));
-                }
-                _ => unreachable!(), // This case should never happen due to the duplication logic above
+            // KEEP THE NUMBER POSITIVE IF THE TOP BIT IS SET
+            if height_bytes.last().copied().unwrap_or(0) & 0x80 != 0 {
+                height_bytes.push(0x00);
             }
-        }
 
-        txids_natural = next_level;
+            script_sig.push(height_bytes.len() as u8);
+            script_sig.extend(height_bytes);
+        }
     }
 
-    Ok(txids_natural[0].clone())
+    // MINER TAG
+    script_sig.extend(
+        hex::decode("184d696e656420627920416e74506f6f6c373946205b8160a4256c0000946e0100")
+            .expect("DECODING: FAILED"),
+    );
+
+    hex::encode(script_sig)
 }
 
-// CREATE THE COINBASE TX AND COINBASE TXID
-pub fn create_coinbase(witness_root_hash: String, block_subsidy: u64) -> Result<(String, String)> {
+// CREATE THE COINBASE TX AND COINBASE TXID, PAYING THE SUBSIDY AND FEES TO `payout_script`
+pub fn create_coinbase(
+    witness_root_hash: String,
+    block_height: u32,
+    fees: u64,
+    payout_script: &Script,
+) -> Result<(String, String)> {
     let mut coinbase_tx = String::new();
     let mut txid_coinbase_tx = String::new();
 
-    let block_amount = 650082296 + block_subsidy;
+    let block_amount = block_subsidy(block_height) + fees;
+
+    let script_sig = coinbase_script_sig(block_height);
+    let script_sig_len = format!("{:02x}", script_sig.len() / 2);
 
     let witness_reserved_value =
         "0000000000000000000000000000000000000000000000000000000000000000".to_string();
@@ -93,6 +153,8 @@ pub fn create_coinbase(witness_root_hash: String, block_subsidy: u64) -> Result<
 
     let wtxid_commitment = format!("{}{}", "6a24aa21a9ed", wtxid_commit);
 
+    let payout_script_len = hex::encode(compact_size(payout_script.len() as u64));
+
     // VERSION MARKER FLAG
     coinbase_tx.push_str("01000000");
     txid_coinbase_tx.push_str("01000000");
@@ -106,9 +168,8 @@ pub fn create_coinbase(witness_root_hash: String, block_subsidy: u64) -> Result<
     // INPUT
     coinbase_tx.push_str("0000000000000000000000000000000000000000000000000000000000000000");
     coinbase_tx.push_str("ffffffff");
-    coinbase_tx.push_str("25");
-    coinbase_tx
-        .push_str("03a0bb0d184d696e656420627920416e74506f6f6c373946205b8160a4256c0000946e0100");
+    coinbase_tx.push_str(&script_sig_len);
+    coinbase_tx.push_str(&script_sig);
     coinbase_tx.push_str("ffffffff");
 
     // OUTPUT COUNT
@@ -116,8 +177,8 @@ pub fn create_coinbase(witness_root_hash: String, block_subsidy: u64) -> Result<
 
     // OUTPUT
     coinbase_tx.push_str(&hex::encode(block_amount.to_le_bytes()));
-    coinbase_tx.push_str("19");
-    coinbase_tx.push_str("76a914edf10a7fac6b32e24daa5305c723f3de58db1bc888ac");
+    coinbase_tx.push_str(&payout_script_len);
+    coinbase_tx.push_str(&payout_script.to_hex());
 
     coinbase_tx.push_str("0000000000000000");
     coinbase_tx.push_str("26");
@@ -128,9 +189,8 @@ pub fn create_coinbase(witness_root_hash: String, block_subsidy: u64) -> Result<
     // INPUT
     txid_coinbase_tx.push_str("0000000000000000000000000000000000000000000000000000000000000000");
     txid_coinbase_tx.push_str("ffffffff");
-    txid_coinbase_tx.push_str("25");
-    txid_coinbase_tx
-        .push_str("03a0bb0d184d696e656420627920416e74506f6f6c373946205b8160a4256c0000946e0100");
+    txid_coinbase_tx.push_str(&script_sig_len);
+    txid_coinbase_tx.push_str(&script_sig);
     txid_coinbase_tx.push_str("ffffffff");
 
     // OUTPUT COUNT
@@ -138,8 +198,8 @@ pub fn create_coinbase(witness_root_hash: String, block_subsidy: u64) -> Result<
 
     // OUTPUT
     txid_coinbase_tx.push_str(&hex::encode(block_amount.to_le_bytes()));
-    txid_coinbase_tx.push_str("19");
-    txid_coinbase_tx.push_str("76a914edf10a7fac6b32e24daa5305c723f3de58db1bc888ac");
+    txid_coinbase_tx.push_str(&payout_script_len);
+    txid_coinbase_tx.push_str(&payout_script.to_hex());
 
     txid_coinbase_tx.push_str("0000000000000000");
     txid_coinbase_tx.push_str("26");
@@ -164,20 +224,120 @@ pub fn create_coinbase(witness_root_hash: String, block_subsidy: u64) -> Result<
 #[cfg(test)]
 
 mod test {
+    use std::fs;
+
+    use crate::{
+        address::address_to_script,
+        block_mine::block::PAYOUT_ADDRESS,
+        network::Network,
+        transaction::Transaction,
+        utxo::{UtxoSet, MEMPOOL_TIP},
+    };
+
     use super::*;
 
+    #[test]
+    fn weight_limit_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let [a, b, c, d] = [1, 2, 3, 4].map(|byte| Txid::from_internal_bytes([byte; 32]));
+        let mut child = tx.clone();
+        child.vin[0].txid = b;
+
+        // B WOULD TAKE THE BLOCK OVER THE LIMIT, SO IT AND ITS CHILD C ARE SKIPPED BUT D FITS
+        let map: Vec<MempoolEntry> = vec![
+            (a, tx.clone(), Wtxid::all_zeros(), 3_000_000, 0),
+            (b, tx.clone(), Wtxid::all_zeros(), 1_000_000, 0),
+            (c, child, Wtxid::all_zeros(), 1_000, 0),
+            (d, tx, Wtxid::all_zeros(), 900_000, 0),
+        ];
+        let utxos = UtxoSet::from_mempool(
+            map.iter().map(|(txid, tx, _, _, _)| (txid, tx)),
+            MEMPOOL_TIP,
+        );
+        let context = BlockContext::new(MEMPOOL_TIP.height + 1, MEMPOOL_TIP.median_time_past);
+        let payout_script = address_to_script(PAYOUT_ADDRESS, Network::Mainnet)?;
+
+        let (_, _, _, txids) = generate_roots(map, &context, &utxos, &payout_script)?;
+        assert_eq!(txids[1..], [a, d]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn mutated_merkel_test() -> Result<()> {
+        let a: Txid = "2ec4532bbb79b5875f3e86cf11f3f1e42b74717c573368a92558cff7b1033365".parse()?;
+        let b: Txid = "958ffdb52a9148d3a6fca79d21d6b17e146c94909f6e63dd7723e409b10a1cd2".parse()?;
+        let c: Txid = "dbba5fdfee9cb36e4f80db9ed7daebaa1460f9836bb0328db2f9f2dc4cd02d14".parse()?;
+
+        let (root, mutated) = compute_merkel_root(&[a, b, c])?;
+        assert!(!mutated);
+
+        // THE DUPLICATED TAIL GIVES THE SAME ROOT BUT IS FLAGGED
+        let (mutated_root, mutated) = compute_merkel_root(&[a, b, c, c])?;
+        assert_eq!(mutated_root, root);
+        assert!(mutated);
+
+        // ONE LEVEL UP: [A B A B] PAIRS H(A,B) WITH ITSELF
+        let (_, mutated) = compute_merkel_root(&[a, b, a, b])?;
+        assert!(mutated);
+
+        // A LONE TXID IS ITS OWN ROOT AND NEVER MUTATED
+        let (_, mutated) = compute_merkel_root(&[c])?;
+        assert!(!mutated);
+
+        Ok(())
+    }
+
     #[test]
     fn merkel_test() -> Result<()> {
         let txids = vec![
-            "2ec4532bbb79b5875f3e86cf11f3f1e42b74717c573368a92558cff7b1033365".to_string(),
-            "958ffdb52a9148d3a6fca79d21d6b17e146c94909f6e63dd7723e409b10a1cd2".to_string(),
-            "dbba5fdfee9cb36e4f80db9ed7daebaa1460f9836bb0328db2f9f2dc4cd02d14".to_string(),
+            "2ec4532bbb79b5875f3e86cf11f3f1e42b74717c573368a92558cff7b1033365".parse()?,
+            "958ffdb52a9148d3a6fca79d21d6b17e146c94909f6e63dd7723e409b10a1cd2".parse()?,
+            "dbba5fdfee9cb36e4f80db9ed7daebaa1460f9836bb0328db2f9f2dc4cd02d14".parse()?,
         ];
 
-        let merkel_root = merkel_root(txids)?;
+        let merkel_root = merkel_root(&txids)?;
 
         println!("{}", merkel_root);
 
         Ok(())
     }
+
+    #[test]
+    fn coinbase_height_test() {
+        assert_eq!(
+            coinbase_script_sig(900000),
+            "03a0bb0d184d696e656420627920416e74506f6f6c373946205b8160a4256c0000946e0100"
+        );
+        assert!(coinbase_script_sig(128).starts_with("028000"));
+        assert!(coinbase_script_sig(16).starts_with("60"));
+
+        assert_eq!(block_subsidy(0), 5_000_000_000);
+        assert_eq!(block_subsidy(900000), 312_500_000);
+        assert_eq!(block_subsidy(64 * 210000), 0);
+    }
+
+    #[test]
+    fn coinbase_payout_test() -> Result<()> {
+        let witness_root = "00".repeat(32);
+
+        // THE DEFAULT PAYOUT IS THE P2PKH SCRIPT THE COINBASE USED TO HARD-CODE
+        let p2pkh = address_to_script(PAYOUT_ADDRESS, Network::Mainnet)?;
+        assert_eq!(
+            p2pkh.to_hex(),
+            "76a914edf10a7fac6b32e24daa5305c723f3de58db1bc888ac"
+        );
+        let (coinbase_tx, _) = create_coinbase(witness_root.clone(), 900000, 0, &p2pkh)?;
+        assert!(coinbase_tx.contains(&format!("19{}", p2pkh.to_hex())));
+
+        // A LONGER TAPROOT SCRIPT GETS ITS OWN LENGTH PREFIX IN BOTH SERIALISATIONS
+        let p2tr = Script::from_hex(&format!("5120{}", "11".repeat(32)))?;
+        let (coinbase_tx, txid_coinbase_tx) = create_coinbase(witness_root, 900000, 0, &p2tr)?;
+        assert!(coinbase_tx.contains(&format!("22{}", p2tr.to_hex())));
+        assert!(txid_coinbase_tx.contains(&format!("22{}", p2tr.to_hex())));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,210 @@
+use failure::format_err;
+
+use crate::{
+    error::Result,
+    hashes::{Txid, Wtxid},
+};
+
+use super::serialise_tx::double_sha256;
+
+// PARENT OF TWO NODES: DOUBLE SHA256 OF THEIR CONCATENATION
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut concat = [0u8; 64];
+    concat[..32].copy_from_slice(left);
+    concat[32..].copy_from_slice(right);
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&double_sha256(&concat));
+    hash
+}
+
+// MERKEL TREE OVER INTERNAL ORDER HASHES THAT KEEPS EVERY LEVEL
+// LEVELS ARE NOT PADDED: THE LAST NODE OF AN ODD LEVEL IS PAIRED WITH ITSELF WHEN HASHING
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerkleTree {
+    // LEVEL 0 ARE THE LEAVES, THE LAST LEVEL HOLDS ONLY THE ROOT
+    levels: Vec<Vec<[u8; 32]>>,
+    // NUMBER OF PAIRS OF IDENTICAL SIBLINGS OVER ALL LEVELS (CVE-2012-2459)
+    duplicate_pairs: usize,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Result<Self> {
+        if leaves.is_empty() {
+            return Err(format_err!("MERKEL TREE NEEDS AT LEAST ONE LEAF"));
+        }
+
+        let mut levels = vec![leaves];
+        let mut duplicate_pairs = 0;
+
+        while levels.last().unwrap().len() > 1 {
+            let level = levels.last().unwrap();
+
+            duplicate_pairs += level
+                .chunks_exact(2)
+                .filter(|pair| pair[0] == pair[1])
+                .count();
+
+            let next_level = level
+                .chunks(2)
+                .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+                .collect();
+
+            levels.push(next_level);
+        }
+
+        Ok(MerkleTree {
+            levels,
+            duplicate_pairs,
+        })
+    }
+
+    pub fn from_txids(txids: &[Txid]) -> Result<Self> {
+        MerkleTree::new(txids.iter().map(|txid| *txid.as_internal_bytes()).collect())
+    }
+
+    // THE WITNESS COMMITMENT TREE, WITH AN ALL ZERO WTXID FOR THE COINBASE
+    pub fn from_wtxids(wtxids: &[Wtxid]) -> Result<Self> {
+        MerkleTree::new(
+            wtxids
+                .iter()
+                .map(|wtxid| *wtxid.as_internal_bytes())
+                .collect(),
+        )
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.levels.last().unwrap()[0]
+    }
+
+    // ROOT AS IT GOES IN THE BLOCK HEADER
+    pub fn root_hex(&self) -> String {
+        hex::encode(self.root())
+    }
+
+    // TRUE IF ANY LEVEL PAIRS TWO IDENTICAL HASHES, LIKE ComputeMerkleRoot'S `mutated`
+    pub fn is_mutated(&self) -> bool {
+        self.duplicate_pairs > 0
+    }
+
+    pub fn leaves(&self) -> &[[u8; 32]] {
+        &self.levels[0]
+    }
+
+    // SIBLING HASHES FROM THE LEAF AT `index` UP TO THE ROOT
+    pub fn branch(&self, index: usize) -> Result<Vec<[u8; 32]>> {
+        if index >= self.levels[0].len() {
+            return Err(format_err!("LEAF INDEX {} OUT OF RANGE", index));
+        }
+
+        let mut branch: Vec<[u8; 32]> = Vec::new();
+        let mut index = index;
+
+        for level in self.levels[..self.levels.len() - 1].iter() {
+            let sibling = (index ^ 1).min(level.len() - 1);
+            branch.push(level[sibling]);
+            index /= 2;
+        }
+
+        Ok(branch)
+    }
+
+    // REPLACES ONE LEAF AND REHASHES ONLY ITS PATH TO THE ROOT
+    pub fn update_leaf(&mut self, index: usize, leaf: [u8; 32]) -> Result<()> {
+        if index >= self.levels[0].len() {
+            return Err(format_err!("LEAF INDEX {} OUT OF RANGE", index));
+        }
+
+        let mut index = index;
+        let mut hash = leaf;
+
+        for height in 0..self.levels.len() {
+            let was_duplicate = self.is_duplicate_pair(height, index);
+            self.levels[height][index] = hash;
+            let is_duplicate = self.is_duplicate_pair(height, index);
+
+            self.duplicate_pairs =
+                self.duplicate_pairs + is_duplicate as usize - was_duplicate as usize;
+
+            if height == self.levels.len() - 1 {
+                break;
+            }
+
+            let level = &self.levels[height];
+            let left = level[index & !1];
+            let right = *level.get(index | 1).unwrap_or(&left);
+
+            hash = hash_pair(&left, &right);
+            index /= 2;
+        }
+
+        Ok(())
+    }
+
+    // THE COINBASE IS ALWAYS LEAF 0, AND IT CHANGES WHEN THE EXTRANONCE ROLLS
+    pub fn update_coinbase(&mut self, coinbase_txid: &Txid) -> Result<()> {
+        self.update_leaf(0, *coinbase_txid.as_internal_bytes())
+    }
+
+    // A PAIR ONLY COUNTS WHEN BOTH SIBLINGS EXIST, NOT FOR THE LAST NODE PAIRED WITH ITSELF
+    fn is_duplicate_pair(&self, height: usize, index: usize) -> bool {
+        let level = &self.levels[height];
+        match level.get(index ^ 1) {
+            Some(sibling) => *sibling == level[index],
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_leaves(n: u8) -> Vec<[u8; 32]> {
+        (0..n)
+            .map(|i| double_sha256(&[i]).try_into().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn update_leaf_test() -> Result<()> {
+        for n in [1, 2, 3, 7, 16, 21] {
+            let leaves = test_leaves(n);
+            let mut tree = MerkleTree::new(leaves.clone())?;
+
+            // A NEW COINBASE GIVES THE SAME TREE AS BUILDING FROM SCRATCH
+            let mut new_leaves = leaves.clone();
+            new_leaves[0] = [0xab; 32];
+            tree.update_leaf(0, new_leaves[0])?;
+            assert_eq!(tree, MerkleTree::new(new_leaves.clone())?);
+
+            // SO DOES CHANGING THE LAST LEAF, THE ONE PAIRED WITH ITSELF ON ODD LEVELS
+            let last = new_leaves.len() - 1;
+            new_leaves[last] = [0xcd; 32];
+            tree.update_leaf(last, new_leaves[last])?;
+            assert_eq!(tree, MerkleTree::new(new_leaves)?);
+        }
+
+        assert!(MerkleTree::new(Vec::new()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn mutated_update_test() -> Result<()> {
+        let leaves = test_leaves(4);
+        let mut tree = MerkleTree::new(leaves.clone())?;
+        assert!(!tree.is_mutated());
+
+        // LEAF 0 EQUAL TO LEAF 1 MUTATES THE TREE, RESTORING IT UNDOES THAT
+        tree.update_leaf(0, leaves[1])?;
+        assert!(tree.is_mutated());
+        tree.update_leaf(0, leaves[0])?;
+        assert!(!tree.is_mutated());
+
+        // AN ODD LEVEL IS NOT MUTATED BY ITS OWN PADDING
+        assert!(!MerkleTree::new(test_leaves(3))?.is_mutated());
+
+        Ok(())
+    }
+}
@@ -1,3 +1,9 @@
 pub mod block;
+pub mod chain;
+pub mod deserialise;
+pub mod difficulty;
+pub mod merkle_proof;
 pub mod merkle_root;
+pub mod merkle_tree;
 pub mod serialise_tx;
+pub mod verify_block;
@@ -1,18 +1,48 @@
-use std::fs;
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
 use walkdir::WalkDir;
 
-use crate::{error::Result, transaction::Transaction};
+use crate::{
+    error::Result,
+    hashes::{Txid, Wtxid},
+    transaction::Transaction,
+    validation_checks::amounts::check_amounts,
+};
 
 pub fn double_sha256(data: &[u8]) -> Vec<u8> {
     Sha256::digest(&Sha256::digest(data)).to_vec()
 }
 
-// ITERATE THROUGH THE VALID-MEMPOOL TO CREATE A VECTOR OF FEATURES TO BE USED FOR EACH TRANSACTION IN BLOCK MINING
-pub fn create_txid_tx_map() -> Result<Vec<(String, Transaction, String, usize, u64)>> {
+// COMPACTSIZE ENCODING USED FOR COUNTS AND LENGTHS IN THE SERIALISATION
+pub fn compact_size(n: u64) -> Vec<u8> {
+    match n {
+        0..=0xfc => vec![n as u8],
+        0xfd..=0xffff => {
+            let mut bytes = vec![0xfd];
+            bytes.extend((n as u16).to_le_bytes());
+            bytes
+        }
+        0x10000..=0xffffffff => {
+            let mut bytes = vec![0xfe];
+            bytes.extend((n as u32).to_le_bytes());
+            bytes
+        }
+        _ => {
+            let mut bytes = vec![0xff];
+            bytes.extend(n.to_le_bytes());
+            bytes
+        }
+    }
+}
+
+// TXID, TRANSACTION, WTXID, WEIGHT AND FEES OF A VALID TRANSACTION
+pub type MempoolEntry = (Txid, Transaction, Wtxid, usize, u64);
 
+// ITERATE THROUGH THE VALID-MEMPOOL TO CREATE A VECTOR OF FEATURES TO BE USED FOR EACH TRANSACTION IN BLOCK MINING
+pub fn create_txid_tx_map() -> Result<Vec<MempoolEntry>> {
     let v_mempool_dir = "./valid-mempool";
-    let mut map: Vec<(String, Transaction, String, usize, u64)> = Vec::new();
+    let mut map: Vec<MempoolEntry> = Vec::new();
 
     for entry in WalkDir::new(v_mempool_dir)
         .into_iter()
@@ -27,14 +57,8 @@ pub fn create_txid_tx_map() -> Result<Vec<(String, Transaction, String, usize, u
                             serialise_tx(&transaction)?;
 
                         if result == true {
-                            let mut txid = double_sha256(&serialised_tx);
-                            let mut wtxid = double_sha256(&serialised_wtx);
-
-                            txid.reverse();
-                            wtxid.reverse();
-
-                            let txid = hex::encode(txid);
-                            let wtxid = hex::encode(wtxid);
+                            let txid = Txid::from_hash(&double_sha256(&serialised_tx))?;
+                            let wtxid = Wtxid::from_hash(&double_sha256(&serialised_wtx))?;
 
                             // Find the correct position to insert the transaction based on its fees
                             let position = map
@@ -53,30 +77,73 @@ pub fn create_txid_tx_map() -> Result<Vec<(String, Transaction, String, usize, u
         }
     }
 
-    Ok(map)
+    Ok(order_by_dependencies(map))
 }
 
-// AIMS TO CREATE THE RAW TX FOR TXID AND RAW WTX FOR WTXID 
-fn serialise_tx(tx: &Transaction) -> Result<(bool, Vec<u8>, Vec<u8>, usize, u64)> {
+// KEEPS THE FEE ORDER BUT PULLS EVERY IN-MEMPOOL PARENT IN FRONT OF ITS CHILD
+pub fn order_by_dependencies(map: Vec<MempoolEntry>) -> Vec<MempoolEntry> {
+    let positions: HashMap<Txid, usize> = map
+        .iter()
+        .enumerate()
+        .map(|(position, (txid, _, _, _, _))| (*txid, position))
+        .collect();
+
+    let mut placed = vec![false; map.len()];
+    let mut order: Vec<usize> = Vec::with_capacity(map.len());
+
+    for position in 0..map.len() {
+        place_with_parents(position, &map, &positions, &mut placed, &mut order);
+    }
+
+    let mut entries: Vec<Option<MempoolEntry>> = map.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .filter_map(|position| entries[position].take())
+        .collect()
+}
+
+fn place_with_parents(
+    position: usize,
+    map: &[MempoolEntry],
+    positions: &HashMap<Txid, usize>,
+    placed: &mut [bool],
+    order: &mut Vec<usize>,
+) {
+    if placed[position] {
+        return;
+    }
+    placed[position] = true;
+
+    for input in map[position].1.vin.iter() {
+        if let Some(parent) = positions.get(&input.txid) {
+            place_with_parents(*parent, map, positions, placed, order);
+        }
+    }
+
+    order.push(position);
+}
+
+// WHETHER IT CAN GO IN A BLOCK, THE RAW TX, THE RAW WTX, ITS WEIGHT AND ITS FEES
+pub type SerialisedTx = (bool, Vec<u8>, Vec<u8>, usize, u64);
+
+// AIMS TO CREATE THE RAW TX FOR TXID AND RAW WTX FOR WTXID
+pub fn serialise_tx(tx: &Transaction) -> Result<SerialisedTx> {
+    // A TX MIXING INPUT TYPES CAN START WITH A LEGACY INPUT, SO ANY WITNESS MAKES IT SEGWIT
     let tx_type;
-    if tx.vin[0].witness == None {
+    if tx.vin.iter().all(|input| input.witness_items().is_empty()) {
         tx_type = "LEGACY";
     } else {
         tx_type = "SEGWIT";
     }
 
-    let mut fees = 0;
     let mut non_witness_bytes = 0;
     let mut witness_bytes = 0;
 
-    // CALCULATE GAS FEES
-    for input in tx.vin.iter() {
-        fees += input.prevout.value;
-    }
-
-    for output in tx.vout.iter() {
-        fees -= output.value;
-    }
+    // CALCULATE GAS FEES, A TX WITH AMOUNTS OUT OF RANGE OR A NEGATIVE FEE CAN'T GO IN A BLOCK
+    let fees = match check_amounts(tx) {
+        Ok(fee) => fee.to_sat(),
+        Err(_) => return Ok((false, Vec::new(), Vec::new(), 0, 0)),
+    };
 
     let mut raw_tx: Vec<u8> = Vec::new();
     let mut raw_wtx: Vec<u8> = Vec::new();
@@ -96,20 +163,21 @@ fn serialise_tx(tx: &Transaction) -> Result<(bool, Vec<u8>, Vec<u8>, usize, u64)
 
         // INPUTS
         for input in tx.vin.iter() {
-            // TXID REVERSED
-            let mut txid = hex::decode(&input.txid.clone())?;
-            txid.reverse();
+            // TXID (INTERNAL ORDER) AND VOUT
+            let outpoint = input.outpoint().consensus_bytes();
             // SCRIPT SIG
             let script_sig = hex::decode(&input.scriptsig.clone().unwrap())?;
             let script_sig_len = script_sig.len();
 
-            raw_tx.extend_from_slice(&txid);
-            raw_tx.extend(input.vout.to_le_bytes());
-            raw_tx.push(script_sig.len().try_into()?);
+            // A MULTISIG SCRIPT SIG CAN BE LONGER THAN A ONE BYTE COMPACTSIZE
+            let script_sig_len_bytes = compact_size(script_sig_len as u64);
+
+            raw_tx.extend_from_slice(&outpoint);
+            raw_tx.extend_from_slice(&script_sig_len_bytes);
             raw_tx.extend_from_slice(&script_sig);
             raw_tx.extend(input.sequence.to_le_bytes());
 
-            non_witness_bytes += 32 + 4 + 1 + script_sig_len + 4;
+            non_witness_bytes += 32 + 4 + script_sig_len_bytes.len() + script_sig_len + 4;
         }
 
         // OUTPUT COUNT
@@ -166,30 +234,24 @@ fn serialise_tx(tx: &Transaction) -> Result<(bool, Vec<u8>, Vec<u8>, usize, u64)
 
         // INPUTS
         for input in tx.vin.iter() {
-            // TXID REVERSED
-            let mut txid = hex::decode(&input.txid.clone())?;
-            txid.reverse();
+            // TXID (INTERNAL ORDER) AND VOUT
+            let outpoint = input.outpoint().consensus_bytes();
 
             // SCRIPT SIG
             let script_sig = hex::decode(&input.scriptsig.clone().unwrap())?;
             let script_sig_len = script_sig.len();
 
-            raw_tx.extend_from_slice(&txid);
-            raw_tx.extend(input.vout.to_le_bytes());
+            raw_tx.extend_from_slice(&outpoint);
 
-            raw_wtx.extend_from_slice(&txid);
-            raw_wtx.extend(input.vout.to_le_bytes());
+            raw_wtx.extend_from_slice(&outpoint);
 
             non_witness_bytes += 32 + 4;
 
-            if script_sig.len() >= 255 {
-                return Ok((false, Vec::new(), Vec::new(), 0, 0));
-            }
-
-            raw_tx.push(script_sig.len().try_into()?);
-            raw_wtx.push(script_sig.len().try_into()?);
+            let script_sig_len_bytes = compact_size(script_sig_len as u64);
+            raw_tx.extend_from_slice(&script_sig_len_bytes);
+            raw_wtx.extend_from_slice(&script_sig_len_bytes);
 
-            non_witness_bytes += 1;
+            non_witness_bytes += script_sig_len_bytes.len();
 
             if script_sig.len() != 0 {
                 raw_tx.extend_from_slice(&script_sig);
@@ -236,7 +298,7 @@ fn serialise_tx(tx: &Transaction) -> Result<(bool, Vec<u8>, Vec<u8>, usize, u64)
 
         // WITNESS ONLY IN WTX
         for input in tx.vin.iter() {
-            let witness = input.witness.clone().unwrap();
+            let witness = input.witness.clone().unwrap_or_default();
             // let witness_len = witness.len();
 
             raw_wtx.push(witness.len().try_into()?);
@@ -246,10 +308,11 @@ fn serialise_tx(tx: &Transaction) -> Result<(bool, Vec<u8>, Vec<u8>, usize, u64)
             for item in witness {
                 let item_bytes = hex::decode(&item)?;
                 let item_bytes_len = item_bytes.len();
-                raw_wtx.push(item_bytes.len().try_into()?);
+                let item_len_bytes = compact_size(item_bytes_len as u64);
+                raw_wtx.extend_from_slice(&item_len_bytes);
                 raw_wtx.extend_from_slice(&item_bytes);
 
-                witness_bytes += 1 + item_bytes_len;
+                witness_bytes += item_len_bytes.len() + item_bytes_len;
             }
         }
 
@@ -288,4 +351,62 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn legacy_first_input_test() -> Result<()> {
+        // P2PKH THEN P2WPKH, THE WITNESS OF THE SECOND INPUT STILL GOES IN THE WTX
+        let path =
+            "./mempool/753b075bd84cdc7400acaf8b52cea866f099de2ebb70ea89fc98abe5f1162d5f.json";
+        let transaction: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        let (_, tx, wtx, weight, _) = serialise_tx(&transaction)?;
+        assert_eq!(tx, transaction.serialise_base()?);
+        assert_eq!(wtx[4..6], [0x00, 0x01]);
+        assert_eq!(wtx.len(), transaction.total_size()?);
+        assert_eq!(weight, transaction.weight()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn compact_size_test() {
+        assert_eq!(compact_size(0xfc), vec![0xfc]);
+        assert_eq!(compact_size(0xfd), vec![0xfd, 0xfd, 0x00]);
+        assert_eq!(compact_size(0x1_0000), vec![0xfe, 0x00, 0x00, 0x01, 0x00]);
+        assert_eq!(compact_size(0x1_0000_0000)[0], 0xff);
+    }
+
+    #[test]
+    fn order_by_dependencies_test() -> Result<()> {
+        let path =
+            "./valid-mempool/001035505afbf143e51bd667099190943a38eee20092bb691e72eaa44992b2f7.json";
+        let parent: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        let [parent_txid, child_txid, grandchild_txid, other_txid] =
+            [1, 2, 3, 4].map(|byte| Txid::from_internal_bytes([byte; 32]));
+
+        let mut child = parent.clone();
+        child.vin[0].txid = parent_txid;
+        let mut grandchild = parent.clone();
+        grandchild.vin[0].txid = child_txid;
+
+        // THE GRANDCHILD PAYS THE HIGHEST FEE RATE BUT STILL COMES LAST
+        let map = vec![
+            (grandchild_txid, grandchild, Wtxid::all_zeros(), 0, 0),
+            (other_txid, parent.clone(), Wtxid::all_zeros(), 0, 0),
+            (child_txid, child, Wtxid::all_zeros(), 0, 0),
+            (parent_txid, parent, Wtxid::all_zeros(), 0, 0),
+        ];
+
+        let txids: Vec<Txid> = order_by_dependencies(map)
+            .into_iter()
+            .map(|(txid, _, _, _, _)| txid)
+            .collect();
+
+        assert_eq!(
+            txids,
+            vec![parent_txid, child_txid, grandchild_txid, other_txid]
+        );
+
+        Ok(())
+    }
 }
@@ -0,0 +1,718 @@
+use std::collections::{HashMap, HashSet};
+use std::{fmt, fs};
+
+use failure::format_err;
+use walkdir::WalkDir;
+
+use crate::{
+    amount::Amount,
+    error::Result,
+    hashes::{BlockHash, OutPoint, Txid, Wtxid},
+    transaction::Transaction,
+    utxo::{UtxoSet, MEMPOOL_TIP},
+    validation_checks::{
+        locktime::BlockContext,
+        reject_reason,
+        sig_cache::thread_stats,
+        sigops::{count_sigops, tx_sigop_cost, MAX_BLOCK_SIGOPS_COST, WITNESS_SCALE_FACTOR},
+        taproot::{verify_schnorr_checks, SchnorrCheck, SchnorrChecks, SchnorrMode},
+        verify_flags::VerifyFlags,
+        RejectReason,
+    },
+};
+
+use super::{
+    block::serialise_block,
+    chain::chain_params,
+    deserialise::{deserialise_block, RawTransaction},
+    difficulty::check_proof_of_work,
+    merkle_root::{block_subsidy, compute_merkel_root},
+    merkle_tree::MerkleTree,
+    serialise_tx::{double_sha256, serialise_tx},
+};
+
+// CONSENSUS LIMIT ON THE WEIGHT OF A BLOCK
+pub const MAX_BLOCK_WEIGHT: usize = 4000000;
+
+// OP_RETURN OP_PUSHBYTES_36 aa21a9ed
+const WITNESS_COMMITMENT_HEADER: &str = "6a24aa21a9ed";
+
+// EVERY WAY A BLOCK CAN FAIL VERIFICATION
+#[derive(Debug, PartialEq)]
+pub enum BlockError {
+    Empty,
+    HighHash(BlockHash, u32),
+    MissingCoinbase,
+    MultipleCoinbase,
+    BadCoinbaseLength(usize),
+    BadMerkleRoot(String, String),
+    MutatedMerkleRoot,
+    BadWitnessCommitment,
+    BadCoinbaseAmount(Amount, Amount),
+    CoinbaseOutOfRange,
+    BadWeight(usize),
+    BadSigops(usize),
+    DuplicateTx(Txid),
+    UnknownTx(Txid),
+    BadTxOrder(Txid),
+    DoubleSpend(Txid, OutPoint),
+    NegativeFee(Txid),
+    FeesOutOfRange,
+    InvalidTx(Txid, RejectReason),
+    // THE TX AND THE INPUT OF A TAPROOT SIGNATURE THAT FAILED
+    BadSchnorrSig(Txid, usize),
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BlockError::Empty => write!(f, "BLOCK HAS NO TRANSACTIONS"),
+            BlockError::HighHash(hash, bits) => {
+                write!(f, "BLOCK HASH {} DOES NOT MEET BITS {:08x}", hash, bits)
+            }
+            BlockError::MissingCoinbase => write!(f, "FIRST TRANSACTION IS NOT A COINBASE"),
+            BlockError::MultipleCoinbase => write!(f, "MORE THAN ONE COINBASE"),
+            BlockError::BadCoinbaseLength(size) => {
+                write!(f, "COINBASE SCRIPT SIG SIZE {} OUT OF RANGE", size)
+            }
+            BlockError::BadMerkleRoot(header, computed) => {
+                write!(
+                    f,
+                    "MERKLE ROOT MISMATCH: HEADER {} COMPUTED {}",
+                    header, computed
+                )
+            }
+            BlockError::MutatedMerkleRoot => {
+                write!(f, "MERKLE TREE PAIRS IDENTICAL HASHES (CVE-2012-2459)")
+            }
+            BlockError::BadWitnessCommitment => write!(f, "WITNESS COMMITMENT MISSING OR WRONG"),
+            BlockError::BadCoinbaseAmount(paid, allowed) => {
+                write!(
+                    f,
+                    "COINBASE PAYS {} BUT SUBSIDY PLUS FEES IS {}",
+                    paid, allowed
+                )
+            }
+            BlockError::CoinbaseOutOfRange => write!(f, "COINBASE PAYS MORE THAN MAX_MONEY"),
+            BlockError::BadWeight(weight) => write!(f, "BLOCK WEIGHT {} EXCEEDS THE LIMIT", weight),
+            BlockError::BadSigops(cost) => write!(f, "BLOCK SIGOP COST {} EXCEEDS THE LIMIT", cost),
+            BlockError::DuplicateTx(txid) => write!(f, "DUPLICATE TRANSACTION {}", txid),
+            BlockError::UnknownTx(txid) => write!(f, "TRANSACTION {} IS NOT IN THE MEMPOOL", txid),
+            BlockError::BadTxOrder(txid) => {
+                write!(
+                    f,
+                    "TRANSACTION {} SPENDS AN OUTPUT OF A LATER TRANSACTION",
+                    txid
+                )
+            }
+            BlockError::DoubleSpend(txid, outpoint) => {
+                write!(f, "TRANSACTION {} DOUBLE SPENDS {}", txid, outpoint)
+            }
+            BlockError::NegativeFee(txid) => {
+                write!(f, "TRANSACTION {} SPENDS MORE THAN ITS INPUTS", txid)
+            }
+            BlockError::FeesOutOfRange => write!(f, "TOTAL FEES ARE MORE THAN MAX_MONEY"),
+            BlockError::InvalidTx(txid, reason) => {
+                write!(f, "TRANSACTION {} REJECTED: {}", txid, reason)
+            }
+            BlockError::BadSchnorrSig(txid, input) => write!(
+                f,
+                "TRANSACTION {} HAS AN INVALID SCHNORR SIGNATURE IN INPUT {}",
+                txid, input
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+// ENTRY POINT OF `verify-block <PATH>`: OUTPUT.TXT, A RAW .dat BLOCK OR A .hex BLOCK
+pub fn verify_block_file(path: &str, mode: SchnorrMode) -> Result<()> {
+    let index = mempool_index("./mempool")?;
+    let raw_block = read_block(path, &index)?;
+
+    // ONLY THE BLOCK'S OWN LOOKUPS, NOT THOSE OF THE MEMPOOL PASS BEFORE IT
+    let before = thread_stats();
+    let errors = verify_block(&raw_block, &index, mode)?;
+    println!("SIGNATURE CACHE: {}", thread_stats().since(before));
+
+    if errors.is_empty() {
+        println!("BLOCK VERIFICATION: PASSED");
+        return Ok(());
+    }
+
+    for error in errors.iter() {
+        println!("BLOCK VERIFICATION: {}", error);
+    }
+
+    Err(format_err!(
+        "BLOCK VERIFICATION: FAILED ({} ERRORS)",
+        errors.len()
+    ))
+}
+
+// LOADS THE BLOCK BYTES, OUTPUT.TXT IS REBUILT FROM ITS HEADER, COINBASE AND THE MEMPOOL TXS
+pub fn read_block(path: &str, index: &HashMap<Txid, Transaction>) -> Result<Vec<u8>> {
+    if path.ends_with(".dat") {
+        return Ok(fs::read(path)?);
+    }
+
+    let contents = fs::read_to_string(path)?;
+    if path.ends_with(".hex") {
+        return Ok(hex::decode(contents.trim())?);
+    }
+
+    let lines: Vec<&str> = contents.lines().map(str::trim).collect();
+    if lines.len() < 3 {
+        return Err(format_err!("OUTPUT FILE TOO SHORT"));
+    }
+
+    // THE FIRST TXID IS THE COINBASE, WHICH IS ALREADY ON THE SECOND LINE
+    let mut txs: Vec<Transaction> = Vec::new();
+    for line in lines[3..].iter().filter(|line| !line.is_empty()) {
+        let txid: Txid = line.parse()?;
+        let tx = index.get(&txid).ok_or(BlockError::UnknownTx(txid))?;
+        txs.push(tx.clone());
+    }
+
+    serialise_block(lines[0], lines[1], &txs)
+}
+
+// TXID -> TRANSACTION FOR EVERY TRANSACTION IN THE MEMPOOL DIRECTORY
+pub fn mempool_index(mempool_dir: &str) -> Result<HashMap<Txid, Transaction>> {
+    let mut index: HashMap<Txid, Transaction> = HashMap::new();
+
+    for entry in WalkDir::new(mempool_dir).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        let transaction = match fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<Transaction>(&contents).ok())
+        {
+            Some(transaction) => transaction,
+            None => continue,
+        };
+
+        // TXS THE SERIALISER REFUSES CAN'T BE IN ONE OF OUR BLOCKS ANYWAY
+        if let Ok((true, raw_tx, _, _, _)) = serialise_tx(&transaction) {
+            index.insert(Txid::from_hash(&double_sha256(&raw_tx))?, transaction);
+        }
+    }
+
+    Ok(index)
+}
+
+// THE TX AS THE BLOCK HAS IT, WITH THE PREVOUTS OF THE MEMPOOL TX WITH THE SAME TXID
+// THE TXID DOESN'T COMMIT TO THE WITNESSES, SO THEY MUST BE TAKEN FROM THE BLOCK AND NOT THE JSON
+// THE ASM, TYPE AND ADDRESS FIELDS ARE KEPT, THE CONSISTENCY CHECK HOLDS THEM TO THE BLOCK'S HEX
+fn block_transaction(raw_tx: &RawTransaction, mempool_tx: &Transaction) -> Transaction {
+    let mut tx = mempool_tx.clone();
+    tx.version = raw_tx.version;
+    tx.locktime = raw_tx.locktime;
+
+    for (input, raw_input) in tx.vin.iter_mut().zip(raw_tx.inputs.iter()) {
+        input.txid = raw_input.previous_output.txid;
+        input.vout = raw_input.previous_output.vout;
+        input.scriptsig = Some(hex::encode(&raw_input.script_sig));
+        input.sequence = raw_input.sequence;
+        input.witness = match (&input.witness, raw_input.witness.is_empty()) {
+            (None, true) => None,
+            _ => Some(raw_input.witness.iter().map(hex::encode).collect()),
+        };
+    }
+
+    for (output, raw_output) in tx.vout.iter_mut().zip(raw_tx.outputs.iter()) {
+        output.value = raw_output.value;
+        output.scriptpubkey = hex::encode(&raw_output.script_pubkey);
+    }
+
+    tx
+}
+
+// RUNS EVERY CONSENSUS CHECK ON A SERIALISED BLOCK AND RETURNS ALL THE FAILURES
+// `mode`: WHETHER THE TAPROOT SIGNATURES OF THE BLOCK ARE CHECKED ONE BY ONE OR AS ONE BATCH
+pub fn verify_block(
+    raw_block: &[u8],
+    index: &HashMap<Txid, Transaction>,
+    mode: SchnorrMode,
+) -> Result<Vec<BlockError>> {
+    let mut errors: Vec<BlockError> = Vec::new();
+
+    let (block_header, raw_txs) = deserialise_block(raw_block)?;
+    let header_bytes = hex::decode(&block_header)?;
+
+    // PROOF OF WORK
+    let bits = u32::from_le_bytes(header_bytes[72..76].try_into()?);
+    let block_hash = BlockHash::from_hash(&double_sha256(&header_bytes))?;
+
+    if !check_proof_of_work(&block_hash, bits, &chain_params()) {
+        errors.push(BlockError::HighHash(block_hash, bits));
+    }
+
+    let coinbase = match raw_txs.first() {
+        Some(coinbase) => coinbase,
+        None => {
+            errors.push(BlockError::Empty);
+            return Ok(errors);
+        }
+    };
+
+    // COINBASE
+    if !coinbase.is_coinbase() {
+        errors.push(BlockError::MissingCoinbase);
+        return Ok(errors);
+    }
+    if raw_txs.iter().skip(1).any(|tx| tx.is_coinbase()) {
+        errors.push(BlockError::MultipleCoinbase);
+    }
+
+    let coinbase_script_sig = &coinbase.inputs[0].script_sig;
+    if !(2..=100).contains(&coinbase_script_sig.len()) {
+        errors.push(BlockError::BadCoinbaseLength(coinbase_script_sig.len()));
+    }
+
+    // WITHOUT THE PREVIOUS HEADERS, TIME LOCKS ARE CHECKED AGAINST THE HEADER'S OWN TIME
+    let block_height = coinbase_height(coinbase_script_sig);
+    let block_time = u32::from_le_bytes(header_bytes[68..72].try_into()?);
+    let context = BlockContext::new(block_height, block_time);
+
+    // COINS CREATED BY THE MEMPOOL ARE UNCONFIRMED, SO THEY CAN ONLY COME FROM THIS SAME BLOCK
+    let utxos = UtxoSet::from_mempool(index, MEMPOOL_TIP);
+
+    // MERKLE ROOT
+    let txids: Vec<Txid> = raw_txs.iter().map(|tx| tx.txid).collect();
+    let (computed_root, mutated) = compute_merkel_root(&txids)?;
+    let header_root = hex::encode(&header_bytes[36..68]);
+
+    if computed_root != header_root {
+        errors.push(BlockError::BadMerkleRoot(header_root, computed_root));
+    }
+    if mutated {
+        errors.push(BlockError::MutatedMerkleRoot);
+    }
+
+    // WITNESS COMMITMENT
+    if !check_witness_commitment(&raw_txs)? {
+        errors.push(BlockError::BadWitnessCommitment);
+    }
+
+    // WEIGHT
+    let tx_count_bytes =
+        raw_block.len() - 80 - raw_txs.iter().map(|tx| tx.raw.len()).sum::<usize>();
+    let block_weight = (80 + tx_count_bytes) * WITNESS_SCALE_FACTOR
+        + raw_txs.iter().map(|tx| tx.weight).sum::<usize>();
+
+    if block_weight > MAX_BLOCK_WEIGHT {
+        errors.push(BlockError::BadWeight(block_weight));
+    }
+
+    // TRANSACTIONS: DUPLICATES, ORDER, DOUBLE SPENDS, SCRIPTS, FEES AND SIGOPS
+    let position: HashMap<Txid, usize> = txids
+        .iter()
+        .enumerate()
+        .map(|(i, txid)| (*txid, i))
+        .collect();
+    let mut seen: HashSet<Txid> = HashSet::new();
+    let mut spent: HashMap<OutPoint, Txid> = HashMap::new();
+    let mut total_fees = Amount::ZERO;
+    let mut schnorr_checks: Vec<(Txid, Vec<SchnorrCheck>)> = Vec::new();
+
+    let mut sigop_cost = count_sigops(coinbase_script_sig, false) * WITNESS_SCALE_FACTOR;
+    for output in coinbase.outputs.iter() {
+        sigop_cost += count_sigops(&output.script_pubkey, false) * WITNESS_SCALE_FACTOR;
+    }
+
+    for (tx_index, raw_tx) in raw_txs.iter().enumerate().skip(1) {
+        if !seen.insert(raw_tx.txid) {
+            errors.push(BlockError::DuplicateTx(raw_tx.txid));
+            continue;
+        }
+
+        for input in raw_tx.inputs.iter() {
+            let outpoint = input.previous_output;
+
+            if let Some(parent_index) = position.get(&outpoint.txid) {
+                if *parent_index >= tx_index {
+                    errors.push(BlockError::BadTxOrder(raw_tx.txid));
+                }
+            }
+
+            if spent.insert(outpoint, raw_tx.txid).is_some() {
+                errors.push(BlockError::DoubleSpend(raw_tx.txid, outpoint));
+            }
+        }
+
+        // ONLY THE PREVOUTS COME FROM THE MEMPOOL JSON OF THE SAME TRANSACTION
+        let transaction = match index.get(&raw_tx.txid) {
+            Some(mempool_tx) => block_transaction(raw_tx, mempool_tx),
+            None => {
+                errors.push(BlockError::UnknownTx(raw_tx.txid));
+                continue;
+            }
+        };
+
+        // AMOUNTS OUT OF RANGE ARE REPORTED BY reject_reason BELOW
+        let inputs = transaction.vin.iter().map(|input| input.prevout.value);
+        let outputs = raw_tx.outputs.iter().map(|output| output.value);
+        let value_in = Amount::money_sum(inputs.map(Amount::from_sat));
+        let value_out = Amount::money_sum(outputs.map(Amount::from_sat));
+        if let (Some(value_in), Some(value_out)) = (value_in, value_out) {
+            match value_in.checked_sub(value_out) {
+                Some(fee) => total_fees = total_fees.saturating_add(fee),
+                None => errors.push(BlockError::NegativeFee(raw_tx.txid)),
+            }
+        }
+
+        sigop_cost += tx_sigop_cost(&transaction)?;
+
+        // A BLOCK ONLY HAS TO FOLLOW THE CONSENSUS RULES, NOT THE MEMPOOL POLICY
+        let flags = VerifyFlags::CONSENSUS;
+        let mut checks = Vec::new();
+        let schnorr = SchnorrChecks::Collect(&mut checks);
+        match reject_reason(&transaction, &context, &utxos, flags, None, schnorr)? {
+            Some(reason) => errors.push(BlockError::InvalidTx(raw_tx.txid, reason)),
+            None => schnorr_checks.push((raw_tx.txid, checks)),
+        }
+    }
+
+    // THE TAPROOT SIGNATURES OF THE WHOLE BLOCK AT ONCE, IF THAT FAILS EACH TX IS CHECKED ON ITS
+    // OWN TO NAME THE INPUT
+    let block_checks: Vec<SchnorrCheck> = schnorr_checks
+        .iter()
+        .flat_map(|(_, checks)| checks.iter().copied())
+        .collect();
+    if verify_schnorr_checks(&block_checks, mode).is_some() {
+        for (txid, checks) in schnorr_checks.iter() {
+            if let Some(input) = verify_schnorr_checks(checks, SchnorrMode::Serial) {
+                errors.push(BlockError::BadSchnorrSig(*txid, input));
+            }
+        }
+    }
+
+    if sigop_cost > MAX_BLOCK_SIGOPS_COST {
+        errors.push(BlockError::BadSigops(sigop_cost));
+    }
+
+    if !total_fees.is_money_range() {
+        errors.push(BlockError::FeesOutOfRange);
+    }
+
+    // COINBASE VALUE
+    let subsidy = Amount::from_sat(block_subsidy(block_height));
+    let max_coinbase_value = subsidy.saturating_add(total_fees);
+    let outputs = coinbase.outputs.iter().map(|output| output.value);
+
+    match Amount::money_sum(outputs.map(Amount::from_sat)) {
+        Some(coinbase_value) if coinbase_value <= max_coinbase_value => {}
+        Some(coinbase_value) => errors.push(BlockError::BadCoinbaseAmount(
+            coinbase_value,
+            max_coinbase_value,
+        )),
+        None => errors.push(BlockError::CoinbaseOutOfRange),
+    }
+
+    Ok(errors)
+}
+
+// BIP141: THE LAST aa21a9ed OUTPUT OF THE COINBASE COMMITS TO THE WTXID MERKLE ROOT
+fn check_witness_commitment(raw_txs: &[RawTransaction]) -> Result<bool> {
+    let coinbase = &raw_txs[0];
+    let commitment_header = hex::decode(WITNESS_COMMITMENT_HEADER)?;
+
+    let commitment = coinbase
+        .outputs
+        .iter()
+        .rev()
+        .find(|output| {
+            output.script_pubkey.len() >= 38 && output.script_pubkey.starts_with(&commitment_header)
+        })
+        .map(|output| output.script_pubkey[6..38].to_vec());
+
+    let commitment = match commitment {
+        Some(commitment) => commitment,
+        // A BLOCK WITHOUT WITNESS DATA DOESN'T NEED A COMMITMENT
+        None => return Ok(!raw_txs.iter().any(|tx| tx.has_witness())),
+    };
+
+    // THE COINBASE WITNESS IS THE 32 BYTE WITNESS RESERVED VALUE
+    let witness_reserved_value = match coinbase.inputs[0].witness.as_slice() {
+        [value] if value.len() == 32 => value.clone(),
+        _ => return Ok(false),
+    };
+
+    let mut wtxids: Vec<Wtxid> = vec![Wtxid::all_zeros()];
+    wtxids.extend(raw_txs.iter().skip(1).map(|tx| tx.wtxid));
+
+    let mut witness_commit = MerkleTree::from_wtxids(&wtxids)?.root().to_vec();
+    witness_commit.extend(witness_reserved_value);
+
+    Ok(double_sha256(&witness_commit) == commitment)
+}
+
+// BIP34 HEIGHT AT THE START OF THE COINBASE SCRIPT SIG
+fn coinbase_height(script_sig: &[u8]) -> u32 {
+    match script_sig.first() {
+        Some(0x00) => 0,
+        Some(opcode @ 0x51..=0x60) => (opcode - 0x50) as u32,
+        Some(len @ 1..=4) if script_sig.len() > *len as usize => {
+            let mut height_bytes = [0u8; 4];
+            height_bytes[..*len as usize].copy_from_slice(&script_sig[1..1 + *len as usize]);
+            u32::from_le_bytes(height_bytes)
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::block_mine::{
+        block::{current_time, mine_block_header, payout_script, take_block_txs, BLOCK_HEIGHT},
+        merkle_root::generate_roots,
+        serialise_tx::{compact_size, create_txid_tx_map},
+    };
+    use crate::validation_checks::{amounts::AmountError, verify_tx};
+
+    use super::*;
+
+    // MINES A SMALL BLOCK FROM THE FIRST FEW VALID-MEMPOOL TXS
+    fn small_block() -> Result<(Vec<u8>, HashMap<Txid, Transaction>)> {
+        let mut map = create_txid_tx_map()?;
+        map.truncate(20);
+
+        let index: HashMap<Txid, Transaction> = map
+            .iter()
+            .map(|(txid, tx, _, _, _)| (*txid, tx.clone()))
+            .collect();
+
+        let time = current_time();
+        let context = BlockContext::new(BLOCK_HEIGHT, time);
+
+        let utxos = UtxoSet::from_mempool(&index, MEMPOOL_TIP);
+
+        let (merkel_root, coinbase_tx, _, txids) =
+            generate_roots(map.clone(), &context, &utxos, &payout_script()?)?;
+        let (block_txs, _) = take_block_txs(map, &txids);
+        let (block_header, _) =
+            mine_block_header(&BlockHash::all_zeros(), &merkel_root, time, 0x1f00ffff)?;
+
+        Ok((
+            serialise_block(&block_header, &coinbase_tx, &block_txs)?,
+            index,
+        ))
+    }
+
+    #[test]
+    fn verify_block_test() -> Result<()> {
+        let (raw_block, index) = small_block()?;
+        assert_eq!(
+            verify_block(&raw_block, &index, SchnorrMode::Serial)?,
+            vec![]
+        );
+
+        // FLIPPING A BYTE OF THE MERKLE ROOT BREAKS BOTH THE ROOT AND (ALMOST SURELY) THE POW
+        let mut tampered = raw_block.clone();
+        tampered[40] ^= 0x01;
+        let errors = verify_block(&tampered, &index, SchnorrMode::Serial)?;
+        assert!(matches!(
+            errors.last(),
+            Some(BlockError::BadMerkleRoot(_, _))
+        ));
+
+        // 21 TXS: REPEATING THE LAST ONE KEEPS THE ROOT, AND SO THE POW, INTACT
+        let (header, raw_txs) = deserialise_block(&raw_block)?;
+        let mut mutated = hex::decode(header)?;
+        mutated.extend(compact_size(raw_txs.len() as u64 + 1));
+        for raw_tx in raw_txs.iter().chain(raw_txs.last()) {
+            mutated.extend(&raw_tx.raw);
+        }
+        let errors = verify_block(&mutated, &index, SchnorrMode::Serial)?;
+        assert!(errors.contains(&BlockError::MutatedMerkleRoot));
+        assert!(!errors.iter().any(|error| matches!(
+            error,
+            BlockError::BadMerkleRoot(_, _) | BlockError::HighHash(_, _)
+        )));
+
+        // WITHOUT THE MEMPOOL THE PREVOUTS ARE UNKNOWN, SO ARE THE FEES THE COINBASE CLAIMS
+        let errors = verify_block(&raw_block, &HashMap::new(), SchnorrMode::Serial)?;
+        let unknown = errors
+            .iter()
+            .filter(|error| matches!(error, BlockError::UnknownTx(_)))
+            .count();
+        assert_eq!(unknown, 20);
+        assert!(matches!(
+            errors.last(),
+            Some(BlockError::BadCoinbaseAmount(_, _))
+        ));
+
+        Ok(())
+    }
+
+    // THE BLOCK WITH ITS TX AT `tx_index` SWAPPED FOR `tx`, THE HEADER IS KEPT AS IT IS
+    fn replace_tx(raw_block: &[u8], tx_index: usize, tx: &Transaction) -> Result<Vec<u8>> {
+        let (header, raw_txs) = deserialise_block(raw_block)?;
+        let mut replaced = hex::decode(header)?;
+        replaced.extend(compact_size(raw_txs.len() as u64));
+        for (index, raw_tx) in raw_txs.iter().enumerate() {
+            match index == tx_index {
+                true => replaced.extend(serialise_tx(tx)?.2),
+                false => replaced.extend(&raw_tx.raw),
+            }
+        }
+        Ok(replaced)
+    }
+
+    #[test]
+    fn non_final_tx_test() -> Result<()> {
+        let (raw_block, mut index) = small_block()?;
+
+        // A TX LOCKED PAST THE HEADER TIME CAN'T BE IN THIS BLOCK
+        let (_, raw_txs) = deserialise_block(&raw_block)?;
+        let mut tx = index[&raw_txs[1].txid].clone();
+        tx.locktime = u32::MAX;
+        tx.vin[0].sequence = 0;
+        let txid = tx.txid()?;
+        index.insert(txid, tx.clone());
+
+        let replaced = replace_tx(&raw_block, 1, &tx)?;
+        let errors = verify_block(&replaced, &index, SchnorrMode::Serial)?;
+        assert!(errors.contains(&BlockError::InvalidTx(
+            txid,
+            RejectReason::NonFinal(u32::MAX)
+        )));
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_witness_test() -> Result<()> {
+        let (raw_block, index) = small_block()?;
+
+        // THE SAME TXID WITH A BROKEN SIGNATURE IN THE WITNESS: THE MEMPOOL JSON IS STILL VALID,
+        // BUT THE SCRIPTS ARE RUN ON WHAT THE BLOCK HAS
+        let (_, raw_txs) = deserialise_block(&raw_block)?;
+        let (tx_index, raw_tx) = raw_txs
+            .iter()
+            .enumerate()
+            .skip(1)
+            .find(|(_, raw_tx)| raw_tx.has_witness())
+            .unwrap();
+        let mut tx = index[&raw_tx.txid].clone();
+        let witness = tx.vin[0].witness.as_mut().unwrap();
+        let signature = witness.iter_mut().find(|item| item.len() >= 128).unwrap();
+        let flipped = if &signature[20..22] == "00" {
+            "01"
+        } else {
+            "00"
+        };
+        signature.replace_range(20..22, flipped);
+        assert_eq!(tx.txid()?, raw_tx.txid);
+
+        let replaced = replace_tx(&raw_block, tx_index, &tx)?;
+        let errors = verify_block(&replaced, &index, SchnorrMode::Serial)?;
+        assert!(errors.contains(&BlockError::BadWitnessCommitment));
+        assert!(errors
+            .iter()
+            .any(|error| matches!(error, BlockError::InvalidTx(txid, _) if *txid == raw_tx.txid)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_range_amounts_test() -> Result<()> {
+        let (raw_block, mut index) = small_block()?;
+
+        // AN INPUT WORTH MORE THAN MAX_MONEY, THE FEE SUMS MUST NOT PANIC OR WRAP
+        let (_, raw_txs) = deserialise_block(&raw_block)?;
+        let txid = raw_txs[1].txid;
+        index.get_mut(&txid).unwrap().vin[0].prevout.value = u64::MAX;
+
+        let errors = verify_block(&raw_block, &index, SchnorrMode::Serial)?;
+        assert!(errors.contains(&BlockError::InvalidTx(
+            txid,
+            RejectReason::Amounts(AmountError::InputValuesOutOfRange)
+        )));
+        assert!(!errors.contains(&BlockError::NegativeFee(txid)));
+
+        // ITS FEE NO LONGER COUNTS, SO THE COINBASE CLAIMS TOO MUCH
+        assert!(matches!(
+            errors.last(),
+            Some(BlockError::BadCoinbaseAmount(_, _))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn signature_cache_reuse_test() -> Result<()> {
+        let (raw_block, index) = small_block()?;
+
+        // THE MEMPOOL PASS CACHES THE SIGNATURES OF THE TXS THE BLOCK WILL HOLD
+        let (_, raw_txs) = deserialise_block(&raw_block)?;
+        for raw_tx in raw_txs.iter().skip(1) {
+            let tx = index[&raw_tx.txid].clone();
+            assert!(verify_tx(tx, VerifyFlags::STANDARD, SchnorrMode::Serial)?);
+        }
+
+        // SO CHECKING THE BLOCK IN THE SAME PROCESS BARELY VERIFIES A SIGNATURE
+        let before = thread_stats();
+        assert_eq!(
+            verify_block(&raw_block, &index, SchnorrMode::Serial)?,
+            vec![]
+        );
+        let stats = thread_stats().since(before);
+        assert!(stats.lookups() >= raw_txs.len() as u64 - 1);
+        assert!(stats.hit_rate() >= 0.9, "{}", stats);
+
+        Ok(())
+    }
+
+    #[test]
+    fn block_schnorr_checks_test() -> Result<()> {
+        let (raw_block, mut index) = small_block()?;
+
+        // TWO KEY PATH INPUTS, SWAPPED IN FOR THE FIRST TX OF THE BLOCK
+        let path =
+            "./mempool/001035505afbf143e51bd667099190943a38eee20092bb691e72eaa44992b2f7.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let txid = tx.txid()?;
+        index.insert(txid, tx.clone());
+
+        let replaced = replace_tx(&raw_block, 1, &tx)?;
+        let errors = verify_block(&replaced, &index, SchnorrMode::Batch)?;
+        assert!(!errors
+            .iter()
+            .any(|error| matches!(error, BlockError::BadSchnorrSig(_, _))));
+
+        // THE SECOND SIGNATURE IS BROKEN IN THE BLOCK, ITS SCRIPT STILL PASSES SO THE WHOLE
+        // BLOCK'S BATCH FAILS, AND THE TX AND INPUT ARE FOUND ONE BY ONE
+        let mut broken = tx.clone();
+        let signature = &mut broken.vin[1].witness.as_mut().unwrap()[0];
+        let flipped = if &signature[20..22] == "00" {
+            "01"
+        } else {
+            "00"
+        };
+        signature.replace_range(20..22, flipped);
+        let replaced = replace_tx(&raw_block, 1, &broken)?;
+
+        for mode in [SchnorrMode::Batch, SchnorrMode::Serial] {
+            let errors = verify_block(&replaced, &index, mode)?;
+            assert!(errors.contains(&BlockError::BadSchnorrSig(txid, 1)));
+            assert!(!errors
+                .iter()
+                .any(|error| matches!(error, BlockError::InvalidTx(id, _) if *id == txid)));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn coinbase_height_test() {
+        assert_eq!(coinbase_height(&hex::decode("03a0bb0d18").unwrap()), 900000);
+        assert_eq!(coinbase_height(&[0x60, 0x01]), 16);
+        assert_eq!(coinbase_height(&[0x00, 0x01]), 0);
+    }
+}
@@ -0,0 +1,185 @@
+// 32 BYTE HASH TYPES WITH AN EXPLICIT BYTE ORDER
+// INTERNAL ORDER IS WHAT DOUBLE SHA256 PRODUCES AND WHAT GOES ON THE WIRE
+// DISPLAY ORDER IS THE REVERSED HEX SHOWN BY EXPLORERS AND USED IN THE MEMPOOL JSON
+
+use std::{fmt, str::FromStr};
+
+use failure::format_err;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::error::Result;
+
+macro_rules! hash_newtype {
+    ($name:ident) => {
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+        pub struct $name([u8; 32]);
+
+        impl $name {
+            pub fn all_zeros() -> Self {
+                $name([0u8; 32])
+            }
+
+            pub fn from_internal_bytes(bytes: [u8; 32]) -> Self {
+                $name(bytes)
+            }
+
+            // DOUBLE SHA256 OUTPUT IS ALREADY IN INTERNAL ORDER
+            pub fn from_hash(hash: &[u8]) -> Result<Self> {
+                let bytes: [u8; 32] = hash
+                    .try_into()
+                    .map_err(|_| format_err!("HASH MUST BE 32 BYTES, GOT {}", hash.len()))?;
+                Ok($name(bytes))
+            }
+
+            pub fn from_display_hex(display_hex: &str) -> Result<Self> {
+                let mut bytes: [u8; 32] = hex::decode(display_hex)?
+                    .try_into()
+                    .map_err(|_| format_err!("INVALID HASH: {}", display_hex))?;
+                bytes.reverse();
+                Ok($name(bytes))
+            }
+
+            pub fn as_internal_bytes(&self) -> &[u8; 32] {
+                &self.0
+            }
+
+            pub fn to_display_bytes(&self) -> [u8; 32] {
+                let mut bytes = self.0;
+                bytes.reverse();
+                bytes
+            }
+
+            // INTERNAL ORDER AS HEX, THE WAY IT APPEARS IN A SERIALISED HEADER OR TX
+            pub fn to_internal_hex(&self) -> String {
+                hex::encode(self.0)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}", hex::encode(self.to_display_bytes()))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = failure::Error;
+
+            fn from_str(display_hex: &str) -> Result<Self> {
+                $name::from_display_hex(display_hex)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.to_string())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error> {
+                let display_hex = String::deserialize(deserializer)?;
+                $name::from_display_hex(&display_hex).map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+hash_newtype!(Txid);
+hash_newtype!(Wtxid);
+hash_newtype!(BlockHash);
+
+// A REFERENCE TO ONE OUTPUT OF A PREVIOUS TRANSACTION
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct OutPoint {
+    pub txid: Txid,
+    pub vout: u32,
+}
+
+impl OutPoint {
+    pub fn new(txid: Txid, vout: u32) -> Self {
+        OutPoint { txid, vout }
+    }
+
+    // THE COINBASE INPUT SPENDS THIS NON-EXISTENT OUTPUT
+    pub fn null() -> Self {
+        OutPoint {
+            txid: Txid::all_zeros(),
+            vout: u32::MAX,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == OutPoint::null()
+    }
+
+    // 32 BYTE TXID IN INTERNAL ORDER FOLLOWED BY THE LITTLE ENDIAN OUTPUT INDEX
+    pub fn consensus_bytes(&self) -> [u8; 36] {
+        let mut bytes = [0u8; 36];
+        bytes[..32].copy_from_slice(self.txid.as_internal_bytes());
+        bytes[32..].copy_from_slice(&self.vout.to_le_bytes());
+        bytes
+    }
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.txid, self.vout)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::block_mine::serialise_tx::double_sha256;
+
+    use super::*;
+
+    #[test]
+    fn byte_order_test() -> Result<()> {
+        // GENESIS COINBASE
+        let display = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+        let txid: Txid = display.parse()?;
+
+        assert_eq!(txid.to_string(), display);
+        assert_eq!(txid.as_internal_bytes()[0], 0x3b);
+        assert_eq!(txid.to_display_bytes()[0], 0x4a);
+
+        // SERDE USES THE DISPLAY ORDER LIKE THE MEMPOOL JSON
+        let json = serde_json::to_string(&txid)?;
+        assert_eq!(json, format!("\"{}\"", display));
+        assert_eq!(serde_json::from_str::<Txid>(&json)?, txid);
+        assert!(serde_json::from_str::<Txid>("\"abcd\"").is_err());
+
+        let hash = double_sha256(b"");
+        assert_eq!(
+            Wtxid::from_hash(&hash)?.to_internal_hex(),
+            hex::encode(&hash)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn outpoint_test() -> Result<()> {
+        let txid = Txid::from_internal_bytes([0x11; 32]);
+        let outpoint = OutPoint::new(txid, 1);
+
+        assert_eq!(&outpoint.consensus_bytes()[..32], &[0x11; 32]);
+        assert_eq!(&outpoint.consensus_bytes()[32..], &[1, 0, 0, 0]);
+        assert_eq!(outpoint.to_string(), format!("{}:1", "11".repeat(32)));
+        assert!(OutPoint::null().is_null());
+        assert!(!outpoint.is_null());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,10 @@
+pub mod address;
+pub mod amount;
+pub mod block_mine;
+pub mod error;
+pub mod hashes;
+pub mod network;
+pub mod script;
+pub mod transaction;
+pub mod utxo;
+pub mod validation_checks;
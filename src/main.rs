@@ -1,23 +1,77 @@
-mod block_mine;
-mod error;
-mod transaction;
-mod validation_checks;
+use std::env;
 
-use crate::error::Result;
+use failure::format_err;
 
+use code_challenge_2024_lla_dane::error::Result;
+use code_challenge_2024_lla_dane::hashes::Txid;
 
-use crate::validation_checks::all_transaction_verification;
-use crate::block_mine::block::valid_block_header;
+use code_challenge_2024_lla_dane::block_mine::block::{
+    current_time, valid_block_header, BLOCK_HEIGHT,
+};
+use code_challenge_2024_lla_dane::block_mine::chain::{mine_chain, CHAIN_DIR};
+use code_challenge_2024_lla_dane::block_mine::merkle_proof::print_merkle_proof;
+use code_challenge_2024_lla_dane::block_mine::verify_block::{
+    mempool_index, read_block, verify_block_file,
+};
+use code_challenge_2024_lla_dane::utxo::{UtxoSet, MEMPOOL_TIP};
+use code_challenge_2024_lla_dane::validation_checks::{
+    all_transaction_verification,
+    locktime::BlockContext,
+    policy::{Policy, POLICY_FILE},
+    taproot::schnorr_bench,
+};
 
 fn main() -> Result<()> {
-    
-    // TRANSACTION VERIFICATION FUNCTION
-    all_transaction_verification()?;
+    let args: Vec<String> = env::args().collect();
+
+    // CHECK A MINED BLOCK WITHOUT MINING A NEW ONE: `verify-block [output.txt | block.dat | block.hex]`
+    if args.get(1).map(String::as_str) == Some("verify-block") {
+        let path = args.get(2).map(String::as_str).unwrap_or("./output.txt");
+        return verify_block_file(path, Policy::load(POLICY_FILE)?.schnorr_mode);
+    }
+
+    // SPV PROOF FOR ONE TX OF A MINED BLOCK: `merkle-proof <TXID> [output.txt | block.dat | block.hex]`
+    if args.get(1).map(String::as_str) == Some("merkle-proof") {
+        let txid: Txid = args
+            .get(2)
+            .ok_or(format_err!("USAGE: merkle-proof <TXID> [PATH]"))?
+            .parse()?;
+        let path = args.get(3).map(String::as_str).unwrap_or("./output.txt");
+        let raw_block = read_block(path, &mempool_index("./mempool")?)?;
+        return print_merkle_proof(&raw_block, &txid);
+    }
+
+    // SERIAL AGAINST BATCH VERIFICATION OF THE MEMPOOL'S TAPROOT SIGNATURES: `schnorr-bench`
+    if args.get(1).map(String::as_str) == Some("schnorr-bench") {
+        return schnorr_bench("./mempool");
+    }
+
+    // TRANSACTION VERIFICATION FUNCTION, FOR THE FIRST BLOCK THAT WILL BE MINED
+    let utxos = UtxoSet::from_mempool(&mempool_index("./mempool")?, MEMPOOL_TIP);
+    let policy = Policy::load(POLICY_FILE)?;
+    all_transaction_verification(
+        &BlockContext::new(BLOCK_HEIGHT, current_time()),
+        &utxos,
+        &policy,
+    )?;
 
     println!("TRANSACTION VERIFICATION: COMPLETED");
 
     // BLOCK MINING
-    valid_block_header()?;
+    let block_path = match args.get(1).map(String::as_str) {
+        // MINE N BLOCKS ON TOP OF EACH OTHER: `chain <N>`
+        Some("chain") => {
+            let n_blocks: u32 = args.get(2).map(|n| n.parse()).unwrap_or(Ok(1))?;
+            mine_chain(n_blocks)?;
+            // THE LATER BLOCKS SPEND COINS CONFIRMED BY THE EARLIER ONES, WHICH THE MEMPOOL LACKS
+            format!("{}/{}.dat", CHAIN_DIR, BLOCK_HEIGHT)
+        }
+        _ => {
+            valid_block_header()?;
+            "./block.dat".to_string()
+        }
+    };
 
-    Ok(())
+    // THE MINED BLOCK IS CHECKED IN THIS PROCESS, SO ITS SIGNATURES ARE ALREADY IN THE CACHE
+    verify_block_file(&block_path, policy.schnorr_mode)
 }
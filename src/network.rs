@@ -0,0 +1,95 @@
+// CHAIN SELECTION AND THE CONSENSUS PARAMETERS THAT DIFFER BETWEEN CHAINS
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Testnet4,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    // BASE58 VERSION BYTE OF P2PKH ADDRESSES
+    pub fn p2pkh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            _ => 0x6f,
+        }
+    }
+
+    // BASE58 VERSION BYTE OF P2SH ADDRESSES
+    pub fn p2sh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            _ => 0xc4,
+        }
+    }
+
+    // HUMAN READABLE PART OF SEGWIT ADDRESSES
+    pub fn bech32_hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Regtest => "bcrt",
+            _ => "tb",
+        }
+    }
+}
+
+// PROOF OF WORK PARAMETERS USED BY THE DIFFICULTY ADJUSTMENT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsensusParams {
+    // EASIEST ALLOWED TARGET IN COMPACT FORM
+    pub pow_limit_bits: u32,
+    // TWO WEEKS ON MAINNET
+    pub pow_target_timespan: u32,
+    // TEN MINUTES ON EVERY PUBLIC CHAIN
+    pub pow_target_spacing: u32,
+    // TESTNET 20-MINUTE RULE: A SLOW BLOCK MAY USE THE POW LIMIT
+    pub pow_allow_min_difficulty_blocks: bool,
+    // REGTEST NEVER RETARGETS
+    pub pow_no_retargeting: bool,
+    // BIP94 (TESTNET4): RETARGET FROM THE FIRST BLOCK OF THE PERIOD AND FORBID TIMEWARP
+    pub enforce_bip94: bool,
+}
+
+impl ConsensusParams {
+    pub fn for_network(network: Network) -> ConsensusParams {
+        let mainnet = ConsensusParams {
+            pow_limit_bits: 0x1d00ffff,
+            pow_target_timespan: 14 * 24 * 60 * 60,
+            pow_target_spacing: 10 * 60,
+            pow_allow_min_difficulty_blocks: false,
+            pow_no_retargeting: false,
+            enforce_bip94: false,
+        };
+
+        match network {
+            Network::Mainnet => mainnet,
+            Network::Testnet => ConsensusParams {
+                pow_allow_min_difficulty_blocks: true,
+                ..mainnet
+            },
+            Network::Testnet4 => ConsensusParams {
+                pow_allow_min_difficulty_blocks: true,
+                enforce_bip94: true,
+                ..mainnet
+            },
+            Network::Signet => ConsensusParams {
+                pow_limit_bits: 0x1e0377ae,
+                ..mainnet
+            },
+            Network::Regtest => ConsensusParams {
+                pow_limit_bits: 0x207fffff,
+                pow_allow_min_difficulty_blocks: true,
+                pow_no_retargeting: true,
+                ..mainnet
+            },
+        }
+    }
+
+    // NUMBER OF BLOCKS BETWEEN TWO RETARGETS (2016 ON MAINNET)
+    pub fn difficulty_adjustment_interval(&self) -> u32 {
+        self.pow_target_timespan / self.pow_target_spacing
+    }
+}
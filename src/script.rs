@@ -0,0 +1,763 @@
+// SCRIPTS PARSED FROM THEIR HEX INSTEAD OF TRUSTING THE ASM AND TYPE STRINGS IN THE MEMPOOL JSON
+// THE DISASSEMBLER FOLLOWS ESPLORA'S ASM FORMAT SO THE TWO CAN BE COMPARED
+
+use std::fmt;
+
+use failure::format_err;
+
+use crate::error::Result;
+
+pub const OP_0: u8 = 0x00;
+pub const OP_PUSHDATA1: u8 = 0x4c;
+pub const OP_PUSHDATA2: u8 = 0x4d;
+pub const OP_PUSHDATA4: u8 = 0x4e;
+pub const OP_1NEGATE: u8 = 0x4f;
+pub const OP_RESERVED: u8 = 0x50;
+pub const OP_1: u8 = 0x51;
+pub const OP_16: u8 = 0x60;
+pub const OP_NOP: u8 = 0x61;
+pub const OP_VER: u8 = 0x62;
+pub const OP_IF: u8 = 0x63;
+pub const OP_NOTIF: u8 = 0x64;
+pub const OP_VERIF: u8 = 0x65;
+pub const OP_VERNOTIF: u8 = 0x66;
+pub const OP_ELSE: u8 = 0x67;
+pub const OP_ENDIF: u8 = 0x68;
+pub const OP_VERIFY: u8 = 0x69;
+pub const OP_RETURN: u8 = 0x6a;
+pub const OP_TOALTSTACK: u8 = 0x6b;
+pub const OP_FROMALTSTACK: u8 = 0x6c;
+pub const OP_2DROP: u8 = 0x6d;
+pub const OP_2DUP: u8 = 0x6e;
+pub const OP_3DUP: u8 = 0x6f;
+pub const OP_2OVER: u8 = 0x70;
+pub const OP_2ROT: u8 = 0x71;
+pub const OP_2SWAP: u8 = 0x72;
+pub const OP_IFDUP: u8 = 0x73;
+pub const OP_DEPTH: u8 = 0x74;
+pub const OP_DROP: u8 = 0x75;
+pub const OP_DUP: u8 = 0x76;
+pub const OP_NIP: u8 = 0x77;
+pub const OP_OVER: u8 = 0x78;
+pub const OP_PICK: u8 = 0x79;
+pub const OP_ROLL: u8 = 0x7a;
+pub const OP_ROT: u8 = 0x7b;
+pub const OP_SWAP: u8 = 0x7c;
+pub const OP_TUCK: u8 = 0x7d;
+pub const OP_CAT: u8 = 0x7e;
+pub const OP_SUBSTR: u8 = 0x7f;
+pub const OP_LEFT: u8 = 0x80;
+pub const OP_RIGHT: u8 = 0x81;
+pub const OP_SIZE: u8 = 0x82;
+pub const OP_INVERT: u8 = 0x83;
+pub const OP_AND: u8 = 0x84;
+pub const OP_OR: u8 = 0x85;
+pub const OP_XOR: u8 = 0x86;
+pub const OP_EQUAL: u8 = 0x87;
+pub const OP_EQUALVERIFY: u8 = 0x88;
+pub const OP_RESERVED1: u8 = 0x89;
+pub const OP_RESERVED2: u8 = 0x8a;
+pub const OP_1ADD: u8 = 0x8b;
+pub const OP_1SUB: u8 = 0x8c;
+pub const OP_2MUL: u8 = 0x8d;
+pub const OP_2DIV: u8 = 0x8e;
+pub const OP_NEGATE: u8 = 0x8f;
+pub const OP_ABS: u8 = 0x90;
+pub const OP_NOT: u8 = 0x91;
+pub const OP_0NOTEQUAL: u8 = 0x92;
+pub const OP_ADD: u8 = 0x93;
+pub const OP_SUB: u8 = 0x94;
+pub const OP_MUL: u8 = 0x95;
+pub const OP_DIV: u8 = 0x96;
+pub const OP_MOD: u8 = 0x97;
+pub const OP_LSHIFT: u8 = 0x98;
+pub const OP_RSHIFT: u8 = 0x99;
+pub const OP_BOOLAND: u8 = 0x9a;
+pub const OP_BOOLOR: u8 = 0x9b;
+pub const OP_NUMEQUAL: u8 = 0x9c;
+pub const OP_NUMEQUALVERIFY: u8 = 0x9d;
+pub const OP_NUMNOTEQUAL: u8 = 0x9e;
+pub const OP_LESSTHAN: u8 = 0x9f;
+pub const OP_GREATERTHAN: u8 = 0xa0;
+pub const OP_LESSTHANOREQUAL: u8 = 0xa1;
+pub const OP_GREATERTHANOREQUAL: u8 = 0xa2;
+pub const OP_MIN: u8 = 0xa3;
+pub const OP_MAX: u8 = 0xa4;
+pub const OP_WITHIN: u8 = 0xa5;
+pub const OP_RIPEMD160: u8 = 0xa6;
+pub const OP_SHA1: u8 = 0xa7;
+pub const OP_SHA256: u8 = 0xa8;
+pub const OP_HASH160: u8 = 0xa9;
+pub const OP_HASH256: u8 = 0xaa;
+pub const OP_CODESEPARATOR: u8 = 0xab;
+pub const OP_CHECKSIG: u8 = 0xac;
+pub const OP_CHECKSIGVERIFY: u8 = 0xad;
+pub const OP_CHECKMULTISIG: u8 = 0xae;
+pub const OP_CHECKMULTISIGVERIFY: u8 = 0xaf;
+pub const OP_NOP1: u8 = 0xb0;
+pub const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+pub const OP_CHECKSEQUENCEVERIFY: u8 = 0xb2;
+pub const OP_NOP4: u8 = 0xb3;
+pub const OP_NOP10: u8 = 0xb9;
+pub const OP_CHECKSIGADD: u8 = 0xba;
+pub const OP_INVALIDOPCODE: u8 = 0xff;
+
+// ESPLORA'S NAME FOR AN OPCODE, PUSHES ARE NAMED BY THEIR LENGTH
+pub fn opcode_name(opcode: u8) -> String {
+    let name = match opcode {
+        OP_0 => "OP_0",
+        0x01..=0x4b => return format!("OP_PUSHBYTES_{}", opcode),
+        OP_PUSHDATA1 => "OP_PUSHDATA1",
+        OP_PUSHDATA2 => "OP_PUSHDATA2",
+        OP_PUSHDATA4 => "OP_PUSHDATA4",
+        OP_1NEGATE => "OP_PUSHNUM_NEG1",
+        OP_RESERVED => "OP_RESERVED",
+        OP_1..=OP_16 => return format!("OP_PUSHNUM_{}", opcode - 0x50),
+        OP_NOP => "OP_NOP",
+        OP_VER => "OP_VER",
+        OP_IF => "OP_IF",
+        OP_NOTIF => "OP_NOTIF",
+        OP_VERIF => "OP_VERIF",
+        OP_VERNOTIF => "OP_VERNOTIF",
+        OP_ELSE => "OP_ELSE",
+        OP_ENDIF => "OP_ENDIF",
+        OP_VERIFY => "OP_VERIFY",
+        OP_RETURN => "OP_RETURN",
+        OP_TOALTSTACK => "OP_TOALTSTACK",
+        OP_FROMALTSTACK => "OP_FROMALTSTACK",
+        OP_2DROP => "OP_2DROP",
+        OP_2DUP => "OP_2DUP",
+        OP_3DUP => "OP_3DUP",
+        OP_2OVER => "OP_2OVER",
+        OP_2ROT => "OP_2ROT",
+        OP_2SWAP => "OP_2SWAP",
+        OP_IFDUP => "OP_IFDUP",
+        OP_DEPTH => "OP_DEPTH",
+        OP_DROP => "OP_DROP",
+        OP_DUP => "OP_DUP",
+        OP_NIP => "OP_NIP",
+        OP_OVER => "OP_OVER",
+        OP_PICK => "OP_PICK",
+        OP_ROLL => "OP_ROLL",
+        OP_ROT => "OP_ROT",
+        OP_SWAP => "OP_SWAP",
+        OP_TUCK => "OP_TUCK",
+        OP_CAT => "OP_CAT",
+        OP_SUBSTR => "OP_SUBSTR",
+        OP_LEFT => "OP_LEFT",
+        OP_RIGHT => "OP_RIGHT",
+        OP_SIZE => "OP_SIZE",
+        OP_INVERT => "OP_INVERT",
+        OP_AND => "OP_AND",
+        OP_OR => "OP_OR",
+        OP_XOR => "OP_XOR",
+        OP_EQUAL => "OP_EQUAL",
+        OP_EQUALVERIFY => "OP_EQUALVERIFY",
+        OP_RESERVED1 => "OP_RESERVED1",
+        OP_RESERVED2 => "OP_RESERVED2",
+        OP_1ADD => "OP_1ADD",
+        OP_1SUB => "OP_1SUB",
+        OP_2MUL => "OP_2MUL",
+        OP_2DIV => "OP_2DIV",
+        OP_NEGATE => "OP_NEGATE",
+        OP_ABS => "OP_ABS",
+        OP_NOT => "OP_NOT",
+        OP_0NOTEQUAL => "OP_0NOTEQUAL",
+        OP_ADD => "OP_ADD",
+        OP_SUB => "OP_SUB",
+        OP_MUL => "OP_MUL",
+        OP_DIV => "OP_DIV",
+        OP_MOD => "OP_MOD",
+        OP_LSHIFT => "OP_LSHIFT",
+        OP_RSHIFT => "OP_RSHIFT",
+        OP_BOOLAND => "OP_BOOLAND",
+        OP_BOOLOR => "OP_BOOLOR",
+        OP_NUMEQUAL => "OP_NUMEQUAL",
+        OP_NUMEQUALVERIFY => "OP_NUMEQUALVERIFY",
+        OP_NUMNOTEQUAL => "OP_NUMNOTEQUAL",
+        OP_LESSTHAN => "OP_LESSTHAN",
+        OP_GREATERTHAN => "OP_GREATERTHAN",
+        OP_LESSTHANOREQUAL => "OP_LESSTHANOREQUAL",
+        OP_GREATERTHANOREQUAL => "OP_GREATERTHANOREQUAL",
+        OP_MIN => "OP_MIN",
+        OP_MAX => "OP_MAX",
+        OP_WITHIN => "OP_WITHIN",
+        OP_RIPEMD160 => "OP_RIPEMD160",
+        OP_SHA1 => "OP_SHA1",
+        OP_SHA256 => "OP_SHA256",
+        OP_HASH160 => "OP_HASH160",
+        OP_HASH256 => "OP_HASH256",
+        OP_CODESEPARATOR => "OP_CODESEPARATOR",
+        OP_CHECKSIG => "OP_CHECKSIG",
+        OP_CHECKSIGVERIFY => "OP_CHECKSIGVERIFY",
+        OP_CHECKMULTISIG => "OP_CHECKMULTISIG",
+        OP_CHECKMULTISIGVERIFY => "OP_CHECKMULTISIGVERIFY",
+        OP_NOP1 => "OP_NOP1",
+        OP_CHECKLOCKTIMEVERIFY => "OP_CLTV",
+        OP_CHECKSEQUENCEVERIFY => "OP_CSV",
+        OP_NOP4..=OP_NOP10 => return format!("OP_NOP{}", opcode - OP_NOP1 + 1),
+        OP_CHECKSIGADD => "OP_CHECKSIGADD",
+        OP_INVALIDOPCODE => "OP_INVALIDOPCODE",
+        _ => return format!("OP_RETURN_{}", opcode),
+    };
+    name.to_string()
+}
+
+// ONE STEP OF A SCRIPT: A DATA PUSH WITH THE OPCODE THAT PUSHED IT, OR ANY OTHER OPCODE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction<'a> {
+    Push(u8, &'a [u8]),
+    Op(u8),
+}
+
+impl Instruction<'_> {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Push(opcode, _) | Instruction::Op(opcode) => *opcode,
+        }
+    }
+
+    pub fn push_data(&self) -> Option<&[u8]> {
+        match self {
+            Instruction::Push(_, data) => Some(data),
+            Instruction::Op(_) => None,
+        }
+    }
+
+    // THE DATA IS PUSHED WITH THE SHORTEST OPCODE THAT CAN PUSH IT, LIKE CheckMinimalPush
+    pub fn is_minimal_push(&self) -> bool {
+        let (opcode, data) = match self {
+            Instruction::Push(opcode, data) => (*opcode, *data),
+            Instruction::Op(_) => return true,
+        };
+        match data {
+            [] => opcode == OP_0,
+            [1..=16] | [0x81] => false,
+            _ if data.len() < OP_PUSHDATA1 as usize => opcode as usize == data.len(),
+            _ if data.len() <= 0xff => opcode == OP_PUSHDATA1,
+            _ if data.len() <= 0xffff => opcode == OP_PUSHDATA2,
+            _ => true,
+        }
+    }
+}
+
+// A PUSH THAT RUNS PAST THE END OF THE SCRIPT, WITH THE OPCODE THAT STARTED IT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    // THE LENGTH OF AN OP_PUSHDATA IS CUT OFF
+    UnexpectedEnd(u8),
+    // THE PUSHED DATA IS CUT OFF
+    PushPastEnd(u8),
+}
+
+impl ParseError {
+    pub fn opcode(&self) -> u8 {
+        match self {
+            ParseError::UnexpectedEnd(opcode) | ParseError::PushPastEnd(opcode) => *opcode,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedEnd(_) => write!(f, "<unexpected end>"),
+            ParseError::PushPastEnd(_) => write!(f, "<push past end>"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// WALKS A SCRIPT ONE INSTRUCTION AT A TIME, STOPS AFTER THE FIRST ERROR
+pub struct Instructions<'a> {
+    script: &'a [u8],
+    index: usize,
+}
+
+impl<'a> Instructions<'a> {
+    // BYTE OFFSET OF THE NEXT INSTRUCTION
+    pub fn position(&self) -> usize {
+        self.index
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = std::result::Result<Instruction<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let script = self.script;
+        let opcode = *script.get(self.index)?;
+        self.index += 1;
+
+        let push_len = match opcode {
+            0x01..=0x4b => opcode as usize,
+            OP_PUSHDATA1..=OP_PUSHDATA4 => {
+                let size_len = 1 << (opcode - OP_PUSHDATA1);
+                if self.index + size_len > script.len() {
+                    self.index = script.len();
+                    return Some(Err(ParseError::UnexpectedEnd(opcode)));
+                }
+                let mut size_bytes = [0u8; 4];
+                size_bytes[..size_len].copy_from_slice(&script[self.index..self.index + size_len]);
+                self.index += size_len;
+                u32::from_le_bytes(size_bytes) as usize
+            }
+            OP_0 => 0,
+            _ => return Some(Ok(Instruction::Op(opcode))),
+        };
+
+        if push_len > script.len() - self.index {
+            self.index = script.len();
+            return Some(Err(ParseError::PushPastEnd(opcode)));
+        }
+
+        let data = &script[self.index..self.index + push_len];
+        self.index += push_len;
+        Some(Ok(Instruction::Push(opcode, data)))
+    }
+}
+
+// STANDARD TEMPLATES A SCRIPT PUB KEY CAN MATCH, AS IN BITCOIN CORE'S Solver
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptType {
+    P2pk,
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    Multisig,
+    OpReturn,
+    WitnessUnknown,
+    NonStandard,
+}
+
+impl fmt::Display for ScriptType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ScriptType::P2pk => "p2pk",
+            ScriptType::P2pkh => "p2pkh",
+            ScriptType::P2sh => "p2sh",
+            ScriptType::P2wpkh => "p2wpkh",
+            ScriptType::P2wsh => "p2wsh",
+            ScriptType::P2tr => "p2tr",
+            ScriptType::Multisig => "multisig",
+            ScriptType::OpReturn => "op_return",
+            ScriptType::WitnessUnknown => "witness_unknown",
+            ScriptType::NonStandard => "nonstandard",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Script(Vec<u8>);
+
+impl Script {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Script(bytes)
+    }
+
+    pub fn from_hex(script_hex: &str) -> Result<Self> {
+        Ok(Script(hex::decode(script_hex)?))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.0)
+    }
+
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions {
+            script: &self.0,
+            index: 0,
+        }
+    }
+
+    // NOTHING BUT PUSHES, OP_1NEGATE, OP_RESERVED AND OP_1..OP_16, LIKE IsPushOnly
+    pub fn is_push_only(&self) -> bool {
+        self.instructions().all(|instruction| match instruction {
+            Ok(instruction) => instruction.opcode() <= OP_16,
+            Err(_) => false,
+        })
+    }
+
+    // DATA OF EVERY PUSH, NONE IF THE SCRIPT IS NOT PUSH ONLY OR IS TRUNCATED
+    pub fn push_data(&self) -> Option<Vec<&[u8]>> {
+        let mut pushes = Vec::new();
+        for instruction in self.instructions() {
+            match instruction.ok()? {
+                Instruction::Push(_, data) => pushes.push(data),
+                Instruction::Op(_) => return None,
+            }
+        }
+        Some(pushes)
+    }
+
+    // (VERSION, PROGRAM) OF A SEGWIT OUTPUT: OP_0..OP_16 FOLLOWED BY A SINGLE 2 TO 40 BYTE PUSH
+    pub fn witness_program(&self) -> Option<(u8, &[u8])> {
+        let bytes = &self.0;
+        if bytes.len() < 4 || bytes.len() > 42 || bytes[1] as usize != bytes.len() - 2 {
+            return None;
+        }
+        match bytes[0] {
+            OP_0 => Some((0, &bytes[2..])),
+            OP_1..=OP_16 => Some((bytes[0] - 0x50, &bytes[2..])),
+            _ => None,
+        }
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        let bytes = &self.0;
+
+        if let Some((version, program)) = self.witness_program() {
+            return match (version, program.len()) {
+                (0, 20) => ScriptType::P2wpkh,
+                (0, 32) => ScriptType::P2wsh,
+                (0, _) => ScriptType::NonStandard,
+                (1, 32) => ScriptType::P2tr,
+                _ => ScriptType::WitnessUnknown,
+            };
+        }
+
+        if bytes.len() == 25
+            && bytes[..3] == [OP_DUP, OP_HASH160, 0x14]
+            && bytes[23..] == [OP_EQUALVERIFY, OP_CHECKSIG]
+        {
+            return ScriptType::P2pkh;
+        }
+
+        if bytes.len() == 23 && bytes[..2] == [OP_HASH160, 0x14] && bytes[22] == OP_EQUAL {
+            return ScriptType::P2sh;
+        }
+
+        if bytes.first() == Some(&OP_RETURN) && Script(bytes[1..].to_vec()).is_push_only() {
+            return ScriptType::OpReturn;
+        }
+
+        let instructions: Vec<Instruction> = match self.instructions().collect() {
+            Ok(instructions) => instructions,
+            Err(_) => return ScriptType::NonStandard,
+        };
+
+        match instructions.as_slice() {
+            [Instruction::Push(_, pubkey), Instruction::Op(OP_CHECKSIG)]
+                if is_pubkey_size(pubkey) =>
+            {
+                ScriptType::P2pk
+            }
+            [Instruction::Op(m), pubkeys @ .., Instruction::Op(n), Instruction::Op(OP_CHECKMULTISIG)]
+                if is_multisig(*m, pubkeys, *n) =>
+            {
+                ScriptType::Multisig
+            }
+            _ => ScriptType::NonStandard,
+        }
+    }
+
+    // THE scriptpubkey_type STRING ESPLORA WOULD GIVE THIS SCRIPT
+    pub fn esplora_type(&self) -> &'static str {
+        if self.is_empty() {
+            return "empty";
+        }
+        match self.script_type() {
+            ScriptType::P2pk => "p2pk",
+            ScriptType::P2pkh => "p2pkh",
+            ScriptType::P2sh => "p2sh",
+            ScriptType::P2wpkh => "v0_p2wpkh",
+            ScriptType::P2wsh => "v0_p2wsh",
+            ScriptType::P2tr => "v1_p2tr",
+            ScriptType::OpReturn => "op_return",
+            _ if self.0[0] == OP_RETURN => "op_return",
+            _ => "unknown",
+        }
+    }
+
+    pub fn to_asm(&self) -> String {
+        let mut asm: Vec<String> = Vec::new();
+
+        for instruction in self.instructions() {
+            match instruction {
+                Ok(Instruction::Push(opcode, data)) if !data.is_empty() => {
+                    asm.push(format!("{} {}", opcode_name(opcode), hex::encode(data)));
+                }
+                Ok(instruction) => asm.push(opcode_name(instruction.opcode())),
+                // ESPLORA STILL NAMES THE OPCODE OF A TRUNCATED PUSH
+                Err(e) => asm.push(format!("{} {}", opcode_name(e.opcode()), e)),
+            }
+        }
+
+        asm.join(" ")
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_asm())
+    }
+}
+
+// CScriptNum: LITTLE ENDIAN WITH THE SIGN IN THE TOP BIT OF THE LAST BYTE, AT MOST `max_len` BYTES
+pub fn decode_script_num(bytes: &[u8], max_len: usize) -> Result<i64> {
+    if bytes.len() > max_len {
+        return Err(format_err!(
+            "SCRIPT NUMBER OVERFLOW: {} BYTES, AT MOST {}",
+            bytes.len(),
+            max_len
+        ));
+    }
+
+    let mut magnitude: i64 = 0;
+    for (index, byte) in bytes.iter().enumerate() {
+        magnitude |= (*byte as i64) << (8 * index);
+    }
+
+    match bytes.last() {
+        Some(last) if last & 0x80 != 0 => Ok(-(magnitude & !(0x80 << (8 * (bytes.len() - 1))))),
+        _ => Ok(magnitude),
+    }
+}
+
+// NO NEEDLESS ZERO BYTE AT THE END, IT MAY ONLY BE THERE TO HOLD THE SIGN
+pub fn is_minimal_script_num(bytes: &[u8]) -> bool {
+    match bytes {
+        [] => true,
+        [.., last] if last & 0x7f != 0 => true,
+        [.., before, _] => before & 0x80 != 0,
+        [_] => false,
+    }
+}
+
+// THE SHORTEST CScriptNum ENCODING OF `value`, ZERO IS THE EMPTY ITEM
+pub fn encode_script_num(value: i64) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut magnitude = value.unsigned_abs();
+    while magnitude > 0 {
+        bytes.push((magnitude & 0xff) as u8);
+        magnitude >>= 8;
+    }
+
+    // THE SIGN NEEDS A BYTE OF ITS OWN IF THE TOP BIT IS ALREADY TAKEN
+    match bytes.last_mut() {
+        Some(last) if *last & 0x80 != 0 => bytes.push(if value < 0 { 0x80 } else { 0x00 }),
+        Some(last) if value < 0 => *last |= 0x80,
+        _ => {}
+    }
+
+    bytes
+}
+
+// `data` AS ONE PUSH, LIKE CScript() << data: THE SMALLEST PUSHDATA THAT FITS, NEVER OP_1..OP_16
+pub fn encode_push(data: &[u8]) -> Vec<u8> {
+    let mut push: Vec<u8> = Vec::new();
+    match data.len() {
+        len if len < OP_PUSHDATA1 as usize => push.push(len as u8),
+        len if len <= 0xff => push.extend([OP_PUSHDATA1, len as u8]),
+        len if len <= 0xffff => {
+            push.push(OP_PUSHDATA2);
+            push.extend((len as u16).to_le_bytes());
+        }
+        len => {
+            push.push(OP_PUSHDATA4);
+            push.extend((len as u32).to_le_bytes());
+        }
+    }
+    push.extend_from_slice(data);
+    push
+}
+
+fn is_pubkey_size(pubkey: &[u8]) -> bool {
+    pubkey.len() == 33 || pubkey.len() == 65
+}
+
+// OP_m <n PUBKEYS> OP_n WITH 1 <= m <= n <= 16
+fn is_multisig(m: u8, pubkeys: &[Instruction], n: u8) -> bool {
+    if !(OP_1..=OP_16).contains(&m) || !(OP_1..=OP_16).contains(&n) || m > n {
+        return false;
+    }
+    pubkeys.len() == (n - 0x50) as usize
+        && pubkeys.iter().all(|instruction| match instruction {
+            Instruction::Push(_, pubkey) => is_pubkey_size(pubkey),
+            Instruction::Op(_) => false,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use walkdir::WalkDir;
+
+    use crate::transaction::Transaction;
+
+    use super::*;
+
+    #[test]
+    fn script_num_test() -> Result<()> {
+        let cases: [(&str, i64); 7] = [
+            ("", 0),
+            ("01", 1),
+            ("81", -1),
+            ("8000", 128),
+            ("80ca00", 51840),
+            ("ffffff7f", i32::MAX as i64),
+            ("ffffffff00", u32::MAX as i64),
+        ];
+
+        for (hex_num, value) in cases.iter() {
+            assert_eq!(decode_script_num(&hex::decode(hex_num)?, 5)?, *value);
+        }
+
+        // CLTV AND CSV TAKE 5 BYTE OPERANDS, EVERYTHING ELSE 4
+        assert!(decode_script_num(&hex::decode("ffffffff00")?, 4).is_err());
+
+        for (hex_num, value) in cases.iter() {
+            assert_eq!(hex::encode(encode_script_num(*value)), *hex_num);
+        }
+        assert_eq!(hex::encode(encode_script_num(-128)), "8080");
+
+        // EVERY ENCODING ABOVE IS MINIMAL, PADDED ZEROS AND NEGATIVE ZERO ARE NOT
+        for (hex_num, _) in cases.iter() {
+            assert!(is_minimal_script_num(&hex::decode(hex_num)?));
+        }
+        for hex_num in ["00", "80", "0100", "ff0000", "0180"] {
+            assert!(!is_minimal_script_num(&hex::decode(hex_num)?));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn minimal_push_test() -> Result<()> {
+        let minimal = |hex_script: &str| -> Result<bool> {
+            let script = Script::from_hex(hex_script)?;
+            let mut instructions = script.instructions();
+            Ok(instructions.next().unwrap().unwrap().is_minimal_push())
+        };
+
+        assert!(minimal("00")?);
+        assert!(minimal("0100")?);
+        assert!(minimal("0111")?);
+        assert!(minimal("51")?);
+        assert!(!minimal("0101")?);
+        assert!(!minimal("0181")?);
+        assert!(!minimal("4c00")?);
+        assert!(!minimal("4c0111")?);
+        assert!(minimal(&format!("4c4c{}", "11".repeat(76)))?);
+        assert!(!minimal(&format!("4dff00{}", "11".repeat(255)))?);
+        assert!(minimal(&format!("4d0001{}", "11".repeat(256)))?);
+
+        // WHAT encode_push WRITES IS ALWAYS MINIMAL, EXCEPT 0x01..0x10 WHICH OP_1..OP_16 COULD PUSH
+        assert_eq!(encode_push(&[]), vec![OP_0]);
+        assert_eq!(encode_push(&[1]), vec![1, 1]);
+        for len in [0x4b, 0x4c, 0xff, 0x100] {
+            assert!(minimal(&hex::encode(encode_push(&vec![0x11; len])))?);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn classify_test() -> Result<()> {
+        let pubkey = format!("21{}", "02".repeat(33));
+        let cases = [
+            (format!("{}ac", pubkey), ScriptType::P2pk),
+            (format!("76a914{}88ac", "11".repeat(20)), ScriptType::P2pkh),
+            (format!("a914{}87", "11".repeat(20)), ScriptType::P2sh),
+            (format!("0014{}", "11".repeat(20)), ScriptType::P2wpkh),
+            (format!("0020{}", "11".repeat(32)), ScriptType::P2wsh),
+            (format!("5120{}", "11".repeat(32)), ScriptType::P2tr),
+            (
+                format!("5210{}", "11".repeat(16)),
+                ScriptType::WitnessUnknown,
+            ),
+            (format!("0010{}", "11".repeat(16)), ScriptType::NonStandard),
+            (format!("51{}{}52ae", pubkey, pubkey), ScriptType::Multisig),
+            (format!("52{}51ae", pubkey), ScriptType::NonStandard),
+            ("6a0401020304".to_string(), ScriptType::OpReturn),
+            ("6a76".to_string(), ScriptType::NonStandard),
+            ("76a9".to_string(), ScriptType::NonStandard),
+        ];
+
+        for (script_hex, script_type) in cases {
+            assert_eq!(
+                Script::from_hex(&script_hex)?.script_type(),
+                script_type,
+                "{}",
+                script_hex
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn asm_test() -> Result<()> {
+        let script = Script::from_hex("004c0201024f60b1b2ba")?;
+        assert_eq!(
+            script.to_asm(),
+            "OP_0 OP_PUSHDATA1 0102 OP_PUSHNUM_NEG1 OP_PUSHNUM_16 OP_CLTV OP_CSV OP_CHECKSIGADD"
+        );
+
+        assert_eq!(
+            Script::from_hex("0201")?.to_asm(),
+            "OP_PUSHBYTES_2 <push past end>"
+        );
+        assert_eq!(
+            Script::from_hex("754d01")?.to_asm(),
+            "OP_DROP OP_PUSHDATA2 <unexpected end>"
+        );
+        assert!(!Script::from_hex("0201")?.is_push_only());
+        assert_eq!(Script::default().to_asm(), "");
+
+        Ok(())
+    }
+
+    // EVERY SCRIPT IN THE MEMPOOL DISASSEMBLES AND CLASSIFIES THE SAME WAY ESPLORA DID
+    #[test]
+    fn mempool_asm_test() -> Result<()> {
+        for entry in WalkDir::new("./mempool").into_iter().filter_map(|e| e.ok()) {
+            if !entry.path().is_file() {
+                continue;
+            }
+            let tx: Transaction = serde_json::from_str(&fs::read_to_string(entry.path())?)?;
+
+            for input in tx.vin.iter() {
+                let script_pubkey = input.prevout.script_pubkey()?;
+                assert_eq!(script_pubkey.to_asm(), input.prevout.scriptpubkey_asm);
+                assert_eq!(
+                    script_pubkey.esplora_type(),
+                    input.prevout.scriptpubkey_type
+                );
+
+                if let Some(scriptsig_asm) = &input.scriptsig_asm {
+                    assert_eq!(&input.script_sig()?.to_asm(), scriptsig_asm);
+                }
+                if let Some(inner_redeemscript_asm) = &input.inner_redeemscript_asm {
+                    let redeem_script = input.redeem_script()?.unwrap_or_default();
+                    assert_eq!(&redeem_script.to_asm(), inner_redeemscript_asm);
+                }
+            }
+
+            for output in tx.vout.iter() {
+                let script_pubkey = output.script_pubkey()?;
+                assert_eq!(script_pubkey.to_asm(), output.scriptpubkey_asm);
+                assert_eq!(script_pubkey.esplora_type(), output.scriptpubkey_type);
+            }
+        }
+
+        Ok(())
+    }
+}
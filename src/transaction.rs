@@ -1,6 +1,15 @@
-
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    block_mine::serialise_tx::{compact_size, double_sha256},
+    error::Result,
+    hashes::{OutPoint, Txid},
+    script::Script,
+};
+
+// BIP141: A BYTE OUTSIDE THE WITNESS WEIGHS 4, A WITNESS BYTE 1
+const WITNESS_SCALE_FACTOR: usize = 4;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Transaction {
     pub version: i32,
@@ -9,17 +18,112 @@ pub struct Transaction {
     pub vout: Vec<Output>,
 }
 
+// THE *_asm AND scriptpubkey_type FIELDS ARE AS SUPPLIED BY THE JSON, VALIDATION USES THE PARSED SCRIPTS
+impl Transaction {
+    // CONSENSUS SERIALISATION WITHOUT MARKER, FLAG AND WITNESSES, WHAT THE TXID COMMITS TO
+    pub fn serialise_base(&self) -> Result<Vec<u8>> {
+        let mut raw_tx: Vec<u8> = Vec::new();
+        raw_tx.extend(self.version.to_le_bytes());
+
+        raw_tx.extend(compact_size(self.vin.len() as u64));
+        for input in self.vin.iter() {
+            let script_sig = input.script_sig()?;
+            raw_tx.extend_from_slice(&input.outpoint().consensus_bytes());
+            raw_tx.extend(compact_size(script_sig.len() as u64));
+            raw_tx.extend_from_slice(script_sig.as_bytes());
+            raw_tx.extend(input.sequence.to_le_bytes());
+        }
+
+        raw_tx.extend(compact_size(self.vout.len() as u64));
+        for output in self.vout.iter() {
+            let script_pubkey = output.script_pubkey()?;
+            raw_tx.extend(output.value.to_le_bytes());
+            raw_tx.extend(compact_size(script_pubkey.len() as u64));
+            raw_tx.extend_from_slice(script_pubkey.as_bytes());
+        }
+
+        raw_tx.extend(self.locktime.to_le_bytes());
+        Ok(raw_tx)
+    }
+
+    pub fn txid(&self) -> Result<Txid> {
+        Txid::from_hash(&double_sha256(&self.serialise_base()?))
+    }
+
+    pub fn base_size(&self) -> Result<usize> {
+        Ok(self.serialise_base()?.len())
+    }
+
+    // SERIALISED SIZE WITH THE WITNESSES, IF ANY INPUT HAS ONE
+    pub fn total_size(&self) -> Result<usize> {
+        let base_size = self.base_size()?;
+        if self
+            .vin
+            .iter()
+            .all(|input| input.witness_items().is_empty())
+        {
+            return Ok(base_size);
+        }
+
+        // MARKER AND FLAG
+        let mut witness_size = 2;
+        for input in self.vin.iter() {
+            let items = input.witness_items();
+            witness_size += compact_size(items.len() as u64).len();
+            for item in items {
+                let item = hex::decode(item)?.len();
+                witness_size += compact_size(item as u64).len() + item;
+            }
+        }
+
+        Ok(base_size + witness_size)
+    }
+
+    pub fn weight(&self) -> Result<usize> {
+        Ok(self.base_size()? * (WITNESS_SCALE_FACTOR - 1) + self.total_size()?)
+    }
+
+    // VIRTUAL SIZE: WEIGHT / 4 ROUNDED UP
+    pub fn vsize(&self) -> Result<usize> {
+        Ok(self.weight()?.div_ceil(WITNESS_SCALE_FACTOR))
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Input {
-    pub txid: String,
+    pub txid: Txid,
     pub vout: u32,
-    pub prevout: Prevout, 
+    pub prevout: Prevout,
     pub scriptsig: Option<String>,
     pub scriptsig_asm: Option<String>,
     pub witness: Option<Vec<String>>,
     pub is_coinbase: bool,
     pub sequence: u32,
-    pub inner_redeemscript_asm: Option<String>, 
+    pub inner_redeemscript_asm: Option<String>,
+}
+
+impl Input {
+    pub fn outpoint(&self) -> OutPoint {
+        OutPoint::new(self.txid, self.vout)
+    }
+
+    // PARSED FROM THE HEX, EMPTY FOR NATIVE SEGWIT INPUTS
+    pub fn script_sig(&self) -> Result<Script> {
+        Script::from_hex(self.scriptsig.as_deref().unwrap_or_default())
+    }
+
+    // THE WITNESS HEX ITEMS, EMPTY FOR LEGACY INPUTS
+    pub fn witness_items(&self) -> &[String] {
+        self.witness.as_deref().unwrap_or_default()
+    }
+
+    // THE LAST PUSH OF A PUSH-ONLY SCRIPT SIG, WHICH A P2SH INPUT REVEALS AS ITS REDEEM SCRIPT
+    pub fn redeem_script(&self) -> Result<Option<Script>> {
+        let script_sig = self.script_sig()?;
+        Ok(script_sig
+            .push_data()
+            .and_then(|pushes| pushes.last().map(|data| Script::new(data.to_vec()))))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -31,6 +135,12 @@ pub struct Prevout {
     pub value: u64,
 }
 
+impl Prevout {
+    pub fn script_pubkey(&self) -> Result<Script> {
+        Script::from_hex(&self.scriptpubkey)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Output {
     pub scriptpubkey: String,
@@ -39,3 +149,9 @@ pub struct Output {
     pub scriptpubkey_address: Option<String>,
     pub value: u64,
 }
+
+impl Output {
+    pub fn script_pubkey(&self) -> Result<Script> {
+        Script::from_hex(&self.scriptpubkey)
+    }
+}
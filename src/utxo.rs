@@ -0,0 +1,127 @@
+// THE MEMPOOL JSON GIVES THE SCRIPT AND VALUE OF EVERY SPENT COIN, BUT NOT WHEN IT WAS MINED
+// BIP68 RELATIVE LOCKS NEED THAT, SO IT COMES FROM A SEPARATE UTXO SOURCE
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    hashes::{OutPoint, Txid},
+    transaction::Transaction,
+    validation_checks::locktime::BlockContext,
+};
+
+// THE CHAIN TIP WHEN THE MEMPOOL WAS TAKEN: THE HIGHEST ANTI FEE SNIPING HEIGHT AND TIME LOCK IN IT
+// COINS FROM OUTSIDE THE MEMPOOL WERE MINED AT OR BEFORE THIS BLOCK, SO IT IS THE YOUNGEST THEY CAN BE
+pub const MEMPOOL_TIP: BlockContext = BlockContext {
+    height: 834637,
+    median_time_past: 1710300751,
+};
+
+pub trait UtxoSource {
+    // THE BLOCK THE COIN WAS MINED IN, NONE IF IT IS NOT CONFIRMED
+    fn coin_context(&self, outpoint: &OutPoint) -> Option<BlockContext>;
+}
+
+// UNSPENT COINS WITH THE HEIGHT AND MEDIAN TIME PAST OF THE BLOCK THAT MINED THEM
+#[derive(Debug, Clone, Default)]
+pub struct UtxoSet {
+    coins: HashMap<OutPoint, BlockContext>,
+}
+
+impl UtxoSet {
+    pub fn new() -> Self {
+        UtxoSet::default()
+    }
+
+    // EVERY COIN THE MEMPOOL SPENDS THAT NO MEMPOOL TX CREATES, TAKEN AS MINED IN `tip`
+    // OUTPUTS OF MEMPOOL TXS ARE LEFT OUT, THEY ARE UNCONFIRMED
+    pub fn from_mempool<'a>(
+        mempool: impl IntoIterator<Item = (&'a Txid, &'a Transaction)>,
+        tip: BlockContext,
+    ) -> Self {
+        let mempool: Vec<(&Txid, &Transaction)> = mempool.into_iter().collect();
+        let unconfirmed: HashSet<&Txid> = mempool.iter().map(|(txid, _)| *txid).collect();
+
+        let mut utxos = UtxoSet::new();
+        for (_, tx) in mempool.iter() {
+            for input in tx.vin.iter() {
+                if !unconfirmed.contains(&input.txid) {
+                    utxos.insert(input.outpoint(), tip);
+                }
+            }
+        }
+
+        utxos
+    }
+
+    pub fn insert(&mut self, outpoint: OutPoint, context: BlockContext) {
+        self.coins.insert(outpoint, context);
+    }
+
+    // A TX MINED IN THE BLOCK OF `context` SPENDS ITS INPUTS AND CREATES ITS OUTPUTS
+    pub fn connect_tx(&mut self, txid: &Txid, tx: &Transaction, context: BlockContext) {
+        for input in tx.vin.iter() {
+            self.coins.remove(&input.outpoint());
+        }
+        for vout in 0..tx.vout.len() {
+            self.insert(OutPoint::new(*txid, vout as u32), context);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.coins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coins.is_empty()
+    }
+}
+
+impl UtxoSource for UtxoSet {
+    fn coin_context(&self, outpoint: &OutPoint) -> Option<BlockContext> {
+        self.coins.get(outpoint).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::error::Result;
+
+    use super::*;
+
+    #[test]
+    fn utxo_set_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let parent: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let parent_txid = Txid::from_internal_bytes([1; 32]);
+
+        // A CHILD OF A MEMPOOL TX SPENDS AN UNCONFIRMED COIN
+        let mut child = parent.clone();
+        child.vin.truncate(1);
+        child.vin[0].txid = parent_txid;
+        child.vin[0].vout = 0;
+        let child_txid = Txid::from_internal_bytes([2; 32]);
+
+        let mut utxos = UtxoSet::from_mempool(
+            [(&parent_txid, &parent), (&child_txid, &child)],
+            MEMPOOL_TIP,
+        );
+        assert_eq!(utxos.len(), parent.vin.len());
+        assert_eq!(
+            utxos.coin_context(&parent.vin[0].outpoint()),
+            Some(MEMPOOL_TIP)
+        );
+        assert_eq!(utxos.coin_context(&child.vin[0].outpoint()), None);
+
+        // ONCE THE PARENT IS MINED ITS OUTPUTS ARE CONFIRMED AND ITS INPUTS ARE GONE
+        let block = BlockContext::new(900000, 1750000000);
+        utxos.connect_tx(&parent_txid, &parent, block);
+        assert_eq!(utxos.len(), parent.vout.len());
+        assert_eq!(utxos.coin_context(&child.vin[0].outpoint()), Some(block));
+        assert_eq!(utxos.coin_context(&parent.vin[0].outpoint()), None);
+
+        Ok(())
+    }
+}
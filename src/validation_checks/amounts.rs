@@ -0,0 +1,140 @@
+// THE CONSENSUS RULES ON THE AMOUNTS OF A TX, FROM BITCOIN CORE'S CheckTransaction AND CheckTxInputs
+// EVERY OUTPUT, THE OUTPUTS TOGETHER AND THE INPUTS TOGETHER STAY IN MoneyRange, AND THE FEE IS
+// NOT NEGATIVE
+
+use std::fmt;
+
+use crate::{amount::Amount, transaction::Transaction};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmountError {
+    // bad-txns-vout-toolarge: THE INDEX AND VALUE OF THE OUTPUT
+    OutputTooLarge(usize, Amount),
+    // bad-txns-txouttotal-toolarge
+    OutputTotalTooLarge,
+    // bad-txns-inputvalues-outofrange
+    InputValuesOutOfRange,
+    // bad-txns-in-belowout: THE VALUE IN AND THE VALUE OUT
+    NegativeFee(Amount, Amount),
+}
+
+impl AmountError {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AmountError::OutputTooLarge(_, _) => "OUTPUT TOO LARGE",
+            AmountError::OutputTotalTooLarge => "OUTPUT TOTAL TOO LARGE",
+            AmountError::InputValuesOutOfRange => "INPUT VALUES OUT OF RANGE",
+            AmountError::NegativeFee(_, _) => "NEGATIVE FEE",
+        }
+    }
+}
+
+impl fmt::Display for AmountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AmountError::OutputTooLarge(output, value) => {
+                write!(f, "vout[{}] PAYS {}, MORE THAN MAX_MONEY", output, value)
+            }
+            AmountError::OutputTotalTooLarge => write!(f, "THE OUTPUTS PAY MORE THAN MAX_MONEY"),
+            AmountError::InputValuesOutOfRange => {
+                write!(f, "THE INPUTS SPEND MORE THAN MAX_MONEY")
+            }
+            AmountError::NegativeFee(value_in, value_out) => {
+                write!(f, "SPENDS {} BUT PAYS OUT {}", value_in, value_out)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AmountError {}
+
+// RETURNS THE FEE: WHAT THE INPUTS SPEND MINUS WHAT THE OUTPUTS PAY
+// ZERO VALUE OUTPUTS ARE VALID, THE DUST RULES OF THE POLICY KEEP THEM OUT OF THE MEMPOOL
+pub fn check_amounts(tx: &Transaction) -> Result<Amount, AmountError> {
+    for (index, output) in tx.vout.iter().enumerate() {
+        let value = Amount::from_sat(output.value);
+        if !value.is_money_range() {
+            return Err(AmountError::OutputTooLarge(index, value));
+        }
+    }
+
+    let outputs = tx.vout.iter().map(|output| Amount::from_sat(output.value));
+    let value_out = Amount::money_sum(outputs).ok_or(AmountError::OutputTotalTooLarge)?;
+
+    let inputs = tx
+        .vin
+        .iter()
+        .map(|input| Amount::from_sat(input.prevout.value));
+    let value_in = Amount::money_sum(inputs).ok_or(AmountError::InputValuesOutOfRange)?;
+
+    value_in
+        .checked_sub(value_out)
+        .ok_or(AmountError::NegativeFee(value_in, value_out))
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::error::Result;
+
+    use super::*;
+
+    #[test]
+    fn check_amounts_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        let value_in: u64 = tx.vin.iter().map(|input| input.prevout.value).sum();
+        let value_out: u64 = tx.vout.iter().map(|output| output.value).sum();
+        assert_eq!(
+            check_amounts(&tx),
+            Ok(Amount::from_sat(value_in - value_out))
+        );
+
+        // PAYING OUT EVERYTHING IS A ZERO FEE, ONE SAT MORE IS A NEGATIVE ONE
+        let mut changed = tx.clone();
+        changed.vout[0].value += value_in - value_out;
+        assert_eq!(check_amounts(&changed), Ok(Amount::ZERO));
+        changed.vout[0].value += 1;
+        assert_eq!(
+            check_amounts(&changed),
+            Err(AmountError::NegativeFee(
+                Amount::from_sat(value_in),
+                Amount::from_sat(value_in + 1)
+            ))
+        );
+
+        // A ZERO VALUE OUTPUT IS FINE
+        let mut changed = tx.clone();
+        changed.vout[0].value = 0;
+        assert!(check_amounts(&changed).is_ok());
+
+        let max = Amount::MAX_MONEY.to_sat();
+        let mut changed = tx.clone();
+        changed.vout[0].value = max + 1;
+        assert_eq!(
+            check_amounts(&changed),
+            Err(AmountError::OutputTooLarge(0, Amount::from_sat(max + 1)))
+        );
+
+        // EACH OUTPUT IN RANGE BUT NOT THEIR SUM, AND THE SAME FOR THE INPUTS
+        let mut changed = tx.clone();
+        changed.vout[0].value = max;
+        changed.vout.push(tx.vout[0].clone());
+        assert_eq!(
+            check_amounts(&changed),
+            Err(AmountError::OutputTotalTooLarge)
+        );
+
+        let mut changed = tx.clone();
+        changed.vin[0].prevout.value = u64::MAX;
+        assert_eq!(
+            check_amounts(&changed),
+            Err(AmountError::InputValuesOutOfRange)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+// THE MEMPOOL JSON CARRIES ASM, TYPE AND ADDRESS NEXT TO EVERY SCRIPT HEX
+// THOSE FIELDS ARE RE-DERIVED FROM THE HEX AND A TX WHOSE FIELDS DISAGREE IS REJECTED
+
+use std::fmt;
+
+use crate::{
+    address::script_address,
+    network::Network,
+    script::{Script, ScriptType},
+    transaction::Transaction,
+};
+
+// ONE SUPPLIED FIELD THAT DOES NOT MATCH WHAT ITS HEX DECODES TO
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMismatch {
+    // E.G. "vin[0].prevout" OR "vout[1]"
+    pub location: String,
+    pub field: &'static str,
+    pub supplied: String,
+    pub derived: String,
+}
+
+impl fmt::Display for FieldMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}.{} IS {:?} BUT THE HEX GIVES {:?}",
+            self.location, self.field, self.supplied, self.derived
+        )
+    }
+}
+
+impl std::error::Error for FieldMismatch {}
+
+fn compare(
+    location: &str,
+    field: &'static str,
+    supplied: Option<&str>,
+    derived: Option<&str>,
+) -> Result<(), FieldMismatch> {
+    if supplied == derived {
+        return Ok(());
+    }
+    Err(FieldMismatch {
+        location: location.to_string(),
+        field,
+        supplied: supplied.unwrap_or("<none>").to_string(),
+        derived: derived.unwrap_or("<none>").to_string(),
+    })
+}
+
+// A SCRIPT HEX THAT DOES NOT DECODE CAN'T AGREE WITH ANYTHING
+fn parse_script(location: &str, field: &'static str, hex: &str) -> Result<Script, FieldMismatch> {
+    Script::from_hex(hex).map_err(|_| FieldMismatch {
+        location: location.to_string(),
+        field,
+        supplied: hex.to_string(),
+        derived: "<invalid hex>".to_string(),
+    })
+}
+
+fn check_script_pubkey(
+    location: &str,
+    scriptpubkey: &str,
+    scriptpubkey_asm: &str,
+    scriptpubkey_type: &str,
+    scriptpubkey_address: Option<&str>,
+) -> Result<(), FieldMismatch> {
+    let script_pubkey = parse_script(location, "scriptpubkey", scriptpubkey)?;
+
+    compare(
+        location,
+        "scriptpubkey_asm",
+        Some(scriptpubkey_asm),
+        Some(&script_pubkey.to_asm()),
+    )?;
+    compare(
+        location,
+        "scriptpubkey_type",
+        Some(scriptpubkey_type),
+        Some(script_pubkey.esplora_type()),
+    )?;
+    compare(
+        location,
+        "scriptpubkey_address",
+        scriptpubkey_address,
+        script_address(&script_pubkey, Network::Mainnet).as_deref(),
+    )
+}
+
+// FIRST FIELD OF THE TX THAT DISAGREES WITH ITS HEX, INPUTS BEFORE OUTPUTS
+pub fn check_consistency(tx: &Transaction) -> Result<(), FieldMismatch> {
+    for (input_index, input) in tx.vin.iter().enumerate() {
+        let location = format!("vin[{}].prevout", input_index);
+        let prevout = &input.prevout;
+
+        // A PREVOUT ALWAYS HAS AN ADDRESS IN THE JSON, EMPTY WHEN THERE IS NONE
+        let supplied_address =
+            Some(prevout.scriptpubkey_address.as_str()).filter(|a| !a.is_empty());
+
+        check_script_pubkey(
+            &location,
+            &prevout.scriptpubkey,
+            &prevout.scriptpubkey_asm,
+            &prevout.scriptpubkey_type,
+            supplied_address,
+        )?;
+
+        let location = format!("vin[{}]", input_index);
+        let script_sig = parse_script(
+            &location,
+            "scriptsig",
+            input.scriptsig.as_deref().unwrap_or_default(),
+        )?;
+
+        if let Some(scriptsig_asm) = &input.scriptsig_asm {
+            compare(
+                &location,
+                "scriptsig_asm",
+                Some(scriptsig_asm),
+                Some(&script_sig.to_asm()),
+            )?;
+        }
+
+        // ESPLORA ONLY DISASSEMBLES THE REDEEM SCRIPT OF P2SH SPENDS
+        let is_p2sh = Script::from_hex(&prevout.scriptpubkey)
+            .map(|script| script.script_type() == ScriptType::P2sh)
+            .unwrap_or(false);
+
+        let derived_redeemscript_asm = match is_p2sh {
+            true => input
+                .redeem_script()
+                .ok()
+                .flatten()
+                .map(|redeem_script| redeem_script.to_asm()),
+            false => None,
+        };
+
+        compare(
+            &location,
+            "inner_redeemscript_asm",
+            input.inner_redeemscript_asm.as_deref(),
+            derived_redeemscript_asm.as_deref(),
+        )?;
+    }
+
+    for (output_index, output) in tx.vout.iter().enumerate() {
+        check_script_pubkey(
+            &format!("vout[{}]", output_index),
+            &output.scriptpubkey,
+            &output.scriptpubkey_asm,
+            &output.scriptpubkey_type,
+            output.scriptpubkey_address.as_deref(),
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::error::Result;
+
+    use super::*;
+
+    #[test]
+    fn check_consistency_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        assert_eq!(check_consistency(&tx), Ok(()));
+
+        // AN ASM THAT DOES NOT MATCH THE SCRIPT PUB KEY IT CLAIMS TO DESCRIBE
+        let mut forged = tx.clone();
+        forged.vin[0].prevout.scriptpubkey_asm = "OP_PUSHNUM_1".to_string();
+        let mismatch = check_consistency(&forged).unwrap_err();
+        assert_eq!(mismatch.location, "vin[0].prevout");
+        assert_eq!(mismatch.field, "scriptpubkey_asm");
+
+        let mut forged = tx.clone();
+        forged.vout[0].scriptpubkey_type = "p2pkh".to_string();
+        assert_eq!(
+            check_consistency(&forged).unwrap_err().field,
+            "scriptpubkey_type"
+        );
+
+        let mut forged = tx.clone();
+        forged.vout[0].scriptpubkey_address =
+            Some("1BitcoinEaterAddressDontSendf59kuE".to_string());
+        assert_eq!(
+            check_consistency(&forged).unwrap_err().field,
+            "scriptpubkey_address"
+        );
+
+        let mut forged = tx;
+        forged.vout[0].scriptpubkey = "zz".to_string();
+        assert_eq!(
+            check_consistency(&forged).unwrap_err().derived,
+            "<invalid hex>"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+// FEE RATES AND THE MEMPOOL MINIMUM FEE, LIKE BITCOIN CORE'S CFeeRate AND CTxMemPool::GetMinFee
+// A TX PAYS THE MIN RELAY FEE FOR ITS VSIZE, AND ONCE THE MEMPOOL IS FULL MORE THAN WHAT IT EVICTED
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amount::Amount, hashes::Txid, transaction::Transaction, validation_checks::policy::Policy,
+};
+
+// SATS PER 1000 VIRTUAL BYTES
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct FeeRate(u64);
+
+impl FeeRate {
+    pub const ZERO: FeeRate = FeeRate(0);
+
+    pub const fn from_sat_per_kvb(sat_per_kvb: u64) -> Self {
+        FeeRate(sat_per_kvb)
+    }
+
+    // ROUNDED DOWN, SO A TX NEVER LOOKS LIKE IT PAYS MORE THAN IT DOES
+    pub fn from_fee(fee: Amount, vsize: usize) -> Self {
+        let fee = fee.to_sat() as u128;
+        match vsize {
+            0 => FeeRate::ZERO,
+            vsize => FeeRate((fee * 1000 / vsize as u128).min(u64::MAX as u128) as u64),
+        }
+    }
+
+    pub fn sat_per_kvb(self) -> u64 {
+        self.0
+    }
+
+    // THE FEE A TX OF `vsize` PAYS AT THIS RATE, ROUNDED UP
+    pub fn fee(self, vsize: usize) -> Amount {
+        let fee = (self.0 as u128 * vsize as u128).div_ceil(1000);
+        Amount::from_sat(fee.min(u64::MAX as u128) as u64)
+    }
+
+    pub fn saturating_add(self, other: FeeRate) -> FeeRate {
+        FeeRate(self.0.saturating_add(other.0))
+    }
+}
+
+impl fmt::Display for FeeRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:03} SAT/VB", self.0 / 1000, self.0 % 1000)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MempoolFeeEntry {
+    fee: Amount,
+    vsize: usize,
+    // TXS IT SPENDS, IN THE MEMPOOL OR NOT, SO A PARENT ADDED AFTER IT STILL FINDS IT
+    spends: HashSet<Txid>,
+}
+
+impl MempoolFeeEntry {
+    fn fee_rate(&self) -> FeeRate {
+        FeeRate::from_fee(self.fee, self.vsize)
+    }
+}
+
+// THE ACCEPTED TXS AND HOW MUCH ROOM THEY TAKE
+// WHEN THEY NO LONGER FIT, THE LOWEST FEE RATES ARE EVICTED WITH THEIR DESCENDANTS, AND THE MIN FEE
+// RISES TO THE EVICTED RATE PLUS THE INCREMENTAL RELAY FEE. IT NEVER DECAYS DURING A RUN
+#[derive(Debug, Clone)]
+pub struct MempoolFees {
+    entries: HashMap<Txid, MempoolFeeEntry>,
+    total_vsize: usize,
+    max_vsize: usize,
+    min_relay_fee: FeeRate,
+    incremental_relay_fee: FeeRate,
+    rolling_min_fee: FeeRate,
+}
+
+impl MempoolFees {
+    pub fn new(policy: &Policy) -> Self {
+        MempoolFees {
+            entries: HashMap::new(),
+            total_vsize: 0,
+            max_vsize: policy.max_mempool_vsize,
+            min_relay_fee: policy.min_relay_fee,
+            incremental_relay_fee: policy.incremental_relay_fee,
+            rolling_min_fee: FeeRate::ZERO,
+        }
+    }
+
+    // THE RATE A NEW TX OR PACKAGE MUST PAY
+    pub fn min_fee(&self) -> FeeRate {
+        self.min_relay_fee.max(self.rolling_min_fee)
+    }
+
+    pub fn total_vsize(&self) -> usize {
+        self.total_vsize
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, txid: &Txid) -> bool {
+        self.entries.contains_key(txid)
+    }
+
+    // ADDS AN ACCEPTED TX, THEN TRIMS THE MEMPOOL BACK TO `max_vsize`
+    // RETURNS THE EVICTED TXS, WHICH CAN INCLUDE THE NEW ONE
+    pub fn add(&mut self, txid: Txid, tx: &Transaction, fee: Amount, vsize: usize) -> Vec<Txid> {
+        let spends = tx.vin.iter().map(|input| input.txid).collect();
+        if let Some(old) = self
+            .entries
+            .insert(txid, MempoolFeeEntry { fee, vsize, spends })
+        {
+            self.total_vsize -= old.vsize;
+        }
+        self.total_vsize += vsize;
+
+        let mut evicted = Vec::new();
+        while self.total_vsize > self.max_vsize {
+            // TIES GO TO THE SMALLER TXID, SO THE ORDER OF EVICTION DOESN'T DEPEND ON THE HASH MAP
+            let lowest = self
+                .entries
+                .iter()
+                .min_by_key(|(txid, entry)| (entry.fee_rate(), **txid))
+                .map(|(txid, entry)| (*txid, entry.fee_rate()));
+            let Some((lowest, fee_rate)) = lowest else {
+                break;
+            };
+
+            let bumped = fee_rate.saturating_add(self.incremental_relay_fee);
+            self.rolling_min_fee = self.rolling_min_fee.max(bumped);
+            evicted.extend(self.remove_with_descendants(lowest));
+        }
+
+        evicted
+    }
+
+    fn remove_with_descendants(&mut self, txid: Txid) -> Vec<Txid> {
+        let mut removed = Vec::new();
+        let mut pending = vec![txid];
+
+        while let Some(txid) = pending.pop() {
+            let Some(entry) = self.entries.remove(&txid) else {
+                continue;
+            };
+            self.total_vsize -= entry.vsize;
+            removed.push(txid);
+
+            pending.extend(
+                self.entries
+                    .iter()
+                    .filter(|(_, child)| child.spends.contains(&txid))
+                    .map(|(child, _)| *child),
+            );
+        }
+
+        removed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::error::Result;
+
+    use super::*;
+
+    #[test]
+    fn fee_rate_test() {
+        // 110 VBYTES AT 14 SAT/VB, AND 100 KVB AT 1 SAT PER 66 VB
+        assert_eq!(
+            FeeRate::from_fee(Amount::from_sat(1540), 110),
+            FeeRate::from_sat_per_kvb(14_000)
+        );
+        assert!(
+            FeeRate::from_fee(Amount::from_sat(1516), 100_000) < FeeRate::from_sat_per_kvb(1000)
+        );
+
+        // RATES ROUND DOWN AND FEES ROUND UP
+        assert_eq!(
+            FeeRate::from_fee(Amount::from_sat(1000), 3),
+            FeeRate::from_sat_per_kvb(333_333)
+        );
+        assert_eq!(
+            FeeRate::from_sat_per_kvb(1000).fee(141),
+            Amount::from_sat(141)
+        );
+        assert_eq!(
+            FeeRate::from_sat_per_kvb(1500).fee(141),
+            Amount::from_sat(212)
+        );
+        assert_eq!(FeeRate::from_fee(Amount::from_sat(1), 0), FeeRate::ZERO);
+
+        assert_eq!(FeeRate::from_sat_per_kvb(2500).to_string(), "2.500 SAT/VB");
+    }
+
+    #[test]
+    fn mempool_min_fee_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        let policy = Policy {
+            max_mempool_vsize: 1000,
+            ..Policy::default()
+        };
+        let mut mempool = MempoolFees::new(&policy);
+        assert_eq!(mempool.min_fee(), policy.min_relay_fee);
+
+        let [low, high, child, other] =
+            [1, 2, 3, 4].map(|byte| Txid::from_internal_bytes([byte; 32]));
+
+        // 2 SAT/VB AND 10 SAT/VB FIT
+        assert!(mempool.add(low, &tx, Amount::from_sat(800), 400).is_empty());
+        assert!(mempool
+            .add(high, &tx, Amount::from_sat(4000), 400)
+            .is_empty());
+
+        // A CHILD OF THE LOW FEE TX
+        let mut spends_low = tx.clone();
+        spends_low.vin[0].txid = low;
+        assert!(mempool
+            .add(child, &spends_low, Amount::from_sat(1000), 100)
+            .is_empty());
+        assert_eq!(mempool.total_vsize(), 900);
+
+        // ANOTHER 200 VBYTES DON'T FIT: THE LOWEST RATE GOES, AND ITS CHILD WITH IT
+        let mut evicted = mempool.add(other, &tx, Amount::from_sat(1000), 200);
+        evicted.sort();
+        assert_eq!(evicted, vec![low, child]);
+        assert_eq!(mempool.len(), 2);
+        assert_eq!(mempool.total_vsize(), 600);
+        assert!(mempool.contains(&other) && !mempool.contains(&low));
+
+        // NEW TXS MUST NOW BEAT THE EVICTED 2 SAT/VB BY THE INCREMENTAL RELAY FEE
+        assert_eq!(mempool.min_fee(), FeeRate::from_sat_per_kvb(3000));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1627 @@
+// SCRIPT INTERPRETER WITH A CONDITION STACK, SO ANY OPCODE CAN RUN IN ANY BRANCH OF ARBITRARILY
+// NESTED OP_IF / OP_NOTIF / OP_ELSE / OP_ENDIF
+
+use ripemd::Ripemd160;
+use sha1::{Digest, Sha1};
+
+use crate::{
+    error::Result,
+    script::{
+        decode_script_num, encode_push, encode_script_num, is_minimal_script_num, Instruction,
+        Script, OP_0NOTEQUAL, OP_1, OP_16, OP_1ADD, OP_1NEGATE, OP_1SUB, OP_2DIV, OP_2DROP,
+        OP_2DUP, OP_2MUL, OP_2OVER, OP_2ROT, OP_2SWAP, OP_3DUP, OP_ABS, OP_ADD, OP_AND, OP_BOOLAND,
+        OP_BOOLOR, OP_CAT, OP_CHECKLOCKTIMEVERIFY, OP_CHECKMULTISIG, OP_CHECKMULTISIGVERIFY,
+        OP_CHECKSEQUENCEVERIFY, OP_CHECKSIG, OP_CHECKSIGADD, OP_CHECKSIGVERIFY, OP_CODESEPARATOR,
+        OP_DEPTH, OP_DIV, OP_DROP, OP_DUP, OP_ELSE, OP_ENDIF, OP_EQUAL, OP_EQUALVERIFY,
+        OP_FROMALTSTACK, OP_GREATERTHAN, OP_GREATERTHANOREQUAL, OP_HASH160, OP_HASH256, OP_IF,
+        OP_IFDUP, OP_INVERT, OP_LEFT, OP_LESSTHAN, OP_LESSTHANOREQUAL, OP_LSHIFT, OP_MAX, OP_MIN,
+        OP_MOD, OP_MUL, OP_NEGATE, OP_NIP, OP_NOP, OP_NOP1, OP_NOP10, OP_NOP4, OP_NOT, OP_NOTIF,
+        OP_NUMEQUAL, OP_NUMEQUALVERIFY, OP_NUMNOTEQUAL, OP_OR, OP_OVER, OP_PICK, OP_RETURN,
+        OP_RIGHT, OP_RIPEMD160, OP_ROLL, OP_ROT, OP_RSHIFT, OP_SHA1, OP_SHA256, OP_SIZE, OP_SUB,
+        OP_SUBSTR, OP_SWAP, OP_TOALTSTACK, OP_TUCK, OP_VERIF, OP_VERIFY, OP_VERNOTIF, OP_WITHIN,
+        OP_XOR,
+    },
+    transaction::Transaction,
+    validation_checks::{
+        check_signature, double_sha256, hash160,
+        locktime::{op_checklocktimeverify, op_checksequenceverify},
+        script_error::ScriptError,
+        sig_encoding::{check_pubkey_encoding, check_signature_encoding, is_witness_v0},
+        sighash_cache::SighashCache,
+        single_sha256,
+        taproot::{schnorr_check, Tapscript, VALIDATION_WEIGHT_PER_SIGOP_PASSED},
+        verify_flags::VerifyFlags,
+    },
+};
+
+pub type ScriptResult<T> = std::result::Result<T, ScriptError>;
+
+// CONSENSUS LIMITS THAT KEEP A SCRIPT FROM USING UNBOUNDED TIME OR MEMORY
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+pub const MAX_SCRIPT_ELEMENT_SIZE: usize = 520;
+pub const MAX_OPS_PER_SCRIPT: usize = 201;
+// STACK AND ALTSTACK TOGETHER
+pub const MAX_STACK_SIZE: usize = 1000;
+
+// A CHECKMULTISIG TAKES AT MOST 20 KEYS, EACH OF WHICH COUNTS TOWARDS MAX_OPS_PER_SCRIPT
+pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
+
+// NUMERIC OPERANDS ARE CSCRIPTNUMS OF AT MOST 4 BYTES, RESULTS MAY OVERFLOW INTO A 5TH
+const MAX_NUM_SIZE: usize = 4;
+
+// FALSE IS ANY RUN OF ZERO BYTES, WITH OR WITHOUT A SIGN BIT ON THE LAST ONE
+pub fn cast_to_bool(item: &[u8]) -> bool {
+    match item.split_last() {
+        Some((last, rest)) => rest.iter().any(|byte| *byte != 0) || (*last != 0 && *last != 0x80),
+        None => false,
+    }
+}
+
+// THE INPUT A SCRIPT IS SPENDING, WHICH ITS SIGNATURES COMMIT TO
+// `tapscript` IS SET WHILE A BIP342 SCRIPT RUNS
+struct Spend<'a> {
+    tx: &'a Transaction,
+    cache: &'a SighashCache,
+    tx_input_index: usize,
+    input_type: &'a str,
+    flags: VerifyFlags,
+    tapscript: Option<&'a mut Tapscript>,
+}
+
+fn push_bool(stack: &mut Vec<Vec<u8>>, value: bool) {
+    stack.push(if value { vec![1u8] } else { Vec::new() });
+}
+
+fn pop(stack: &mut Vec<Vec<u8>>) -> ScriptResult<Vec<u8>> {
+    stack.pop().ok_or(ScriptError::InvalidStackOperation)
+}
+
+fn pop_num(stack: &mut Vec<Vec<u8>>, flags: VerifyFlags) -> ScriptResult<i64> {
+    let item = pop(stack)?;
+    check_minimal_num(&item, flags)?;
+    decode_script_num(&item, MAX_NUM_SIZE).map_err(|_| ScriptError::NumOverflow)
+}
+
+// UNDER MINIMALDATA A NUMERIC OPERAND HAS NO NEEDLESS ZERO BYTES
+fn check_minimal_num(item: &[u8], flags: VerifyFlags) -> ScriptResult<()> {
+    if flags.contains(VerifyFlags::MINIMALDATA) && !is_minimal_script_num(item) {
+        return Err(ScriptError::MinimalData);
+    }
+    Ok(())
+}
+
+// OPCODES TURNED OFF IN 2010, THEY FAIL A SCRIPT BY BEING IN IT, EXECUTED OR NOT
+fn is_disabled(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        OP_CAT
+            | OP_SUBSTR
+            | OP_LEFT
+            | OP_RIGHT
+            | OP_INVERT
+            | OP_AND
+            | OP_OR
+            | OP_XOR
+            | OP_2MUL
+            | OP_2DIV
+            | OP_MUL
+            | OP_DIV
+            | OP_MOD
+            | OP_LSHIFT
+            | OP_RSHIFT
+    )
+}
+
+// BIP342: OPCODES THAT MAKE A TAPSCRIPT VALID, SO A SOFT FORK CAN GIVE THEM ANY MEANING
+fn is_op_success(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        80 | 98 | 126..=129 | 131..=134 | 137..=138 | 141..=142 | 149..=153 | 187..=254
+    )
+}
+
+// THE SCRIPT CODE A LEGACY SIGNATURE COMMITS TO HAS EVERY OP_CODESEPARATOR TAKEN OUT
+fn legacy_script_code(script_code: &[u8]) -> Vec<u8> {
+    let mut stripped = Vec::new();
+    let script = Script::new(script_code.to_vec());
+    let mut instructions = script.instructions();
+    let mut start = 0;
+    while let Some(instruction) = instructions.next() {
+        let end = instructions.position();
+        if !matches!(instruction, Ok(Instruction::Op(OP_CODESEPARATOR))) {
+            stripped.extend_from_slice(&script_code[start..end]);
+        }
+        start = end;
+    }
+    stripped
+}
+
+// FindAndDelete: DROPS EVERY `pattern` THAT STARTS ON AN OPCODE BOUNDARY, AND SAYS HOW MANY
+// `pattern` IS A WHOLE PUSH, SO IT PARSES AS ONE INSTRUCTION WHEREVER IT STARTS ON A BOUNDARY
+fn find_and_delete(script_code: &[u8], pattern: &[u8]) -> (Vec<u8>, usize) {
+    let mut kept = Vec::new();
+    let mut found = 0;
+    let script = Script::new(script_code.to_vec());
+    let mut instructions = script.instructions();
+    let mut start = 0;
+    while instructions.next().is_some() {
+        let end = instructions.position();
+        match &script_code[start..end] == pattern {
+            true => found += 1,
+            false => kept.extend_from_slice(&script_code[start..end]),
+        }
+        start = end;
+    }
+    (kept, found)
+}
+
+// THE SCRIPT CODE `signatures` COMMIT TO, BUILT ONLY WHEN A SIGNATURE OPCODE RUNS
+// A LEGACY SIGNATURE CAN'T COMMIT TO ITSELF, SO ITS PUSHES ARE TAKEN OUT, THEN THE OP_CODESEPARATORS
+// SEGWIT SCRIPT CODES ARE KEPT AS THEY ARE, AND UNDER CONST_SCRIPTCODE A SIGNATURE FOUND IN THE
+// SCRIPT CODE FAILS THE SCRIPT
+fn delete_signatures(
+    script_code: &[u8],
+    signatures: &[Vec<u8>],
+    spend: &Spend,
+) -> ScriptResult<Vec<u8>> {
+    let mut script_code = script_code.to_vec();
+    if spend.input_type != "NON_SEGWIT" {
+        return Ok(script_code);
+    }
+
+    for signature in signatures {
+        let (kept, found) = find_and_delete(&script_code, &encode_push(signature));
+        if found > 0 && spend.flags.contains(VerifyFlags::CONST_SCRIPTCODE) {
+            return Err(ScriptError::SigFindAndDelete);
+        }
+        script_code = kept;
+    }
+    Ok(legacy_script_code(&script_code))
+}
+
+// RUNS `script` ON `stack`, STOPPING AT THE FIRST RULE IT BREAKS
+// THE CALLER DECIDES WHAT THE STACK MUST HOLD AFTERWARDS
+// `flags`: THE CONSENSUS AND POLICY RULES THE SCRIPT IS HELD TO
+pub fn eval_script(
+    stack: &mut Vec<Vec<u8>>,
+    script: &[u8],
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    input_type: &str,
+    flags: VerifyFlags,
+) -> ScriptResult<()> {
+    let mut spend = Spend {
+        tx,
+        cache,
+        tx_input_index,
+        input_type,
+        flags,
+        tapscript: None,
+    };
+    run_script(stack, script, &mut spend)
+}
+
+fn run_script(stack: &mut Vec<Vec<u8>>, script: &[u8], spend: &mut Spend) -> ScriptResult<()> {
+    // TAPSCRIPTS HAVE NO SIZE OR OPCODE LIMIT, THE VALIDATION WEIGHT BOUNDS THEIR SIGNATURES
+    let tapscript = spend.tapscript.is_some();
+    if !tapscript && script.len() > MAX_SCRIPT_SIZE {
+        return Err(ScriptError::ScriptSize);
+    }
+
+    let parsed = Script::new(script.to_vec());
+    let mut instructions = parsed.instructions();
+    let flags = spend.flags;
+
+    // OP_IF AND OP_NOTIF ONLY ACCEPT AN EMPTY ITEM OR 0x01 IN A SEGWIT V0 SCRIPT, AND ALWAYS IN A
+    // TAPSCRIPT
+    let minimal_if =
+        tapscript || (flags.contains(VerifyFlags::MINIMALIF) && is_witness_v0(spend.input_type));
+    let legacy = spend.input_type == "NON_SEGWIT";
+
+    // ONE ENTRY PER OPEN OP_IF / OP_NOTIF, FALSE WHILE ITS BRANCH IS SKIPPED
+    let mut conditions: Vec<bool> = Vec::new();
+    let mut alt_stack: Vec<Vec<u8>> = Vec::new();
+
+    // SIGNATURES COMMIT TO THE SCRIPT FROM THE LAST EXECUTED OP_CODESEPARATOR ON
+    let mut code_start = 0;
+    let mut op_count = 0;
+    // A TAPSCRIPT SIGNATURE COMMITS TO THE OPCODE POSITION OF THAT OP_CODESEPARATOR INSTEAD
+    let mut opcode_pos: u32 = 0;
+
+    while let Some(instruction) = instructions.next() {
+        let instruction = instruction.map_err(|_| ScriptError::BadOpcode)?;
+        let position = opcode_pos;
+        opcode_pos += 1;
+        let executing = conditions.iter().all(|condition| *condition);
+
+        // PUSH SIZES AND OPCODES ARE COUNTED WHETHER OR NOT THEIR BRANCH RUNS
+        if let Instruction::Push(_, data) = instruction {
+            if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                return Err(ScriptError::PushSize);
+            }
+        }
+        if !tapscript && instruction.opcode() > OP_16 {
+            op_count += 1;
+            if op_count > MAX_OPS_PER_SCRIPT {
+                return Err(ScriptError::OpCount);
+            }
+        }
+
+        // THE BRANCH OPCODES ARE SEEN EVEN IN A SKIPPED BRANCH, TO KEEP TRACK OF THE NESTING
+        match instruction.opcode() {
+            opcode @ (OP_IF | OP_NOTIF) => {
+                let mut condition = false;
+                if executing {
+                    let top = stack.pop().ok_or(ScriptError::UnbalancedConditional)?;
+                    if minimal_if && !(top.is_empty() || top == [1u8]) {
+                        return Err(match tapscript {
+                            true => ScriptError::TapscriptMinimalIf,
+                            false => ScriptError::MinimalIf,
+                        });
+                    }
+                    condition = cast_to_bool(&top) == (opcode == OP_IF);
+                }
+                conditions.push(condition);
+                continue;
+            }
+            OP_ELSE => {
+                match conditions.last_mut() {
+                    Some(condition) => *condition = !*condition,
+                    None => return Err(ScriptError::UnbalancedConditional),
+                }
+                continue;
+            }
+            OP_ENDIF => {
+                if conditions.pop().is_none() {
+                    return Err(ScriptError::UnbalancedConditional);
+                }
+                continue;
+            }
+            opcode if is_disabled(opcode) => return Err(ScriptError::DisabledOpcode),
+            OP_VERIF | OP_VERNOTIF => return Err(ScriptError::BadOpcode),
+            OP_CODESEPARATOR if legacy && flags.contains(VerifyFlags::CONST_SCRIPTCODE) => {
+                return Err(ScriptError::OpCodeSeparator)
+            }
+            _ => {}
+        }
+
+        if !executing {
+            continue;
+        }
+
+        match instruction {
+            Instruction::Push(_, data) => {
+                if flags.contains(VerifyFlags::MINIMALDATA) && !instruction.is_minimal_push() {
+                    return Err(ScriptError::MinimalData);
+                }
+                stack.push(data.to_vec())
+            }
+            Instruction::Op(OP_CODESEPARATOR) => {
+                code_start = instructions.position();
+                if let Some(tapscript) = spend.tapscript.as_deref_mut() {
+                    tapscript.codesep_pos = position;
+                }
+            }
+            Instruction::Op(opcode) => execute_opcode(
+                opcode,
+                stack,
+                &mut alt_stack,
+                &mut op_count,
+                &script[code_start..],
+                spend,
+            )?,
+        }
+
+        if stack.len() + alt_stack.len() > MAX_STACK_SIZE {
+            return Err(ScriptError::StackSize);
+        }
+    }
+
+    // AN OP_IF WITHOUT ITS OP_ENDIF
+    if !conditions.is_empty() {
+        return Err(ScriptError::UnbalancedConditional);
+    }
+    Ok(())
+}
+
+// ITEMS AN OPCODE NEEDS ON THE STACK BEFORE IT CAN RUN
+fn stack_items_needed(opcode: u8) -> usize {
+    match opcode {
+        OP_VERIFY | OP_TOALTSTACK | OP_IFDUP | OP_DROP | OP_DUP | OP_PICK | OP_ROLL | OP_SIZE => 1,
+        OP_1ADD | OP_1SUB | OP_NEGATE | OP_ABS | OP_NOT | OP_0NOTEQUAL => 1,
+        OP_RIPEMD160 | OP_SHA1 | OP_SHA256 | OP_HASH160 | OP_HASH256 => 1,
+        OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => 1,
+        OP_2DROP | OP_2DUP | OP_NIP | OP_OVER | OP_SWAP | OP_TUCK => 2,
+        OP_EQUAL | OP_EQUALVERIFY | OP_CHECKSIG | OP_CHECKSIGVERIFY => 2,
+        OP_ADD | OP_SUB | OP_BOOLAND | OP_BOOLOR | OP_NUMEQUAL | OP_NUMEQUALVERIFY => 2,
+        OP_NUMNOTEQUAL | OP_LESSTHAN | OP_GREATERTHAN | OP_LESSTHANOREQUAL => 2,
+        OP_GREATERTHANOREQUAL | OP_MIN | OP_MAX => 2,
+        OP_3DUP | OP_ROT | OP_WITHIN => 3,
+        OP_2OVER | OP_2SWAP => 4,
+        OP_2ROT => 6,
+        _ => 0,
+    }
+}
+
+// ONE NON PUSH OPCODE OF AN EXECUTED BRANCH
+// `script_code` IS THE SCRIPT FROM THE LAST OP_CODESEPARATOR ON, UNTOUCHED UNTIL A SIGNATURE NEEDS IT
+fn execute_opcode(
+    opcode: u8,
+    stack: &mut Vec<Vec<u8>>,
+    alt_stack: &mut Vec<Vec<u8>>,
+    op_count: &mut usize,
+    script_code: &[u8],
+    spend: &mut Spend,
+) -> ScriptResult<()> {
+    if stack.len() < stack_items_needed(opcode) {
+        return Err(ScriptError::InvalidStackOperation);
+    }
+    let len = stack.len();
+
+    match opcode {
+        OP_1NEGATE => stack.push(encode_script_num(-1)),
+        OP_1..=OP_16 => stack.push(encode_script_num((opcode - OP_1 + 1) as i64)),
+        OP_NOP => {}
+        OP_NOP1 | OP_NOP4..=OP_NOP10 => discourage_upgradable_nop(spend.flags)?,
+
+        OP_VERIFY => {
+            if !cast_to_bool(&pop(stack)?) {
+                return Err(ScriptError::Verify);
+            }
+        }
+        OP_RETURN => return Err(ScriptError::OpReturn),
+
+        OP_TOALTSTACK => alt_stack.push(pop(stack)?),
+        OP_FROMALTSTACK => match alt_stack.pop() {
+            Some(item) => stack.push(item),
+            None => return Err(ScriptError::InvalidAltstackOperation),
+        },
+        OP_2DROP => stack.truncate(len - 2),
+        OP_2DUP | OP_3DUP => {
+            let count = if opcode == OP_2DUP { 2 } else { 3 };
+            let copied = stack[len - count..].to_vec();
+            stack.extend(copied);
+        }
+        OP_2OVER => {
+            let copied = stack[len - 4..len - 2].to_vec();
+            stack.extend(copied);
+        }
+        OP_2ROT | OP_2SWAP => {
+            let from = if opcode == OP_2ROT { len - 6 } else { len - 4 };
+            let moved: Vec<Vec<u8>> = stack.drain(from..from + 2).collect();
+            stack.extend(moved);
+        }
+        OP_IFDUP => {
+            let top = stack[len - 1].clone();
+            if cast_to_bool(&top) {
+                stack.push(top);
+            }
+        }
+        OP_DEPTH => stack.push(encode_script_num(len as i64)),
+        OP_DROP => {
+            stack.pop();
+        }
+        OP_DUP => stack.push(stack[len - 1].clone()),
+        OP_NIP => {
+            stack.remove(len - 2);
+        }
+        OP_OVER => stack.push(stack[len - 2].clone()),
+        OP_PICK | OP_ROLL => {
+            let depth = pop_num(stack, spend.flags)?;
+            if depth < 0 || depth as usize >= stack.len() {
+                return Err(ScriptError::InvalidStackOperation);
+            }
+            let depth = depth as usize;
+            let index = stack.len() - 1 - depth;
+            let item = if opcode == OP_PICK {
+                stack[index].clone()
+            } else {
+                stack.remove(index)
+            };
+            stack.push(item);
+        }
+        OP_ROT => {
+            let item = stack.remove(len - 3);
+            stack.push(item);
+        }
+        OP_SWAP => stack.swap(len - 1, len - 2),
+        OP_TUCK => stack.insert(len - 2, stack[len - 1].clone()),
+        OP_SIZE => stack.push(encode_script_num(stack[len - 1].len() as i64)),
+
+        OP_EQUAL | OP_EQUALVERIFY => {
+            let a = pop(stack)?;
+            let b = pop(stack)?;
+            if opcode == OP_EQUALVERIFY {
+                return if a == b {
+                    Ok(())
+                } else {
+                    Err(ScriptError::EqualVerify)
+                };
+            }
+            push_bool(stack, a == b);
+        }
+
+        OP_1ADD | OP_1SUB | OP_NEGATE | OP_ABS | OP_NOT | OP_0NOTEQUAL => {
+            let a = pop_num(stack, spend.flags)?;
+            let result = match opcode {
+                OP_1ADD => a + 1,
+                OP_1SUB => a - 1,
+                OP_NEGATE => -a,
+                OP_ABS => a.abs(),
+                OP_NOT => (a == 0) as i64,
+                _ => (a != 0) as i64,
+            };
+            stack.push(encode_script_num(result));
+        }
+        OP_ADD
+        | OP_SUB
+        | OP_BOOLAND
+        | OP_BOOLOR
+        | OP_NUMEQUAL
+        | OP_NUMEQUALVERIFY
+        | OP_NUMNOTEQUAL
+        | OP_LESSTHAN
+        | OP_GREATERTHAN
+        | OP_LESSTHANOREQUAL
+        | OP_GREATERTHANOREQUAL
+        | OP_MIN
+        | OP_MAX => {
+            let b = pop_num(stack, spend.flags)?;
+            let a = pop_num(stack, spend.flags)?;
+            let result = match opcode {
+                OP_ADD => a + b,
+                OP_SUB => a - b,
+                OP_BOOLAND => (a != 0 && b != 0) as i64,
+                OP_BOOLOR => (a != 0 || b != 0) as i64,
+                OP_NUMEQUAL | OP_NUMEQUALVERIFY => (a == b) as i64,
+                OP_NUMNOTEQUAL => (a != b) as i64,
+                OP_LESSTHAN => (a < b) as i64,
+                OP_GREATERTHAN => (a > b) as i64,
+                OP_LESSTHANOREQUAL => (a <= b) as i64,
+                OP_GREATERTHANOREQUAL => (a >= b) as i64,
+                OP_MIN => a.min(b),
+                _ => a.max(b),
+            };
+            if opcode == OP_NUMEQUALVERIFY {
+                return if result != 0 {
+                    Ok(())
+                } else {
+                    Err(ScriptError::NumEqualVerify)
+                };
+            }
+            stack.push(encode_script_num(result));
+        }
+        OP_WITHIN => {
+            let max = pop_num(stack, spend.flags)?;
+            let min = pop_num(stack, spend.flags)?;
+            let x = pop_num(stack, spend.flags)?;
+            push_bool(stack, min <= x && x < max);
+        }
+
+        OP_RIPEMD160 | OP_SHA1 | OP_SHA256 | OP_HASH160 | OP_HASH256 => {
+            let top = pop(stack)?;
+            stack.push(match opcode {
+                OP_RIPEMD160 => Ripemd160::digest(&top).to_vec(),
+                OP_SHA1 => Sha1::digest(&top).to_vec(),
+                OP_SHA256 => single_sha256(&top),
+                OP_HASH160 => hash160(&top),
+                _ => double_sha256(&top),
+            });
+        }
+
+        OP_CHECKSIG | OP_CHECKSIGVERIFY if spend.tapscript.is_some() => {
+            let pubkey = pop(stack)?;
+            let signature = pop(stack)?;
+            let result = check_tapscript_signature(&signature, &pubkey, spend)?;
+
+            if opcode == OP_CHECKSIGVERIFY {
+                return if result {
+                    Ok(())
+                } else {
+                    Err(ScriptError::CheckSigVerify)
+                };
+            }
+            push_bool(stack, result);
+        }
+        // <sig> <n> <pubkey> OP_CHECKSIGADD LEAVES n + 1 FOR A VALID SIGNATURE, n FOR AN EMPTY ONE
+        OP_CHECKSIGADD if spend.tapscript.is_some() => {
+            let pubkey = pop(stack)?;
+            let n = pop_num(stack, spend.flags)?;
+            let signature = pop(stack)?;
+            let result = check_tapscript_signature(&signature, &pubkey, spend)?;
+            stack.push(encode_script_num(n + result as i64));
+        }
+        OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY if spend.tapscript.is_some() => {
+            return Err(ScriptError::TapscriptCheckMultisig)
+        }
+        OP_CHECKSIG | OP_CHECKSIGVERIFY => {
+            let pubkey = pop(stack)?;
+            let signature = pop(stack)?;
+            let script_code =
+                delete_signatures(script_code, std::slice::from_ref(&signature), spend)?;
+            check_signature_encoding(&signature, spend.flags)?;
+            check_pubkey_encoding(&pubkey, spend.flags, is_witness_v0(spend.input_type))?;
+
+            let result = check_signature(
+                &signature,
+                &pubkey,
+                spend.tx,
+                spend.cache,
+                spend.tx_input_index,
+                spend.input_type,
+                Some(&script_code),
+            )
+            .map_err(|error| ScriptError::SigHash(error.to_string()))?;
+
+            if !result && spend.flags.contains(VerifyFlags::NULLFAIL) && !signature.is_empty() {
+                return Err(ScriptError::SigNullFail);
+            }
+
+            if opcode == OP_CHECKSIGVERIFY {
+                return if result {
+                    Ok(())
+                } else {
+                    Err(ScriptError::CheckSigVerify)
+                };
+            }
+            push_bool(stack, result);
+        }
+        OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+            let result = check_multisig(stack, op_count, script_code, spend)?;
+
+            if opcode == OP_CHECKMULTISIGVERIFY {
+                return if result {
+                    Ok(())
+                } else {
+                    Err(ScriptError::CheckMultisigVerify)
+                };
+            }
+            push_bool(stack, result);
+        }
+
+        // WITHOUT THEIR FLAGS THESE ARE STILL OP_NOP2 AND OP_NOP3
+        OP_CHECKLOCKTIMEVERIFY | OP_CHECKSEQUENCEVERIFY => {
+            let cltv = opcode == OP_CHECKLOCKTIMEVERIFY;
+            let flag = match cltv {
+                true => VerifyFlags::CHECKLOCKTIMEVERIFY,
+                false => VerifyFlags::CHECKSEQUENCEVERIFY,
+            };
+            if !spend.flags.contains(flag) {
+                return discourage_upgradable_nop(spend.flags);
+            }
+
+            let top = stack.last().ok_or(ScriptError::InvalidStackOperation)?;
+            check_minimal_num(top, spend.flags)?;
+            let satisfied = match cltv {
+                true => op_checklocktimeverify(stack, spend.tx, spend.tx_input_index),
+                false => op_checksequenceverify(stack, spend.tx, spend.tx_input_index),
+            };
+            if !satisfied {
+                return Err(ScriptError::UnsatisfiedLocktime);
+            }
+        }
+
+        // OP_RESERVED, OP_VER, OP_RESERVED1/2 AND EVERY UNASSIGNED OPCODE FAIL WHEN EXECUTED
+        _ => return Err(ScriptError::BadOpcode),
+    }
+
+    Ok(())
+}
+
+// OP_NOPS LEFT FOR SOFT FORKS, SO A SCRIPT USING THEM ISN'T RELAYED BEFORE THEY GET A MEANING
+fn discourage_upgradable_nop(flags: VerifyFlags) -> ScriptResult<()> {
+    if flags.contains(VerifyFlags::DISCOURAGE_UPGRADABLE_NOPS) {
+        return Err(ScriptError::DiscourageUpgradableNops);
+    }
+    Ok(())
+}
+
+// BIP342: AN EMPTY SIGNATURE IS A FAILED CHECK, ANY OTHER MUST BE VALID OR THE SCRIPT FAILS, SO IT
+// IS TAKEN AS VALID HERE AND VERIFIED WITH THE OTHER SIGNATURES OF THE TX
+fn check_tapscript_signature(
+    signature: &[u8],
+    pubkey: &[u8],
+    spend: &mut Spend,
+) -> ScriptResult<bool> {
+    let tapscript = match spend.tapscript.as_deref_mut() {
+        Some(tapscript) => tapscript,
+        None => return Err(ScriptError::BadOpcode),
+    };
+
+    if !signature.is_empty() {
+        tapscript.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+        if tapscript.validation_weight_left < 0 {
+            return Err(ScriptError::TapscriptValidationWeight);
+        }
+    }
+
+    match pubkey.len() {
+        0 => return Err(ScriptError::PubkeyType),
+        32 if !signature.is_empty() => {
+            let check = schnorr_check(
+                spend.tx,
+                spend.cache,
+                spend.tx_input_index,
+                pubkey,
+                signature,
+                tapscript.annex.as_deref(),
+                Some((&tapscript.leaf_hash, tapscript.codesep_pos)),
+            )?;
+            tapscript.checks.push(check);
+        }
+        32 => {}
+        // OTHER KEY TYPES ARE LEFT FOR SOFT FORKS, THEIR SIGNATURES ARE NOT CHECKED
+        _ => {
+            if spend
+                .flags
+                .contains(VerifyFlags::DISCOURAGE_UPGRADABLE_PUBKEYTYPE)
+            {
+                return Err(ScriptError::DiscourageUpgradablePubkeyType);
+            }
+        }
+    }
+
+    Ok(!signature.is_empty())
+}
+
+// THE COUNT IN THE TOP ITEM IS READ IN PLACE, NOT POPPED
+fn stack_count(
+    stack: &[Vec<u8>],
+    depth: usize,
+    max: usize,
+    flags: VerifyFlags,
+) -> ScriptResult<Option<usize>> {
+    let item = stack
+        .len()
+        .checked_sub(depth)
+        .map(|index| &stack[index])
+        .ok_or(ScriptError::InvalidStackOperation)?;
+    check_minimal_num(item, flags)?;
+    let count = decode_script_num(item, MAX_NUM_SIZE).map_err(|_| ScriptError::NumOverflow)?;
+    Ok(usize::try_from(count).ok().filter(|count| *count <= max))
+}
+
+// <dummy> <sig_1> .. <sig_m> <m> <key_1> .. <key_n> <n>, AS IN BITCOIN CORE
+// SIGNATURES MUST MATCH THE KEYS IN ORDER, SO EACH KEY IS TRIED AT MOST ONCE
+fn check_multisig(
+    stack: &mut Vec<Vec<u8>>,
+    op_count: &mut usize,
+    script_code: &[u8],
+    spend: &Spend,
+) -> ScriptResult<bool> {
+    // DEPTHS FROM THE TOP OF THE STACK, 1 IS THE TOP ITEM
+    let mut depth = 1;
+
+    let mut keys_count = stack_count(stack, depth, MAX_PUBKEYS_PER_MULTISIG, spend.flags)?
+        .ok_or(ScriptError::PubkeyCount)?;
+    *op_count += keys_count;
+    if *op_count > MAX_OPS_PER_SCRIPT {
+        return Err(ScriptError::OpCount);
+    }
+    let mut key_depth = depth + 1;
+    depth += keys_count + 1;
+
+    let mut sigs_count =
+        stack_count(stack, depth, keys_count, spend.flags)?.ok_or(ScriptError::SigCount)?;
+    let mut sig_depth = depth + 1;
+    depth += sigs_count;
+
+    // THE ARGUMENTS AND ONE EXTRA ITEM UNDER THEM, A BUG IN THE ORIGINAL CLIENT
+    if stack.len() < depth + 1 {
+        return Err(ScriptError::InvalidStackOperation);
+    }
+
+    let len = stack.len();
+    let signatures = len - depth..len + 1 - sig_depth;
+    let script_code = delete_signatures(script_code, &stack[signatures.clone()], spend)?;
+    let mut success = true;
+    while success && sigs_count > 0 {
+        let signature = &stack[len - sig_depth];
+        let pubkey = &stack[len - key_depth];
+        check_signature_encoding(signature, spend.flags)?;
+        check_pubkey_encoding(pubkey, spend.flags, is_witness_v0(spend.input_type))?;
+
+        let matched = check_signature(
+            signature,
+            pubkey,
+            spend.tx,
+            spend.cache,
+            spend.tx_input_index,
+            spend.input_type,
+            Some(&script_code),
+        )
+        .map_err(|error| ScriptError::SigHash(error.to_string()))?;
+
+        if matched {
+            sig_depth += 1;
+            sigs_count -= 1;
+        }
+        key_depth += 1;
+        keys_count -= 1;
+
+        // TOO FEW KEYS LEFT FOR THE SIGNATURES STILL UNMATCHED
+        if sigs_count > keys_count {
+            success = false;
+        }
+    }
+
+    // NULLFAIL: A FAILED CHECK ONLY GETS EMPTY SIGNATURES
+    if !success
+        && spend.flags.contains(VerifyFlags::NULLFAIL)
+        && stack[signatures]
+            .iter()
+            .any(|signature| !signature.is_empty())
+    {
+        return Err(ScriptError::SigNullFail);
+    }
+
+    stack.truncate(len - depth);
+
+    // BIP147 NULLDUMMY: THE EXTRA ITEM MUST BE EMPTY
+    if !pop(stack)?.is_empty() && spend.flags.contains(VerifyFlags::NULLDUMMY) {
+        return Err(ScriptError::SigNullDummy);
+    }
+
+    Ok(success)
+}
+
+// THE SCRIPT MUST END WITH TRUE ON TOP
+fn expect_true(stack: &[Vec<u8>]) -> ScriptResult<()> {
+    match stack.last() {
+        Some(top) if cast_to_bool(top) => Ok(()),
+        _ => Err(ScriptError::EvalFalse),
+    }
+}
+
+// BIP141: THE WITNESS SCRIPT RUNS ON THE OTHER WITNESS ITEMS AND MUST LEAVE ONLY TRUE
+pub fn verify_witness_script(
+    mut stack: Vec<Vec<u8>>,
+    witness_script: &[u8],
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    input_type: &str,
+    flags: VerifyFlags,
+) -> ScriptResult<()> {
+    if stack
+        .iter()
+        .any(|item| item.len() > MAX_SCRIPT_ELEMENT_SIZE)
+    {
+        return Err(ScriptError::PushSize);
+    }
+
+    eval_script(
+        &mut stack,
+        witness_script,
+        tx,
+        cache,
+        tx_input_index,
+        input_type,
+        flags,
+    )?;
+
+    // WITNESS SCRIPTS ARE ALWAYS HELD TO A CLEAN STACK
+    if stack.len() != 1 {
+        return Err(ScriptError::CleanStack);
+    }
+    expect_true(&stack)
+}
+
+// A SPEND WITHOUT A WITNESS: THE SCRIPT SIG RUNS, THEN THE SCRIPT PUB KEY ON WHAT IT LEFT, WHICH
+// MUST END WITH TRUE ON TOP. UNDER SIGPUSHONLY THE SCRIPT SIG MAY ONLY PUSH, AND UNDER CLEANSTACK
+// THAT TRUE MUST BE ALL IT LEAVES
+pub fn verify_legacy_script(
+    script_sig: &Script,
+    script_pubkey: &Script,
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    flags: VerifyFlags,
+) -> ScriptResult<()> {
+    if flags.contains(VerifyFlags::SIGPUSHONLY) && !script_sig.is_push_only() {
+        return Err(ScriptError::SigPushOnly);
+    }
+
+    let mut stack = Vec::new();
+    for script in [script_sig, script_pubkey] {
+        eval_script(
+            &mut stack,
+            script.as_bytes(),
+            tx,
+            cache,
+            tx_input_index,
+            "NON_SEGWIT",
+            flags,
+        )?;
+    }
+    expect_true(&stack)?;
+
+    if flags.contains(VerifyFlags::CLEANSTACK) && stack.len() != 1 {
+        return Err(ScriptError::CleanStack);
+    }
+    Ok(())
+}
+
+// BIP16: THE REDEEM SCRIPT RUNS ON WHAT THE SCRIPT SIG PUSHED UNDER IT AND MUST LEAVE TRUE ON TOP
+// UNDER CLEANSTACK THAT TRUE MUST BE ALL IT LEAVES
+pub fn verify_redeem_script(
+    mut stack: Vec<Vec<u8>>,
+    redeem_script: &[u8],
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    input_type: &str,
+    flags: VerifyFlags,
+) -> ScriptResult<()> {
+    eval_script(
+        &mut stack,
+        redeem_script,
+        tx,
+        cache,
+        tx_input_index,
+        input_type,
+        flags,
+    )?;
+    expect_true(&stack)?;
+
+    if flags.contains(VerifyFlags::CLEANSTACK) && stack.len() != 1 {
+        return Err(ScriptError::CleanStack);
+    }
+    Ok(())
+}
+
+// BIP342: THE TAPSCRIPT RUNS ON THE OTHER WITNESS ITEMS AND MUST LEAVE ONLY TRUE
+// ITS SIGNATURES ARE LEFT IN `tapscript.checks`, THE SCRIPT IS ONLY VALID IF THEY ALL ARE
+pub fn verify_tapscript(
+    mut stack: Vec<Vec<u8>>,
+    script: &[u8],
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    flags: VerifyFlags,
+    tapscript: &mut Tapscript,
+) -> ScriptResult<()> {
+    // AN OP_SUCCESSX ANYWHERE, EVEN AFTER AN OP_RETURN, MAKES THE SCRIPT VALID
+    for instruction in Script::new(script.to_vec()).instructions() {
+        let instruction = instruction.map_err(|_| ScriptError::BadOpcode)?;
+        if is_op_success(instruction.opcode()) {
+            if flags.contains(VerifyFlags::DISCOURAGE_OP_SUCCESS) {
+                return Err(ScriptError::DiscourageOpSuccess);
+            }
+            return Ok(());
+        }
+    }
+
+    if stack.len() > MAX_STACK_SIZE {
+        return Err(ScriptError::StackSize);
+    }
+    if stack
+        .iter()
+        .any(|item| item.len() > MAX_SCRIPT_ELEMENT_SIZE)
+    {
+        return Err(ScriptError::PushSize);
+    }
+
+    let mut spend = Spend {
+        tx,
+        cache,
+        tx_input_index,
+        input_type: "P2TR",
+        flags,
+        tapscript: Some(tapscript),
+    };
+    run_script(&mut stack, script, &mut spend)?;
+
+    if stack.len() != 1 {
+        return Err(ScriptError::CleanStack);
+    }
+    expect_true(&stack)
+}
+
+// A SCRIPT THAT FAILED ON AN ENCODING RULE IS AN ERROR, SO THE REPORT CAN NAME THE RULE
+// ANY OTHER FAILURE IS JUST FALSE
+pub fn encoding_error_or_ok(result: ScriptResult<()>) -> Result<bool> {
+    match result {
+        Ok(()) => Ok(true),
+        Err(error) if error.is_encoding() => Err(error.into()),
+        Err(_) => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+
+    use crate::{
+        script::{OP_0, OP_PUSHDATA2},
+        validation_checks::trimmed_tx,
+    };
+
+    use super::*;
+
+    const CONSENSUS: VerifyFlags = VerifyFlags::CONSENSUS;
+    const STANDARD: VerifyFlags = VerifyFlags::STANDARD;
+
+    fn eval(
+        script_hex: &str,
+        mut stack: Vec<Vec<u8>>,
+        flags: VerifyFlags,
+    ) -> ScriptResult<Vec<Vec<u8>>> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        let cache = SighashCache::new(&tx).unwrap();
+
+        let script = hex::decode(script_hex).unwrap();
+        eval_script(&mut stack, &script, &tx, &cache, 0, "P2WSH", flags)?;
+        Ok(stack)
+    }
+
+    fn run(script_hex: &str, stack: Vec<Vec<u8>>, flags: VerifyFlags) -> Option<Vec<Vec<u8>>> {
+        eval(script_hex, stack, flags).ok()
+    }
+
+    #[test]
+    fn conditional_test() {
+        // OP_IF OP_2 OP_ELSE OP_3 OP_ENDIF
+        assert_eq!(
+            run("6352675368", vec![vec![1]], STANDARD),
+            Some(vec![vec![2]])
+        );
+        assert_eq!(
+            run("6352675368", vec![vec![]], STANDARD),
+            Some(vec![vec![3]])
+        );
+
+        // OP_NOTIF TAKES THE OTHER BRANCH
+        assert_eq!(
+            run("6452675368", vec![vec![]], STANDARD),
+            Some(vec![vec![2]])
+        );
+
+        // NESTED: OP_IF OP_IF OP_2 OP_ELSE OP_3 OP_ENDIF OP_ELSE OP_4 OP_ENDIF
+        let nested = "636352675368675468";
+        assert_eq!(
+            run(nested, vec![vec![1], vec![1]], STANDARD),
+            Some(vec![vec![2]])
+        );
+        assert_eq!(
+            run(nested, vec![vec![], vec![1]], STANDARD),
+            Some(vec![vec![3]])
+        );
+        assert_eq!(run(nested, vec![vec![]], STANDARD), Some(vec![vec![4]]));
+
+        // A SKIPPED BRANCH DOES NOT RUN ITS OPCODES, NOT EVEN OP_RETURN
+        assert_eq!(run("636a6851", vec![vec![]], STANDARD), Some(vec![vec![1]]));
+
+        // UNBALANCED BRANCHES FAIL
+        assert_eq!(run("6351", vec![vec![1]], STANDARD), None);
+        assert_eq!(run("5168", vec![], STANDARD), None);
+        assert_eq!(run("5167", vec![], STANDARD), None);
+
+        // MINIMALIF: ONLY AN EMPTY ITEM OR 0x01 CAN DECIDE A BRANCH OF A WITNESS SCRIPT
+        assert_eq!(run("635168", vec![vec![2]], STANDARD), None);
+        assert_eq!(run("635168", vec![vec![1, 0]], STANDARD), None);
+        assert_eq!(run("635168", vec![vec![2]], CONSENSUS), Some(vec![vec![1]]));
+    }
+
+    #[test]
+    fn arithmetic_test() {
+        // OP_2 OP_3 OP_ADD, OP_2 OP_3 OP_SUB
+        assert_eq!(run("525393", vec![], CONSENSUS), Some(vec![vec![5]]));
+        assert_eq!(run("525394", vec![], CONSENSUS), Some(vec![vec![0x81]]));
+
+        // OP_1ADD OP_NEGATE OP_ABS ON -1 GIVES 0, THE EMPTY ITEM
+        assert_eq!(run("4f8b8f90", vec![], CONSENSUS), Some(vec![vec![]]));
+
+        // OP_MIN / OP_MAX / OP_BOOLAND / OP_BOOLOR
+        assert_eq!(run("5253a3", vec![], CONSENSUS), Some(vec![vec![2]]));
+        assert_eq!(run("5253a4", vec![], CONSENSUS), Some(vec![vec![3]]));
+        assert_eq!(run("00539a", vec![], CONSENSUS), Some(vec![vec![]]));
+        assert_eq!(run("00539b", vec![], CONSENSUS), Some(vec![vec![1]]));
+
+        // OP_WITHIN IS MIN INCLUSIVE, MAX EXCLUSIVE
+        assert_eq!(run("525254a5", vec![], CONSENSUS), Some(vec![vec![1]]));
+        assert_eq!(run("545254a5", vec![], CONSENSUS), Some(vec![vec![]]));
+
+        // OP_NUMEQUAL COMPARES VALUES, NOT ENCODINGS: 0x0200 IS 2
+        assert_eq!(run("0202005287", vec![], CONSENSUS), Some(vec![vec![]]));
+        assert_eq!(run("020200529c", vec![], CONSENSUS), Some(vec![vec![1]]));
+        assert_eq!(run("020200529d", vec![], CONSENSUS), Some(vec![]));
+        assert_eq!(run("020300529d", vec![], CONSENSUS), None);
+
+        // OPERANDS ARE LIMITED TO 4 BYTES, RESULTS ARE NOT UNTIL THEY ARE USED AGAIN
+        assert_eq!(
+            run("04ffffff7f8b", vec![], CONSENSUS),
+            Some(vec![vec![0, 0, 0, 0x80, 0]])
+        );
+        assert_eq!(run("04ffffff7f8b8b", vec![], CONSENSUS), None);
+        assert_eq!(run("05000000000051938b", vec![], CONSENSUS), None);
+
+        // A NUMBER OPCODE ON AN EMPTY STACK
+        assert_eq!(run("93", vec![vec![1]], CONSENSUS), None);
+    }
+
+    #[test]
+    fn stack_ops_test() {
+        let items = || vec![vec![1], vec![2], vec![3]];
+
+        // OP_ROT, OP_TUCK, OP_2DUP, OP_3DUP, OP_NIP, OP_OVER
+        assert_eq!(
+            run("7b", items(), CONSENSUS),
+            Some(vec![vec![2], vec![3], vec![1]])
+        );
+        assert_eq!(
+            run("7d", items(), CONSENSUS),
+            Some(vec![vec![1], vec![3], vec![2], vec![3]])
+        );
+        assert_eq!(
+            run("6e", items(), CONSENSUS).map(|stack| stack.len()),
+            Some(5)
+        );
+        assert_eq!(
+            run("6f", items(), CONSENSUS).map(|stack| stack[3..].to_vec()),
+            Some(items())
+        );
+        assert_eq!(run("77", items(), CONSENSUS), Some(vec![vec![1], vec![3]]));
+        assert_eq!(
+            run("78", items(), CONSENSUS),
+            Some(vec![vec![1], vec![2], vec![3], vec![2]])
+        );
+
+        // OP_2 OP_PICK COPIES THE THIRD ITEM, OP_2 OP_ROLL MOVES IT
+        assert_eq!(
+            run("5279", items(), CONSENSUS),
+            Some(vec![vec![1], vec![2], vec![3], vec![1]])
+        );
+        assert_eq!(
+            run("527a", items(), CONSENSUS),
+            Some(vec![vec![2], vec![3], vec![1]])
+        );
+        assert_eq!(run("5379", items(), CONSENSUS), None);
+        assert_eq!(run("4f79", items(), CONSENSUS), None);
+
+        // OP_2SWAP / OP_2ROT MOVE PAIRS
+        assert_eq!(
+            run("72", vec![vec![1], vec![2], vec![3], vec![4]], CONSENSUS),
+            Some(vec![vec![3], vec![4], vec![1], vec![2]])
+        );
+        assert_eq!(
+            run("71", (1..=6).map(|n| vec![n]).collect(), CONSENSUS),
+            Some(vec![vec![3], vec![4], vec![5], vec![6], vec![1], vec![2]])
+        );
+
+        // OP_TOALTSTACK OP_DROP OP_FROMALTSTACK, AND AN EMPTY ALTSTACK
+        assert_eq!(
+            run("6b756c", items(), CONSENSUS),
+            Some(vec![vec![1], vec![3]])
+        );
+        assert_eq!(run("6c", items(), CONSENSUS), None);
+    }
+
+    #[test]
+    fn hash_opcodes_test() {
+        // THE HASHES OF THE EMPTY STRING
+        let digests = [
+            ("a6", "9c1185a5c5e9fc54612808977ee8f548b2258d31"),
+            ("a7", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+            (
+                "a8",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            ("a9", "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb"),
+            (
+                "aa",
+                "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456",
+            ),
+        ];
+        for (opcode, digest) in digests {
+            assert_eq!(
+                run(opcode, vec![vec![]], CONSENSUS),
+                Some(vec![hex::decode(digest).unwrap()])
+            );
+        }
+        assert_eq!(
+            run("aa", vec![vec![]], CONSENSUS),
+            run("a8a8", vec![vec![]], CONSENSUS)
+        );
+    }
+
+    #[test]
+    fn disabled_opcodes_test() {
+        // OP_CAT AND OP_MUL FAIL WHEN EXECUTED
+        assert_eq!(run("7e", vec![vec![1], vec![2]], CONSENSUS), None);
+        assert_eq!(run("95", vec![vec![1], vec![2]], CONSENSUS), None);
+
+        // ... AND IN A BRANCH THAT IS NEVER TAKEN, AS DO OP_VERIF AND OP_VERNOTIF
+        assert_eq!(run("00637e6851", vec![], CONSENSUS), None);
+        assert_eq!(run("0063986851", vec![], CONSENSUS), None);
+        assert_eq!(run("0063656851", vec![], CONSENSUS), None);
+
+        // OP_RESERVED AND UNASSIGNED OPCODES ONLY FAIL WHEN EXECUTED
+        assert_eq!(run("50", vec![], CONSENSUS), None);
+        assert_eq!(run("0063506851", vec![], CONSENSUS), Some(vec![vec![1]]));
+        assert_eq!(run("0063bb6851", vec![], CONSENSUS), Some(vec![vec![1]]));
+
+        // THE UPGRADABLE NOPS DO NOTHING
+        assert_eq!(run("b0b3b951", vec![], CONSENSUS), Some(vec![vec![1]]));
+    }
+
+    #[test]
+    fn legacy_script_code_test() {
+        // OP_1 OP_CODESEPARATOR OP_PUSHBYTES_1 ab OP_CODESEPARATOR OP_CHECKSIG
+        let script = hex::decode("51ab01ababac").unwrap();
+        assert_eq!(
+            legacy_script_code(&script),
+            hex::decode("5101abac").unwrap()
+        );
+
+        // THE SEPARATOR MOVES WHERE THE SCRIPT CODE STARTS, OTHERWISE A NOP
+        assert_eq!(run("51ab", vec![], CONSENSUS), Some(vec![vec![1]]));
+    }
+
+    #[test]
+    fn find_and_delete_test() {
+        let delete = |script: &str, pattern: &str| {
+            let script = hex::decode(script).unwrap();
+            let (kept, found) = find_and_delete(&script, &hex::decode(pattern).unwrap());
+            (hex::encode(kept), found)
+        };
+
+        // CASES FROM BITCOIN CORE'S script_FindAndDelete
+        assert_eq!(delete("0302ff030302ff03", "0302ff03"), ("".into(), 2));
+        assert_eq!(delete("0003feed", "03feed"), ("00".into(), 1));
+        assert_eq!(delete("0003feed", "00"), ("03feed".into(), 1));
+        assert_eq!(delete("5152", "52"), ("51".into(), 1));
+
+        // ONLY WHERE AN OPCODE STARTS, NOT INSIDE A PUSH
+        assert_eq!(delete("0302ff03", "02ff03"), ("0302ff03".into(), 0));
+        assert_eq!(delete("02feed51", "51"), ("02feed".into(), 1));
+        assert_eq!(delete("03feed51", "51"), ("03feed51".into(), 0));
+    }
+
+    #[test]
+    fn legacy_signature_in_script_code_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let cache = SighashCache::new(&tx)?;
+
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[0x11; 32])?;
+        let pubkey = PublicKey::from_secret_key(&secp, &secret_key).serialize();
+
+        // A SIGHASH_ALL SIGNATURE OF INPUT 0 OVER A LEGACY SCRIPT CODE
+        let sign = |script_code: &[u8]| -> Result<Vec<u8>> {
+            let mut preimage = trimmed_tx(&tx, &cache, 0, "NON_SEGWIT", 1, Some(script_code))?;
+            preimage.extend(1u32.to_le_bytes());
+            let msg = Message::from_digest_slice(&double_sha256(&preimage))?;
+            let mut signature = secp.sign_ecdsa(&msg, &secret_key).serialize_der().to_vec();
+            signature.push(1);
+            Ok(signature)
+        };
+        let eval_legacy = |script: &[u8], flags| {
+            let mut stack = Vec::new();
+            eval_script(&mut stack, script, &tx, &cache, 0, "NON_SEGWIT", flags).map(|_| stack)
+        };
+
+        // <sig> <pubkey> OP_CHECKSIG: THE SIGNATURE COMMITS TO THE SCRIPT WITHOUT ITS OWN PUSH
+        let mut signed_code = encode_push(&pubkey);
+        signed_code.push(OP_CHECKSIG);
+        let mut script = encode_push(&sign(&signed_code)?);
+        script.extend(&signed_code);
+        assert_eq!(eval_legacy(&script, CONSENSUS), Ok(vec![vec![1]]));
+        assert_eq!(
+            eval_legacy(&script, STANDARD),
+            Err(ScriptError::SigFindAndDelete)
+        );
+
+        // OP_0 <sig> OP_1 <pubkey> OP_1 OP_CHECKMULTISIG
+        let mut signed_code = vec![OP_0, OP_1];
+        signed_code.extend(encode_push(&pubkey));
+        signed_code.extend([OP_1, OP_CHECKMULTISIG]);
+        let mut script = vec![OP_0];
+        script.extend(encode_push(&sign(&signed_code)?));
+        script.extend(&signed_code[1..]);
+        assert_eq!(eval_legacy(&script, CONSENSUS), Ok(vec![vec![1]]));
+        assert_eq!(
+            eval_legacy(&script, STANDARD),
+            Err(ScriptError::SigFindAndDelete)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_codes_test() {
+        assert_eq!(eval("6a", vec![], CONSENSUS), Err(ScriptError::OpReturn));
+        assert_eq!(eval("0069", vec![], CONSENSUS), Err(ScriptError::Verify));
+        assert_eq!(
+            eval("515288", vec![], CONSENSUS),
+            Err(ScriptError::EqualVerify)
+        );
+        assert_eq!(
+            eval("76", vec![], CONSENSUS),
+            Err(ScriptError::InvalidStackOperation)
+        );
+        assert_eq!(
+            eval("6c", vec![], CONSENSUS),
+            Err(ScriptError::InvalidAltstackOperation)
+        );
+        assert_eq!(
+            eval("7e", vec![], CONSENSUS),
+            Err(ScriptError::DisabledOpcode)
+        );
+        assert_eq!(eval("50", vec![], CONSENSUS), Err(ScriptError::BadOpcode));
+        assert_eq!(
+            eval("6351", vec![vec![1]], CONSENSUS),
+            Err(ScriptError::UnbalancedConditional)
+        );
+        assert_eq!(
+            eval("6351", vec![vec![2]], STANDARD),
+            Err(ScriptError::MinimalIf)
+        );
+        assert_eq!(
+            eval("0500000000005193", vec![], CONSENSUS),
+            Err(ScriptError::NumOverflow)
+        );
+
+        // A PUSH THAT RUNS PAST THE END OF THE SCRIPT
+        assert_eq!(eval("0201", vec![], CONSENSUS), Err(ScriptError::BadOpcode));
+    }
+
+    #[test]
+    fn signature_encoding_errors_test() {
+        let key = "03bf68f1ce783df58a2459d549d5c655a1edc0f0cf4d79421fe978d358d79ee42a";
+        let compressed = hex::decode(key).unwrap();
+        let uncompressed = [vec![0x04], vec![0x11; 64]].concat();
+
+        // THE KEY IS CHECKED EVEN WHEN THE SIGNATURE IS EMPTY
+        assert_eq!(
+            run("ac", vec![vec![], compressed.clone()], STANDARD),
+            Some(vec![vec![]])
+        );
+        assert_eq!(
+            eval("ac", vec![vec![], vec![0x06; 65]], STANDARD),
+            Err(ScriptError::PubkeyType)
+        );
+        assert_eq!(
+            eval("ac", vec![vec![], uncompressed], STANDARD),
+            Err(ScriptError::WitnessPubkeyType)
+        );
+        assert_eq!(
+            eval("ac", vec![vec![0x30, 0x01], compressed], STANDARD),
+            Err(ScriptError::SigDer)
+        );
+
+        // CHECKMULTISIG CHECKS EVERY SIGNATURE AND KEY IT COMPARES
+        let multisig = format!("000230015121{}51ae", key);
+        assert_eq!(eval(&multisig, vec![], STANDARD), Err(ScriptError::SigDer));
+    }
+
+    #[test]
+    fn policy_flags_test() -> Result<()> {
+        // MINIMALDATA: 0x01 IS PUSHED WITH OP_1, 0x0100 IS THE NUMBER 1 WITH A NEEDLESS ZERO BYTE
+        assert_eq!(run("0101", vec![], CONSENSUS), Some(vec![vec![1]]));
+        assert_eq!(
+            eval("0101", vec![], STANDARD),
+            Err(ScriptError::MinimalData)
+        );
+        assert_eq!(run("8b", vec![vec![1, 0]], CONSENSUS), Some(vec![vec![2]]));
+        assert_eq!(
+            eval("8b", vec![vec![1, 0]], STANDARD),
+            Err(ScriptError::MinimalData)
+        );
+
+        // OP_NOP1, AND OP_CHECKLOCKTIMEVERIFY WITHOUT ITS FLAG, ARE UPGRADABLE NOPS
+        assert_eq!(run("b051", vec![], CONSENSUS), Some(vec![vec![1]]));
+        assert_eq!(
+            eval("b051", vec![], STANDARD),
+            Err(ScriptError::DiscourageUpgradableNops)
+        );
+        let no_cltv = CONSENSUS.difference(VerifyFlags::CHECKLOCKTIMEVERIFY);
+        assert_eq!(run("b151", vec![], no_cltv), Some(vec![vec![1]]));
+        assert_eq!(
+            eval("b151", vec![], CONSENSUS),
+            Err(ScriptError::InvalidStackOperation)
+        );
+
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let cache = SighashCache::new(&tx)?;
+
+        // CONST_SCRIPTCODE: NO OP_CODESEPARATOR IN LEGACY SCRIPTS, EVEN UNEXECUTED
+        let separator = [OP_0, OP_IF, OP_CODESEPARATOR, OP_ENDIF, OP_1];
+        let legacy =
+            |flags| eval_script(&mut vec![], &separator, &tx, &cache, 0, "NON_SEGWIT", flags);
+        assert_eq!(legacy(CONSENSUS), Ok(()));
+        assert_eq!(legacy(STANDARD), Err(ScriptError::OpCodeSeparator));
+
+        // CLEANSTACK IS POLICY FOR REDEEM SCRIPTS BUT ALWAYS HOLDS FOR WITNESS SCRIPTS
+        let extra = vec![vec![1]];
+        let redeem = |flags| {
+            verify_redeem_script(extra.clone(), &[OP_1], &tx, &cache, 0, "NON_SEGWIT", flags)
+        };
+        assert_eq!(redeem(CONSENSUS), Ok(()));
+        assert_eq!(redeem(STANDARD), Err(ScriptError::CleanStack));
+        assert_eq!(
+            verify_witness_script(extra, &[OP_1], &tx, &cache, 0, "P2WSH", CONSENSUS),
+            Err(ScriptError::CleanStack)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn tapscript_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let cache = SighashCache::new(&tx)?;
+
+        let mut tapscript = Tapscript {
+            leaf_hash: [0x11; 32],
+            annex: None,
+            codesep_pos: u32::MAX,
+            validation_weight_left: 100,
+            checks: Vec::new(),
+        };
+        let mut run_tapscript = |stack: Vec<Vec<u8>>, script_hex: &str, flags| {
+            let script = hex::decode(script_hex).unwrap();
+            let result = verify_tapscript(stack, &script, &tx, &cache, 0, flags, &mut tapscript);
+            (
+                result,
+                tapscript.checks.len(),
+                tapscript.validation_weight_left,
+            )
+        };
+        let key = format!(
+            "20{}",
+            "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        );
+
+        // <sig> 0 <key> OP_CHECKSIGADD: AN EMPTY SIGNATURE ADDS 0 AND ISN'T CHECKED
+        let add = format!("00{}ba0087", key);
+        assert_eq!(
+            run_tapscript(vec![vec![]], &add, STANDARD),
+            (Ok(()), 0, 100)
+        );
+
+        // ANY OTHER IS TAKEN AS VALID, LEFT TO BE VERIFIED, AND USES UP 50 OF THE WEIGHT
+        let add = format!("00{}ba5187", key);
+        assert_eq!(
+            run_tapscript(vec![vec![1; 64]], &add, STANDARD),
+            (Ok(()), 1, 50)
+        );
+        assert_eq!(
+            run_tapscript(vec![vec![1; 64]], &add, STANDARD),
+            (Ok(()), 2, 0)
+        );
+        assert_eq!(
+            run_tapscript(vec![vec![1; 64]], &add, STANDARD).0,
+            Err(ScriptError::TapscriptValidationWeight)
+        );
+
+        // MINIMALIF IS CONSENSUS, OP_CHECKMULTISIG IS GONE, OP_SUCCESSX PASSES ANYTHING
+        assert_eq!(
+            run_tapscript(vec![vec![2]], "635168", CONSENSUS).0,
+            Err(ScriptError::TapscriptMinimalIf)
+        );
+        assert_eq!(
+            run_tapscript(vec![vec![]], "0000ae", CONSENSUS).0,
+            Err(ScriptError::TapscriptCheckMultisig)
+        );
+        assert_eq!(run_tapscript(vec![], "6a50", CONSENSUS).0, Ok(()));
+        assert_eq!(
+            run_tapscript(vec![], "6a50", STANDARD).0,
+            Err(ScriptError::DiscourageOpSuccess)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resource_limits_test() {
+        // PUSHES OF UP TO 520 BYTES, EVEN IN A BRANCH THAT IS NOT TAKEN
+        let push = |len: usize| {
+            let mut script = vec![OP_PUSHDATA2];
+            script.extend((len as u16).to_le_bytes());
+            script.extend(vec![1u8; len]);
+            script
+        };
+        assert!(eval(&hex::encode(push(520)), vec![], CONSENSUS).is_ok());
+        let mut skipped = vec![OP_0, OP_IF];
+        skipped.extend(push(521));
+        skipped.extend([OP_ENDIF, OP_1]);
+        assert_eq!(
+            eval(&hex::encode(skipped), vec![], CONSENSUS),
+            Err(ScriptError::PushSize)
+        );
+
+        // 201 NON PUSH OPCODES, COUNTED WHETHER THEY RUN OR NOT
+        assert!(eval(&hex::encode(vec![OP_NOP; 201]), vec![], CONSENSUS).is_ok());
+        let mut skipped = vec![OP_0, OP_IF];
+        skipped.extend(vec![OP_NOP; 200]);
+        skipped.push(OP_ENDIF);
+        assert_eq!(
+            eval(&hex::encode(skipped), vec![], CONSENSUS),
+            Err(ScriptError::OpCount)
+        );
+
+        // 1000 ITEMS ON THE STACK AND ALTSTACK TOGETHER
+        let items = vec![vec![1u8]; 998];
+        assert!(eval("51", items.clone(), CONSENSUS).is_ok());
+        assert_eq!(
+            eval("516b5151", items, CONSENSUS),
+            Err(ScriptError::StackSize)
+        );
+
+        // SCRIPTS OF UP TO 10,000 BYTES, PADDED WITH PUSHES IN A BRANCH THAT IS NOT TAKEN
+        let padded = |len: usize| {
+            let mut script = vec![OP_0, OP_IF];
+            while script.len() + 2 < len {
+                let push_len = (len - script.len() - 3).min(75);
+                script.push(push_len as u8);
+                script.extend(vec![0u8; push_len]);
+            }
+            script.extend([OP_ENDIF, OP_1]);
+            hex::encode(script)
+        };
+        assert_eq!(
+            eval(&padded(MAX_SCRIPT_SIZE), vec![], CONSENSUS),
+            Ok(vec![vec![1]])
+        );
+        assert_eq!(
+            eval(&padded(MAX_SCRIPT_SIZE + 1), vec![], CONSENSUS),
+            Err(ScriptError::ScriptSize)
+        );
+    }
+
+    #[test]
+    fn checkmultisig_test() -> Result<()> {
+        let path =
+            "./mempool/0bec1aee6decd078b98553691be92f99ad12271241c6b6f7cf00433954d3f166.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let cache = SighashCache::new(&tx)?;
+
+        // <dummy> <sig> <sig> <2-OF-3 MULTISIG SCRIPT>
+        let mut witness: Vec<Vec<u8>> = Vec::new();
+        for item in tx.vin[0].witness.clone().unwrap_or_default() {
+            witness.push(hex::decode(item)?);
+        }
+        let witness_script = witness.pop().unwrap_or_default();
+        let verify_with = |witness: Vec<Vec<u8>>, flags: VerifyFlags| {
+            verify_witness_script(witness, &witness_script, &tx, &cache, 0, "P2WSH", flags)
+        };
+        let verify = |witness: Vec<Vec<u8>>| verify_with(witness, STANDARD);
+        assert_eq!(verify(witness.clone()), Ok(()));
+
+        // SIGNATURES MUST BE IN THE ORDER OF THEIR KEYS
+        // A FAILED CHECK WITH NON EMPTY SIGNATURES ONLY BREAKS POLICY (NULLFAIL)
+        let mut swapped = witness.clone();
+        swapped.swap(1, 2);
+        assert_eq!(
+            verify_with(swapped.clone(), CONSENSUS),
+            Err(ScriptError::EvalFalse)
+        );
+        assert_eq!(verify(swapped), Err(ScriptError::SigNullFail));
+
+        // THE SAME SIGNATURE TWICE ONLY MATCHES ONE KEY
+        let mut repeated = witness.clone();
+        repeated[1] = repeated[2].clone();
+        assert_eq!(
+            verify_with(repeated.clone(), CONSENSUS),
+            Err(ScriptError::EvalFalse)
+        );
+        assert_eq!(verify(repeated), Err(ScriptError::SigNullFail));
+
+        // EMPTY SIGNATURES FAIL THE CHECK WITHOUT BREAKING NULLFAIL
+        let mut empty = witness.clone();
+        empty[1] = Vec::new();
+        empty[2] = Vec::new();
+        assert_eq!(verify(empty), Err(ScriptError::EvalFalse));
+
+        // BIP147: THE DUMMY MUST BE EMPTY, AND IT MUST BE THERE
+        let mut dummy = witness.clone();
+        dummy[0] = vec![0];
+        assert_eq!(verify(dummy), Err(ScriptError::SigNullDummy));
+        let no_nulldummy = CONSENSUS.difference(VerifyFlags::NULLDUMMY);
+        let mut dummy = witness.clone();
+        dummy[0] = vec![0];
+        assert_eq!(verify_with(dummy, no_nulldummy), Ok(()));
+        assert_eq!(
+            verify(witness[1..].to_vec()),
+            Err(ScriptError::InvalidStackOperation)
+        );
+
+        // 0-OF-0, 21 KEYS, AND MORE SIGNATURES THAN KEYS
+        assert_eq!(run("000000ae", vec![], CONSENSUS), Some(vec![vec![1]]));
+        assert_eq!(
+            eval("00000115ae", vec![], CONSENSUS),
+            Err(ScriptError::PubkeyCount)
+        );
+        assert_eq!(
+            eval("005100ae", vec![], CONSENSUS),
+            Err(ScriptError::SigCount)
+        );
+
+        // EVERY KEY COUNTS AS AN OPCODE: 180 NOPS AND A 20 KEY CHECKMULTISIG ARE 201
+        let multisig = |nops: usize| {
+            let mut script = vec![OP_NOP; nops];
+            script.extend([OP_0, OP_0]);
+            for _ in 0..MAX_PUBKEYS_PER_MULTISIG {
+                script.extend([1, 0]);
+            }
+            script.extend([1, MAX_PUBKEYS_PER_MULTISIG as u8, OP_CHECKMULTISIG]);
+            hex::encode(script)
+        };
+        assert_eq!(run(&multisig(180), vec![], CONSENSUS), Some(vec![vec![1]]));
+        assert_eq!(
+            eval(&multisig(181), vec![], CONSENSUS),
+            Err(ScriptError::OpCount)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_witness_script_test() -> Result<()> {
+        let path =
+            "./mempool/0bec1aee6decd078b98553691be92f99ad12271241c6b6f7cf00433954d3f166.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let cache = SighashCache::new(&tx)?;
+
+        let mut witness: Vec<Vec<u8>> = Vec::new();
+        for item in tx.vin[0].witness.clone().unwrap_or_default() {
+            witness.push(hex::decode(item)?);
+        }
+        let witness_script = witness.pop().unwrap_or_default();
+
+        assert_eq!(
+            verify_witness_script(
+                witness.clone(),
+                &witness_script,
+                &tx,
+                &cache,
+                0,
+                "P2WSH",
+                STANDARD
+            ),
+            Ok(())
+        );
+
+        // FLIPPING A BYTE OF THE FIRST SIGNATURE'S S VALUE INVALIDATES IT
+        let first_sig = witness.iter().position(|item| item.len() > 60).unwrap();
+        let sig_len = witness[first_sig].len();
+        witness[first_sig][sig_len - 2] ^= 1;
+        assert_eq!(
+            verify_witness_script(
+                witness.clone(),
+                &witness_script,
+                &tx,
+                &cache,
+                0,
+                "P2WSH",
+                CONSENSUS
+            ),
+            Err(ScriptError::EvalFalse)
+        );
+        assert_eq!(
+            verify_witness_script(
+                witness.clone(),
+                &witness_script,
+                &tx,
+                &cache,
+                0,
+                "P2WSH",
+                STANDARD
+            ),
+            Err(ScriptError::SigNullFail)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+// TRANSACTION FINALITY (IsFinalTx): A TX CAN ONLY BE MINED ONCE ITS LOCKTIME HAS PASSED
+// RELATIVE LOCKS (BIP68) AND THE SCRIPT CHECKS OF BIP65 AND BIP112 ARE HERE TOO
+
+use crate::{script::decode_script_num, transaction::Transaction, utxo::UtxoSource};
+
+// LOCKTIMES BELOW THIS ARE BLOCK HEIGHTS, FROM IT ON THEY ARE UNIX TIMESTAMPS
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+// AN INPUT WITH THIS SEQUENCE OPTS OUT OF THE LOCKTIME
+pub const SEQUENCE_FINAL: u32 = 0xffffffff;
+
+// BIP68: A SEQUENCE WITH THIS BIT SET HAS NO RELATIVE LOCK
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+
+// SET FOR A RELATIVE LOCK IN UNITS OF 512 SECONDS, CLEAR FOR ONE IN BLOCKS
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+
+pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000ffff;
+
+// 2^9 = 512 SECONDS PER UNIT OF A TIME BASED RELATIVE LOCK
+pub const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 9;
+
+// OPERANDS OF OP_CHECKLOCKTIMEVERIFY AND OP_CHECKSEQUENCEVERIFY MAY BE 5 BYTES, NOT 4
+pub const LOCKTIME_NUM_SIZE: usize = 5;
+
+// THE BLOCK A TX IS CHECKED FOR: ITS HEIGHT AND THE MEDIAN TIME PAST OF ITS ANCESTORS (BIP113)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockContext {
+    pub height: u32,
+    pub median_time_past: u32,
+}
+
+impl BlockContext {
+    pub fn new(height: u32, median_time_past: u32) -> Self {
+        BlockContext {
+            height,
+            median_time_past,
+        }
+    }
+}
+
+// A ZERO LOCKTIME, ONE BELOW THE BLOCK'S HEIGHT OR MTP, OR ONLY FINAL SEQUENCES
+pub fn is_final_tx(tx: &Transaction, context: &BlockContext) -> bool {
+    if tx.locktime == 0 {
+        return true;
+    }
+
+    let cutoff = if tx.locktime < LOCKTIME_THRESHOLD {
+        context.height
+    } else {
+        context.median_time_past
+    };
+
+    if tx.locktime < cutoff {
+        return true;
+    }
+
+    tx.vin.iter().all(|input| input.sequence == SEQUENCE_FINAL)
+}
+
+// LAST HEIGHT AND MEDIAN TIME PAST AT WHICH A TX IS STILL LOCKED, -1 FOR NO LOCK OF THAT KIND
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceLock {
+    pub min_height: i64,
+    pub min_time: i64,
+}
+
+impl SequenceLock {
+    // EvaluateSequenceLocks
+    pub fn is_satisfied(&self, context: &BlockContext) -> bool {
+        self.min_height < context.height as i64 && self.min_time < context.median_time_past as i64
+    }
+}
+
+// CalculateSequenceLocks: EVERY INPUT WITH A RELATIVE LOCK COUNTS FROM THE BLOCK ITS COIN WAS MINED IN
+// A COIN THE SOURCE DOES NOT KNOW IS UNCONFIRMED, SO IT IS MINED IN THE BLOCK OF `context` AT THE EARLIEST
+pub fn sequence_locks(
+    tx: &Transaction,
+    utxos: &impl UtxoSource,
+    context: &BlockContext,
+) -> SequenceLock {
+    let mut lock = SequenceLock {
+        min_height: -1,
+        min_time: -1,
+    };
+
+    // RELATIVE LOCKS ONLY APPLY FROM VERSION 2
+    if tx.version < 2 {
+        return lock;
+    }
+
+    for input in tx.vin.iter() {
+        if input.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            continue;
+        }
+
+        let coin = utxos.coin_context(&input.outpoint()).unwrap_or(*context);
+        let value = (input.sequence & SEQUENCE_LOCKTIME_MASK) as i64;
+
+        if input.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+            let unlock_time =
+                coin.median_time_past as i64 + (value << SEQUENCE_LOCKTIME_GRANULARITY) - 1;
+            lock.min_time = lock.min_time.max(unlock_time);
+        } else {
+            lock.min_height = lock.min_height.max(coin.height as i64 + value - 1);
+        }
+    }
+
+    lock
+}
+
+// CheckLockTime (BIP65): THE TX'S LOCKTIME IS OF THE SAME KIND AS `lock_time` AND NOT BELOW IT
+pub fn check_lock_time(lock_time: i64, tx: &Transaction, tx_input_index: usize) -> bool {
+    let tx_lock_time = tx.locktime as i64;
+    let threshold = LOCKTIME_THRESHOLD as i64;
+
+    if (tx_lock_time < threshold) != (lock_time < threshold) {
+        return false;
+    }
+    if lock_time > tx_lock_time {
+        return false;
+    }
+
+    // A FINAL SEQUENCE WOULD LET THE TX IGNORE ITS LOCKTIME
+    tx.vin[tx_input_index].sequence != SEQUENCE_FINAL
+}
+
+// CheckSequence (BIP112): THE INPUT'S RELATIVE LOCK IS OF THE SAME KIND AS `sequence` AND NOT BELOW IT
+pub fn check_sequence(sequence: i64, tx: &Transaction, tx_input_index: usize) -> bool {
+    let tx_sequence = tx.vin[tx_input_index].sequence;
+
+    if tx.version < 2 || tx_sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+        return false;
+    }
+
+    let mask = (SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK) as i64;
+    let tx_masked = tx_sequence as i64 & mask;
+    let masked = sequence & mask;
+
+    let type_flag = SEQUENCE_LOCKTIME_TYPE_FLAG as i64;
+    if (tx_masked & type_flag) != (masked & type_flag) {
+        return false;
+    }
+
+    masked <= tx_masked
+}
+
+// OP_CHECKLOCKTIMEVERIFY: THE TOP ITEM STAYS ON THE STACK AND MUST BE A NON NEGATIVE LOCKTIME
+pub fn op_checklocktimeverify(stack: &[Vec<u8>], tx: &Transaction, tx_input_index: usize) -> bool {
+    match stack
+        .last()
+        .and_then(|top| decode_script_num(top, LOCKTIME_NUM_SIZE).ok())
+    {
+        Some(lock_time) if lock_time >= 0 => check_lock_time(lock_time, tx, tx_input_index),
+        _ => false,
+    }
+}
+
+// OP_CHECKSEQUENCEVERIFY: LIKE OP_CHECKLOCKTIMEVERIFY, BUT AN OPERAND WITH THE DISABLE FLAG IS A NOP
+pub fn op_checksequenceverify(stack: &[Vec<u8>], tx: &Transaction, tx_input_index: usize) -> bool {
+    match stack
+        .last()
+        .and_then(|top| decode_script_num(top, LOCKTIME_NUM_SIZE).ok())
+    {
+        Some(sequence) if sequence >= 0 => {
+            sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG as i64 != 0
+                || check_sequence(sequence, tx, tx_input_index)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::{error::Result, utxo::UtxoSet};
+
+    use super::*;
+
+    #[test]
+    fn is_final_tx_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let mut tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        tx.vin[0].sequence = 0xfffffffd;
+
+        let context = BlockContext::new(834000, 1710000000);
+
+        tx.locktime = 0;
+        assert!(is_final_tx(&tx, &context));
+
+        // HEIGHT LOCKS MUST BE STRICTLY BELOW THE HEIGHT OF THE BLOCK
+        tx.locktime = 833999;
+        assert!(is_final_tx(&tx, &context));
+        tx.locktime = 834000;
+        assert!(!is_final_tx(&tx, &context));
+
+        // TIME LOCKS ARE COMPARED WITH THE MEDIAN TIME PAST, NOT THE HEIGHT
+        tx.locktime = 1709999999;
+        assert!(is_final_tx(&tx, &context));
+        tx.locktime = 1710000000;
+        assert!(!is_final_tx(&tx, &context));
+
+        // FINAL SEQUENCES ON EVERY INPUT DISABLE THE LOCKTIME
+        for input in tx.vin.iter_mut() {
+            input.sequence = SEQUENCE_FINAL;
+        }
+        assert!(is_final_tx(&tx, &context));
+
+        Ok(())
+    }
+
+    #[test]
+    fn sequence_locks_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let mut tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        tx.version = 2;
+        tx.vin.truncate(1);
+
+        let mut utxos = UtxoSet::new();
+        utxos.insert(tx.vin[0].outpoint(), BlockContext::new(900000, 1750000000));
+
+        // 10 BLOCKS AFTER THE COIN'S OWN BLOCK
+        tx.vin[0].sequence = 10;
+        let lock = sequence_locks(&tx, &utxos, &BlockContext::new(900010, 0));
+        assert_eq!(lock.min_height, 900009);
+        assert!(lock.is_satisfied(&BlockContext::new(900010, 0)));
+        assert!(!lock.is_satisfied(&BlockContext::new(900009, 0)));
+
+        // 2 * 512 SECONDS AFTER THE MEDIAN TIME PAST BEFORE THE COIN'S BLOCK
+        tx.vin[0].sequence = SEQUENCE_LOCKTIME_TYPE_FLAG | 2;
+        let lock = sequence_locks(&tx, &utxos, &BlockContext::new(900010, 0));
+        assert_eq!(lock.min_time, 1750001023);
+        assert!(lock.is_satisfied(&BlockContext::new(900001, 1750001024)));
+        assert!(!lock.is_satisfied(&BlockContext::new(900001, 1750001023)));
+
+        // AN UNCONFIRMED COIN IS MINED IN THE SAME BLOCK, ONLY A ZERO LOCK CAN BE MET
+        let unconfirmed = UtxoSet::new();
+        let context = BlockContext::new(900000, 1750000000);
+        tx.vin[0].sequence = 0;
+        assert!(sequence_locks(&tx, &unconfirmed, &context).is_satisfied(&context));
+        tx.vin[0].sequence = 1;
+        assert!(!sequence_locks(&tx, &unconfirmed, &context).is_satisfied(&context));
+
+        // VERSION 1 AND THE DISABLE FLAG TURN RELATIVE LOCKS OFF
+        tx.vin[0].sequence = SEQUENCE_LOCKTIME_DISABLE_FLAG | 1;
+        assert!(sequence_locks(&tx, &unconfirmed, &context).is_satisfied(&context));
+        tx.vin[0].sequence = 1;
+        tx.version = 1;
+        assert!(sequence_locks(&tx, &unconfirmed, &context).is_satisfied(&context));
+
+        Ok(())
+    }
+
+    #[test]
+    fn locktime_opcodes_test() -> Result<()> {
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let mut tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        tx.version = 2;
+        tx.locktime = 834000;
+        tx.vin[0].sequence = 0xfffffffe;
+
+        // CLTV: HEIGHT OPERAND AT OR BELOW THE TX'S HEIGHT LOCKTIME
+        let operand = |hex_num: &str| vec![hex::decode(hex_num).unwrap()];
+        assert!(op_checklocktimeverify(&operand("d0b90c"), &tx, 0));
+        assert!(!op_checklocktimeverify(&operand("d1b90c"), &tx, 0));
+        assert!(!op_checklocktimeverify(&operand("81"), &tx, 0));
+        assert!(!op_checklocktimeverify(&[], &tx, 0));
+
+        // A TIME OPERAND CAN'T BE MET BY A HEIGHT LOCKTIME
+        assert!(!op_checklocktimeverify(&operand("0065cd1d"), &tx, 0));
+
+        // A FINAL SEQUENCE DISABLES THE LOCKTIME, SO CLTV FAILS
+        tx.vin[0].sequence = SEQUENCE_FINAL;
+        assert!(!op_checklocktimeverify(&operand("d0b90c"), &tx, 0));
+
+        // CSV: 51840 BLOCKS (80ca00) AGAINST THE INPUT'S OWN SEQUENCE
+        tx.vin[0].sequence = 51840;
+        assert!(op_checksequenceverify(&operand("80ca00"), &tx, 0));
+        tx.vin[0].sequence = 51839;
+        assert!(!op_checksequenceverify(&operand("80ca00"), &tx, 0));
+
+        // A TIME SEQUENCE DOES NOT SATISFY A HEIGHT OPERAND
+        tx.vin[0].sequence = SEQUENCE_LOCKTIME_TYPE_FLAG | 51840;
+        assert!(!op_checksequenceverify(&operand("80ca00"), &tx, 0));
+
+        // AN OPERAND WITH THE DISABLE FLAG IS A NOP, VERSION 1 TXS FAIL EVERY OTHER OPERAND
+        assert!(op_checksequenceverify(&operand("0000008000"), &tx, 0));
+        tx.version = 1;
+        tx.vin[0].sequence = 51840;
+        assert!(!op_checksequenceverify(&operand("80ca00"), &tx, 0));
+
+        Ok(())
+    }
+}
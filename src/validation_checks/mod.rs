@@ -1,7 +1,8 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    fmt,
     fs::{self},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 use ripemd::Ripemd160;
@@ -9,17 +10,59 @@ use secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1};
 use sha2::{Digest, Sha256};
 use walkdir::WalkDir;
 
-use crate::{error::Result, transaction::Transaction};
+use failure::format_err;
+
+use crate::{
+    amount::Amount,
+    block_mine::serialise_tx::compact_size,
+    error::Result,
+    hashes::{OutPoint, Txid},
+    script::ScriptType,
+    transaction::Transaction,
+    utxo::UtxoSource,
+};
 
 use self::{
-    p2pkh::input_verification_p2pkh, p2wpkh::input_verification_p2wpkh,
+    amounts::{check_amounts, AmountError},
+    consistency::{check_consistency, FieldMismatch},
+    fees::{FeeRate, MempoolFees},
+    interpreter::{encoding_error_or_ok, verify_legacy_script},
+    locktime::{is_final_tx, sequence_locks, BlockContext, SequenceLock},
+    p2pkh::input_verification_p2pkh,
+    p2sh::input_verification_p2sh,
+    p2wpkh::input_verification_p2wpkh,
     p2wsh::input_verification_p2wsh,
+    policy::{Policy, PolicyViolation},
+    script_error::ScriptError,
+    sig_cache::{signature_cache, verify_cached, SigKind},
+    sig_encoding::{
+        check_pubkey_encoding, check_signature_encoding, is_witness_v0, SIGHASH_ANYONECANPAY,
+        SIGHASH_NONE, SIGHASH_SINGLE,
+    },
+    sighash_cache::SighashCache,
+    taproot::{
+        input_verification_p2tr, verify_schnorr_checks, SchnorrCheck, SchnorrChecks, SchnorrMode,
+    },
+    verify_flags::VerifyFlags,
 };
 
+pub mod amounts;
+pub mod consistency;
+pub mod fees;
+pub mod interpreter;
+pub mod locktime;
 pub mod p2pkh;
 pub mod p2sh;
 pub mod p2wpkh;
 pub mod p2wsh;
+pub mod policy;
+pub mod script_error;
+pub mod sig_cache;
+pub mod sig_encoding;
+pub mod sighash_cache;
+pub mod sigops;
+pub mod taproot;
+pub mod verify_flags;
 
 // HASH160
 pub fn hash160(data: &[u8]) -> Vec<u8> {
@@ -37,644 +80,645 @@ pub fn single_sha256(data: &[u8]) -> Vec<u8> {
 }
 
 // OPCHECKSIG OPCODE IMPLEMENTATION
+// A SIGNATURE OR KEY BREAKING AN ENCODING RULE OF `flags` IS A SCRIPT ERROR, NOT A FAILED CHECK
 pub fn op_checksig(
     stack: &mut Vec<Vec<u8>>,
-    tx: Transaction,
+    tx: &Transaction,
+    cache: &SighashCache,
     tx_input_index: usize,
     input_type: &str,
+    script_code: Option<&[u8]>,
+    flags: VerifyFlags,
 ) -> Result<bool> {
-    // CREATE DUMMY PUBKEY AND SIGNATURES INCASE THEIR FORMAT IS NOT AS EXPECTED
-
-    let dummy_pubkey: PublicKey = PublicKey::from_slice(
-        &hex::decode("03bf68f1ce783df58a2459d549d5c655a1edc0f0cf4d79421fe978d358d79ee42a").unwrap(),
-    )
-    .unwrap();
-    let dummy_signature: Signature = Signature::from_der(&hex::decode("304402205112f96bf7423703c221976603307f0e33913d39efc3344d68376fd2b8c0bd20022003ea588c06fa1a3e262e07ed6bf01a36f78741fe7bc6a91ff43c38a0a14e43fe").unwrap()).unwrap();
-
-    // POP THE PUBLIC KEY FROM THE STACK
-
-    let pubkey_bytes = stack.pop().unwrap();
-    let pubkey = PublicKey::from_slice(&pubkey_bytes).unwrap_or(dummy_pubkey);
-    let signature_bytes = stack.pop().expect("STACK UNDERFLOW");
-
-    let sig = Signature::from_der(&signature_bytes[..signature_bytes.len() - 1])
-        .unwrap_or(dummy_signature);
-
-    // EXTRACT THE SIGHASH TYPE
-    let sighash_type = signature_bytes.last().copied().expect("SIGHASH: MISSING") as u32;
-
-    let mut trimmed_tx = trimmed_tx(tx.clone(), tx_input_index, input_type, sighash_type.clone())?;
-    trimmed_tx.extend(&sighash_type.to_le_bytes());
-
-    // HASH256 THE TRIMMED-TX AND THEN CREATE THE MESSAGE OBJECT
-    let trimmed_tx_hash = double_sha256(&trimmed_tx);
-    let msg = Message::from_digest_slice(&trimmed_tx_hash).expect("PARSING: FAILED");
-
-    // VERIFYING THE SIGNATURE
-    let secp = Secp256k1::new();
-
-    let mut result: bool = false;
-
-    // ECDSA VERIFICATION FUNCTION
-    if secp.verify_ecdsa(&msg, &sig, &pubkey).is_ok() {
-        result = true;
+    // POP THE PUBLIC KEY AND THE SIGNATURE FROM THE STACK
+    let pubkey_bytes = stack.pop().ok_or(ScriptError::InvalidStackOperation)?;
+    let signature_bytes = stack.pop().ok_or(ScriptError::InvalidStackOperation)?;
+
+    check_signature_encoding(&signature_bytes, flags)?;
+    check_pubkey_encoding(&pubkey_bytes, flags, is_witness_v0(input_type))?;
+
+    let result = check_signature(
+        &signature_bytes,
+        &pubkey_bytes,
+        tx,
+        cache,
+        tx_input_index,
+        input_type,
+        script_code,
+    )?;
+
+    // NULLFAIL: A FAILED CHECK ONLY GETS AN EMPTY SIGNATURE
+    if !result && flags.contains(VerifyFlags::NULLFAIL) && !signature_bytes.is_empty() {
+        return Err(ScriptError::SigNullFail.into());
     }
     Ok(result)
 }
 
-// OPMULTICHECKSIG OPCODE IMPLEMENTATION
-pub fn op_checkmultisig(
-    stack: &mut Vec<Vec<u8>>,
-    tx: Transaction,
+// ONE SIGNATURE, ENDING IN ITS SIGHASH TYPE, AGAINST ONE PUBLIC KEY
+// A KEY OR SIGNATURE THAT DOES NOT PARSE IS A FAILED CHECK, NOT AN ERROR
+pub fn check_signature(
+    signature_bytes: &[u8],
+    pubkey_bytes: &[u8],
+    tx: &Transaction,
+    cache: &SighashCache,
     tx_input_index: usize,
     input_type: &str,
+    script_code: Option<&[u8]>,
 ) -> Result<bool> {
-    // CREATE DUMMY PUBKEY AND SIGNATURES INCASE THEIR FORMAT IS NOT AS EXPECTED
-
-    let dummy_pubkey: PublicKey = PublicKey::from_slice(
-        &hex::decode("03bf68f1ce783df58a2459d549d5c655a1edc0f0cf4d79421fe978d358d79ee42a").unwrap(),
-    )
-    .unwrap();
-
-    let dummy_signature: Signature = Signature::from_der(&hex::decode("304402205112f96bf7423703c221976603307f0e33913d39efc3344d68376fd2b8c0bd20022003ea588c06fa1a3e262e07ed6bf01a36f78741fe7bc6a91ff43c38a0a14e43fe").unwrap()).unwrap();
-
-    // POP THE PUBLIC KEYS FROM THE STACK
-    let mut n_keys = 0;
-    if let Some(item) = stack.pop() {
-        if let Some(&byte) = item.first() {
-            n_keys = byte as u8;
-        }
-    }
-
-    // EXTRACT THE PUBKEYS AND SIGNATURES FROM THE STACK
-    let mut pubkeys: Vec<PublicKey> = Vec::new();
-
-    for _ in 0..n_keys {
-        let pubkey_bytes = stack.pop().expect("STACK UNDERFLOW: PUBKEY");
-        pubkeys.push(PublicKey::from_slice(&pubkey_bytes).unwrap_or(dummy_pubkey));
-    }
-
-    // POP THE SIGNATURES FROM THE STACK
-    let mut n_signatures = 0;
-    if let Some(item) = stack.pop() {
-        if let Some(&byte) = item.first() {
-            n_signatures = byte as u8;
-        }
-    }
-
-    let mut signatures: Vec<(Signature, u32)> = Vec::new();
-
-    for _ in 0..n_signatures {
-        let signature_bytes = stack.pop().expect("STACK UNDERFLOW: SIGNATURES");
-
-        // EXTRACT THE SIGHASH TYPE
-        let sighash_type = signature_bytes.last().copied().expect("SIGHASH: MISSING") as u32;
-        signatures.push((
-            Signature::from_der(&signature_bytes[..&signature_bytes.len() - 1])
-                .unwrap_or(dummy_signature),
-            sighash_type,
-        ));
-    }
-
-    let secp = Secp256k1::new();
-    let mut valid_sig_count = 0;
-
-    for (sig, sighash) in signatures {
-        // TRIM THE TRANSACTION AS PER THE SIGHASH_TYPE
-        let mut trimmed_tx = trimmed_tx(tx.clone(), tx_input_index, input_type, sighash.clone())?;
-        trimmed_tx.extend(&sighash.to_le_bytes());
-
-        let trimmed_tx_hash = double_sha256(&trimmed_tx);
-        let msg = Message::from_digest_slice(&trimmed_tx_hash).expect("PARSING: FAILED");
+    let (sighash_type, der) = match signature_bytes.split_last() {
+        Some((sighash_type, der)) => (*sighash_type as u32, der),
+        None => return Ok(false),
+    };
+    let pubkey = match PublicKey::from_slice(pubkey_bytes) {
+        Ok(pubkey) => pubkey,
+        Err(_) => return Ok(false),
+    };
+
+    // CONSENSUS ACCEPTS LAX DER AND HIGH S VALUES, LIBSECP256K1 ONLY VERIFIES LOW S
+    let mut sig = match Signature::from_der_lax(der) {
+        Ok(sig) => sig,
+        Err(_) => return Ok(false),
+    };
+    sig.normalize_s();
+
+    let sighash = signature_hash(
+        tx,
+        cache,
+        tx_input_index,
+        input_type,
+        sighash_type,
+        script_code,
+    )?;
+    let msg = Message::from_digest_slice(&sighash)?;
+
+    // A SIGNATURE ALREADY CHECKED, FOR THE MEMPOOL OR AN EARLIER BLOCK, ISN'T CHECKED AGAIN
+    Ok(verify_cached(
+        SigKind::Ecdsa,
+        &sighash,
+        pubkey_bytes,
+        signature_bytes,
+        || {
+            Secp256k1::verification_only()
+                .verify_ecdsa(&msg, &sig, &pubkey)
+                .is_ok()
+        },
+    ))
+}
 
-        for pk in &pubkeys {
-            if secp.verify_ecdsa(&msg, &sig, pk).is_ok() {
-                valid_sig_count += 1;
-                break;
-            }
-        }
+// THE HASH AN ECDSA SIGNATURE OF `sighash_type` SIGNS: HASH256 OF THE TRIMMED TX AND THE TYPE
+// A LEGACY SIGHASH_SINGLE WITHOUT A MATCHING OUTPUT SIGNS 1 INSTEAD, A BUG KEPT BY CONSENSUS
+pub fn signature_hash(
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    input_type: &str,
+    sighash_type: u32,
+    script_code: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let single = sighash_type as u8 & 0x1f == SIGHASH_SINGLE;
+    if input_type == "NON_SEGWIT" && single && tx_input_index >= tx.vout.len() {
+        let mut one = vec![0u8; 32];
+        one[0] = 1;
+        return Ok(one);
     }
-    let mut result: bool = false;
 
-    if valid_sig_count == n_signatures {
-        result = true;
-    }
-    Ok(result)
+    let mut trimmed_tx = trimmed_tx(
+        tx,
+        cache,
+        tx_input_index,
+        input_type,
+        sighash_type,
+        script_code,
+    )?;
+    trimmed_tx.extend(&sighash_type.to_le_bytes());
+    Ok(double_sha256(&trimmed_tx))
 }
 
-// FUNCION TO CREATE SERIALISED TRIMMED TXS AS PER THE SCRIPT TYPE FOR SIGHASH_ALL
+// FUNCION TO CREATE SERIALISED TRIMMED TXS AS PER THE SCRIPT TYPE AND SIGHASH TYPE
+// SIGHASH_NONE SIGNS NO OUTPUT, SIGHASH_SINGLE THE ONE AT THE INPUT'S INDEX, ANY OTHER TYPE ALL OF
+// THEM. SIGHASH_ANYONECANPAY SIGNS ONLY THE INPUT BEING VERIFIED
+// `script_code`: THE EXECUTING SCRIPT FROM ITS LAST OP_CODESEPARATOR ON, NONE FOR ALL OF IT
+// `cache` HOLDS THE SEGWIT HASHES OF THE WHOLE TX, SO THEY AREN'T REDONE PER INPUT
 pub fn trimmed_tx(
-    tx: Transaction,
+    tx: &Transaction,
+    cache: &SighashCache,
     tx_input_index: usize,
     input_type: &str,
     sighash_type: u32,
+    script_code: Option<&[u8]>,
 ) -> Result<Vec<u8>> {
     let mut trimmed_tx: Vec<u8> = Vec::new();
 
-    // FOR SIGHASH_ALL
-    if sighash_type == 01 {
-        // FOR LEGACY TXS
-        if input_type == "NON_SEGWIT" {
-            trimmed_tx.extend(&tx.version.to_le_bytes());
-
-            // INPUTS
-            trimmed_tx.push(tx.vin.len() as u8);
-
-            for input_index in 0..tx.vin.len() {
-                let mut txid_bytes_reversed =
-                    hex::decode(&tx.vin[input_index].txid).expect("DECODING: FAILED");
-                txid_bytes_reversed.reverse();
-
-                trimmed_tx.extend_from_slice(&txid_bytes_reversed);
-                trimmed_tx.extend(&tx.vin[input_index].vout.to_le_bytes());
-
-                // PUSHING THE INPUTS IN THE BYTE SEQUENCE
-                if input_index == tx_input_index {
-                    // PUSHING THE REDEEM SCRIPT IN PLACE OF SCRIPT PUB KEY
-                    let scriptsig_asm = tx.vin[input_index]
-                        .scriptsig_asm
-                        .clone()
-                        .unwrap_or("SCRIPT SIG ASM: MISSING".to_string());
-
-                    let scriptsig_asm_slices: Vec<&str> =
-                        scriptsig_asm.split_whitespace().collect();
-
-                    let redeem_script = scriptsig_asm_slices
-                        .last()
-                        .cloned()
-                        .expect("STACK UNDERFLOW");
-
-                    let redeem_script_bytes = hex::decode(redeem_script)?;
-
-                    trimmed_tx.push(redeem_script_bytes.len().try_into()?);
-                    trimmed_tx.extend_from_slice(&redeem_script_bytes);
-                } else {
-                    trimmed_tx.push(0 as u8);
-                }
+    let base_type = sighash_type as u8 & 0x1f;
+    let anyone_can_pay = sighash_type as u8 & SIGHASH_ANYONECANPAY != 0;
+
+    // FOR LEGACY TXS
+    if input_type == "NON_SEGWIT" {
+        trimmed_tx.extend(&tx.version.to_le_bytes());
+
+        // INPUTS, OR ONLY THE ONE BEING VERIFIED FOR SIGHASH_ANYONECANPAY
+        let input_indexes = match anyone_can_pay {
+            true => tx_input_index..tx_input_index + 1,
+            false => 0..tx.vin.len(),
+        };
+        trimmed_tx.extend(compact_size(input_indexes.len() as u64));
+
+        for input_index in input_indexes {
+            trimmed_tx.extend_from_slice(&tx.vin[input_index].outpoint().consensus_bytes());
+
+            // PUSHING THE INPUTS IN THE BYTE SEQUENCE
+            if input_index == tx_input_index {
+                // PUSHING THE REDEEM SCRIPT IN PLACE OF SCRIPT PUB KEY
+                let redeem_script_bytes = match script_code {
+                    Some(script_code) => script_code.to_vec(),
+                    None => tx.vin[input_index]
+                        .redeem_script()?
+                        .ok_or_else(|| format_err!("REDEEM SCRIPT: MISSING"))?
+                        .as_bytes()
+                        .to_vec(),
+                };
+
+                trimmed_tx.extend(compact_size(redeem_script_bytes.len() as u64));
+                trimmed_tx.extend_from_slice(&redeem_script_bytes);
                 trimmed_tx.extend(&tx.vin[input_index].sequence.to_le_bytes());
+            } else {
+                trimmed_tx.push(0 as u8);
+
+                // WITHOUT THEIR OUTPUTS SIGNED, THE OTHER INPUTS MAY CHANGE THEIR SEQUENCES
+                let sequence = match base_type {
+                    SIGHASH_NONE | SIGHASH_SINGLE => 0,
+                    _ => tx.vin[input_index].sequence,
+                };
+                trimmed_tx.extend(&sequence.to_le_bytes());
             }
-
-            // OUTPUTS
-            trimmed_tx.push(tx.vout.len() as u8);
-
-            // PUSHING THE OUTPUTS IN THE BYTE SEQUENCE
-            for tx_ouput in tx.vout.iter() {
-                let script_pubkey_bytes =
-                    hex::decode(tx_ouput.scriptpubkey.clone()).expect("DECODING FAILED");
-
-                trimmed_tx.extend(tx_ouput.value.to_le_bytes());
-                trimmed_tx.push(script_pubkey_bytes.len().try_into()?);
-                trimmed_tx.extend_from_slice(&script_pubkey_bytes);
-            }
-            trimmed_tx.extend(&tx.locktime.to_le_bytes());
         }
 
-        // FOR SEGWIT_TXS
-        if input_type == "P2SH-P2WPKH" {
-            trimmed_tx.extend(&tx.version.to_le_bytes());
-
-            // PUSHING HASHPREVOUTS AND HASHSEQUENCE
-            let mut prevouts: Vec<u8> = Vec::new();
-            let mut sequence: Vec<u8> = Vec::new();
-            for input_index in 0..tx.vin.len() {
-                let mut txid_bytes_reversed = hex::decode(&tx.vin[input_index].txid)?;
-                txid_bytes_reversed.reverse();
-
-                prevouts.extend_from_slice(&txid_bytes_reversed);
-                prevouts.extend(&tx.vin[input_index].vout.to_le_bytes());
-
-                sequence.extend(&tx.vin[input_index].sequence.to_le_bytes());
+        // OUTPUTS
+        let outputs_count = match base_type {
+            SIGHASH_NONE => 0,
+            SIGHASH_SINGLE if tx_input_index < tx.vout.len() => tx_input_index + 1,
+            SIGHASH_SINGLE => {
+                return Err(format_err!("SIGHASH_SINGLE: NO OUTPUT {}", tx_input_index))
             }
-            let hashprevouts = double_sha256(&prevouts);
-            let hashsequence = double_sha256(&sequence);
-
-            trimmed_tx.extend_from_slice(&hashprevouts);
-            trimmed_tx.extend_from_slice(&hashsequence);
-
-            // OUTPOINTS FOR THE INPUT BEING VERIFIED
-
-            // SUBPARTS :-
-
-            // PUSING THE REVERSED TXID
-            let mut txid_bytes_reversed_sig = hex::decode(&tx.vin[tx_input_index].txid)?;
-            txid_bytes_reversed_sig.reverse();
-
-            trimmed_tx.extend_from_slice(&txid_bytes_reversed_sig);
-            trimmed_tx.extend(tx.vin[tx_input_index].vout.to_le_bytes());
-
-            // EXTRACTING THE REDEEM SCRIPT FROM THE INNER REDEEM SCRIPT ASM
-            let inner_redeemscript_asm = tx.vin[tx_input_index]
-                .inner_redeemscript_asm
-                .clone()
-                .unwrap_or("REDEEM SCRIPT: MISSING".to_string());
-
-            let inner_redeemscript_asm_slices: Vec<&str> =
-                inner_redeemscript_asm.split_whitespace().collect();
-            let redeem_script = inner_redeemscript_asm_slices
-                .last()
-                .cloned()
-                .unwrap_or("REDEEM SCRIPT: MISSING");
-
-            // CREATING AND PUSHING THE SCRIPT CODE
-            let scrip_code = format!("{}{}{}", "1976a914", redeem_script, "88ac");
-            let script_code_bytes = hex::decode(&scrip_code)?;
-
-            trimmed_tx.extend_from_slice(&script_code_bytes);
-
-            // PUSHING THE AMOUNT
-            trimmed_tx.extend(tx.vin[tx_input_index].prevout.value.to_le_bytes());
-
-            // PUSHING THE SEQUENCE
-            trimmed_tx.extend(tx.vin[tx_input_index].sequence.to_le_bytes());
-
-            // PUSHING THE OUTPUTS IN THE SEQUENCE
-
-            let mut outputs: Vec<u8> = Vec::new();
-            for output in tx.vout.iter() {
-                outputs.extend(output.value.to_le_bytes());
-
-                let scriptpubkey_bytes = hex::decode(&output.scriptpubkey)?;
-                outputs.push(scriptpubkey_bytes.len().try_into()?);
-                outputs.extend_from_slice(&scriptpubkey_bytes);
+            _ => tx.vout.len(),
+        };
+        trimmed_tx.extend(compact_size(outputs_count as u64));
+
+        // PUSHING THE OUTPUTS IN THE BYTE SEQUENCE
+        for (output_index, tx_ouput) in tx.vout[..outputs_count].iter().enumerate() {
+            // SIGHASH_SINGLE BLANKS THE OUTPUTS BEFORE ITS OWN: A VALUE OF -1 AND NO SCRIPT
+            if base_type == SIGHASH_SINGLE && output_index != tx_input_index {
+                trimmed_tx.extend(u64::MAX.to_le_bytes());
+                trimmed_tx.push(0);
+                continue;
             }
 
-            let hash_outputs = double_sha256(&outputs);
-
-            trimmed_tx.extend_from_slice(&hash_outputs);
-
-            // PUSHING THE LOCKTIME
-            trimmed_tx.extend(tx.locktime.to_le_bytes());
+            let script_pubkey = tx_ouput.script_pubkey()?;
+            trimmed_tx.extend(tx_ouput.value.to_le_bytes());
+            trimmed_tx.extend(compact_size(script_pubkey.as_bytes().len() as u64));
+            trimmed_tx.extend_from_slice(script_pubkey.as_bytes());
         }
+        trimmed_tx.extend(&tx.locktime.to_le_bytes());
 
-        if input_type == "P2SH-P2WSH" {
-            trimmed_tx.extend(&tx.version.to_le_bytes());
-
-            // PUSHING HASHPREVOUTS AND HASHSEQUENCE
-            let mut prevouts: Vec<u8> = Vec::new();
-            let mut sequence: Vec<u8> = Vec::new();
-            for input_index in 0..tx.vin.len() {
-                let mut txid_bytes_reversed = hex::decode(&tx.vin[input_index].txid)?;
-                txid_bytes_reversed.reverse();
-
-                prevouts.extend_from_slice(&txid_bytes_reversed);
-                prevouts.extend(&tx.vin[input_index].vout.to_le_bytes());
-
-                sequence.extend(&tx.vin[input_index].sequence.to_le_bytes());
-            }
-            let hashprevouts = double_sha256(&prevouts);
-            let hashsequence = double_sha256(&sequence);
-
-            trimmed_tx.extend_from_slice(&hashprevouts);
-            trimmed_tx.extend_from_slice(&hashsequence);
-
-            // OUTPOINTS FOR THE INPUT BEING VERIFIED
-
-            // SUBPARTS :-
-
-            // PUSING THE REVERSED TXID
-            let mut txid_bytes_reversed_sig = hex::decode(&tx.vin[tx_input_index].txid)?;
-            txid_bytes_reversed_sig.reverse();
-
-            trimmed_tx.extend_from_slice(&txid_bytes_reversed_sig);
-            trimmed_tx.extend(tx.vin[tx_input_index].vout.to_le_bytes());
-
-            // SCRIPT CODE
-
-            // EXTRACTING THE REDEEM SCRIPT FROM THE WITNESS
-            let witness_script_hex = tx.vin[tx_input_index]
-                .witness
-                .clone()
-                .expect("WITNESS: MISSING")
-                .last()
-                .cloned()
-                .expect("WITNESS SCRIPT: MISSING");
-
-            let script_code_bytes = hex::decode(&witness_script_hex)?;
-
-            trimmed_tx.push(script_code_bytes.len().try_into()?);
-            trimmed_tx.extend_from_slice(&script_code_bytes);
-
-            // PUSHING THE AMOUNT
-            trimmed_tx.extend(tx.vin[tx_input_index].prevout.value.to_le_bytes());
-
-            // PUSHING THE SEQUENCE
-            trimmed_tx.extend(tx.vin[tx_input_index].sequence.to_le_bytes());
-
-            // PUSHING THE OUTPUTS IN THE SEQUENCE
-
-            let mut outputs: Vec<u8> = Vec::new();
-            for output in tx.vout.iter() {
-                outputs.extend(output.value.to_le_bytes());
-
-                let scriptpubkey_bytes = hex::decode(&output.scriptpubkey)?;
-                outputs.push(scriptpubkey_bytes.len().try_into()?);
-                outputs.extend_from_slice(&scriptpubkey_bytes);
-            }
-
-            let hash_outputs = double_sha256(&outputs);
+        return Ok(trimmed_tx);
+    }
 
-            trimmed_tx.extend_from_slice(&hash_outputs);
+    // FOR SEGWIT_TXS: THE SCRIPT CODE, WITHOUT ITS LENGTH
+    let script_code_bytes = match input_type {
+        "P2SH-P2WPKH" | "P2WPKH" => {
+            // EXTRACTING THE PUBLIC KEY HASH FROM THE P2WPKH REDEEM SCRIPT OR SCRIPT PUB KEY
+            let witness_program = match input_type {
+                "P2SH-P2WPKH" => tx.vin[tx_input_index]
+                    .redeem_script()?
+                    .ok_or_else(|| format_err!("REDEEM SCRIPT: MISSING"))?,
+                _ => tx.vin[tx_input_index].prevout.script_pubkey()?,
+            };
+
+            let pubkey_hash = match witness_program.witness_program() {
+                Some((0, program)) => hex::encode(program),
+                _ => return Err(format_err!("{}: NOT P2WPKH", input_type)),
+            };
 
-            // PUSHING THE LOCKTIME
-            trimmed_tx.extend(tx.locktime.to_le_bytes());
+            // script-code 0x1976a914{20-byte-pubkey-hash}88ac
+            hex::decode(format!("{}{}{}", "76a914", pubkey_hash, "88ac"))?
         }
-
-        if input_type == "P2WPKH" {
-            // println!("P2WPKH");
-            trimmed_tx.extend(&tx.version.to_le_bytes());
-
-            // PUSHING HASHPREVOUTS AND HASHSEQUENCE
-            let mut prevouts: Vec<u8> = Vec::new();
-            let mut sequence: Vec<u8> = Vec::new();
-            for input_index in 0..tx.vin.len() {
-                let mut txid_bytes_reversed = hex::decode(&tx.vin[input_index].txid)?;
-                txid_bytes_reversed.reverse();
-
-                prevouts.extend_from_slice(&txid_bytes_reversed);
-                prevouts.extend(&tx.vin[input_index].vout.to_le_bytes());
-
-                sequence.extend(&tx.vin[input_index].sequence.to_le_bytes());
+        "P2SH-P2WSH" | "P2WSH" => match script_code {
+            Some(script_code) => script_code.to_vec(),
+            // EXTRACTING THE WITNESS SCRIPT FROM THE WITNESS
+            None => {
+                let witness_script_hex = tx.vin[tx_input_index]
+                    .witness_items()
+                    .last()
+                    .cloned()
+                    .ok_or_else(|| format_err!("WITNESS SCRIPT: MISSING"))?;
+                hex::decode(&witness_script_hex)?
             }
-            let hashprevouts = double_sha256(&prevouts);
-            let hashsequence = double_sha256(&sequence);
-
-            trimmed_tx.extend_from_slice(&hashprevouts);
-            trimmed_tx.extend_from_slice(&hashsequence);
-
-            // OUTPOINTS FOR THE INPUT BEING VERIFIED
-
-            // SUBPARTS :-
-
-            // PUSING THE REVERSED TXID
-            let mut txid_bytes_reversed_sig = hex::decode(&tx.vin[tx_input_index].txid)?;
-            txid_bytes_reversed_sig.reverse();
+        },
+        _ => return Err(format_err!("NO ECDSA SIGHASH FOR {}", input_type)),
+    };
 
-            trimmed_tx.extend_from_slice(&txid_bytes_reversed_sig);
-            trimmed_tx.extend(tx.vin[tx_input_index].vout.to_le_bytes());
+    trimmed_tx.extend(&tx.version.to_le_bytes());
 
-            // SCRIPT CODE
+    // PUSHING HASHPREVOUTS AND HASHSEQUENCE, ZEROS FOR WHAT THE SIGHASH TYPE LEAVES OUT
+    let zeros = [0u8; 32];
+    match anyone_can_pay {
+        true => trimmed_tx.extend_from_slice(&zeros),
+        false => trimmed_tx.extend_from_slice(&cache.hash_prevouts),
+    }
+    match anyone_can_pay || matches!(base_type, SIGHASH_NONE | SIGHASH_SINGLE) {
+        true => trimmed_tx.extend_from_slice(&zeros),
+        false => trimmed_tx.extend_from_slice(&cache.hash_sequence),
+    }
 
-            // EXTRACTING THE PUBLIC KEY HASH FROM THE SCRIPT PUB KEY ASM
-            let scriptpubkey_asm = tx.vin[tx_input_index].prevout.scriptpubkey_asm.clone();
+    // PUSHING THE OUTPOINT, TXID IN INTERNAL ORDER
+    trimmed_tx.extend_from_slice(&tx.vin[tx_input_index].outpoint().consensus_bytes());
+
+    // PUSHING THE SCRIPT CODE
+    trimmed_tx.extend(compact_size(script_code_bytes.len() as u64));
+    trimmed_tx.extend_from_slice(&script_code_bytes);
+
+    // PUSHING THE AMOUNT
+    trimmed_tx.extend(tx.vin[tx_input_index].prevout.value.to_le_bytes());
+
+    // PUSHING THE SEQUENCE
+    trimmed_tx.extend(tx.vin[tx_input_index].sequence.to_le_bytes());
+
+    // PUSHING HASHOUTPUTS: EVERY OUTPUT, THE ONE AT THE INPUT'S INDEX FOR SIGHASH_SINGLE, OR ZEROS
+    match base_type {
+        SIGHASH_NONE => trimmed_tx.extend_from_slice(&zeros),
+        SIGHASH_SINGLE => match tx.vout.get(tx_input_index) {
+            Some(tx_ouput) => {
+                let script_pubkey = tx_ouput.script_pubkey()?;
+                let mut output = tx_ouput.value.to_le_bytes().to_vec();
+                output.extend(compact_size(script_pubkey.as_bytes().len() as u64));
+                output.extend_from_slice(script_pubkey.as_bytes());
+                trimmed_tx.extend(double_sha256(&output));
+            }
+            None => trimmed_tx.extend_from_slice(&zeros),
+        },
+        _ => trimmed_tx.extend_from_slice(&cache.hash_outputs),
+    }
 
-            let scriptpubkey_slices: Vec<&str> = scriptpubkey_asm.split_whitespace().collect();
+    // PUSHING THE LOCKTIME
+    trimmed_tx.extend(tx.locktime.to_le_bytes());
 
-            // script-code 0x1976a914{20-byte-pubkey-hash}88ac
+    Ok(trimmed_tx)
+}
 
-            let pubkey_hash = scriptpubkey_slices
-                .last()
-                .cloned()
-                .unwrap_or("SCRIPT PUB KEY: MISSING");
-            let script_code_hex = format!("{}{}{}", "1976a914", pubkey_hash, "88ac");
+// A SCRIPT ERROR IS PASSED ON SO THE REPORT CAN NAME THE BROKEN RULE, ANY OTHER ERROR FAILS THE TX
+fn script_error_or_false(error: failure::Error) -> Result<bool> {
+    match error.downcast::<ScriptError>() {
+        Ok(script_error) => Err(script_error.into()),
+        Err(_) => Ok(false),
+    }
+}
 
-            let script_code_bytes = hex::decode(&script_code_hex)?;
+// FINAL VERIFICATION FUNCTION: THE SCRIPTS OF EVERY INPUT, THEN THEIR TAPROOT SIGNATURES
+// `flags`: VerifyFlags::CONSENSUS TO VALIDATE A BLOCK, VerifyFlags::STANDARD FOR THE MEMPOOL
+// `mode`: WHETHER THE TAPROOT SIGNATURES ARE CHECKED ONE BY ONE OR AS A BATCH
+pub fn verify_tx(tx: Transaction, flags: VerifyFlags, mode: SchnorrMode) -> Result<bool> {
+    let mut checks = Vec::new();
+    if !verify_tx_scripts(&tx, flags, &mut checks)? {
+        return Ok(false);
+    }
+    Ok(verify_schnorr_checks(&checks, mode).is_none())
+}
 
-            trimmed_tx.extend_from_slice(&script_code_bytes);
+// VERIFIES THE SCRIPT OF EVERY INPUT
+// A SIGNATURE OR KEY BREAKING AN ENCODING RULE COMES BACK AS A ScriptError
+// THE TAPROOT SIGNATURES ARE ADDED TO `checks` INSTEAD OF BEING VERIFIED
+pub fn verify_tx_scripts(
+    tx: &Transaction,
+    flags: VerifyFlags,
+    checks: &mut Vec<SchnorrCheck>,
+) -> Result<bool> {
+    if tx.vin.is_empty() {
+        return Ok(false);
+    }
 
-            // PUSHING THE AMOUNT
-            trimmed_tx.extend(tx.vin[tx_input_index].prevout.value.to_le_bytes());
+    // AMOUNTS IN RANGE AND A FEE THAT IS NOT NEGATIVE
+    if check_amounts(tx).is_err() {
+        return Ok(false);
+    }
 
-            // PUSHING THE SEQUENCE
-            trimmed_tx.extend(tx.vin[tx_input_index].sequence.to_le_bytes());
+    // THE SIGHASH PARTS SHARED BY EVERY INPUT ARE HASHED ONCE
+    let cache = match SighashCache::new(tx) {
+        Ok(cache) => cache,
+        Err(_) => return Ok(false),
+    };
 
-            // PUSHING THE OUTPUTS IN THE SEQUENCE
+    // THE SIGNATURES ARE ONLY HANDED OVER ONCE THE SCRIPTS OF ALL THE INPUTS HAVE PASSED
+    let mut tx_checks = Vec::new();
+    for input_index in 0..tx.vin.len() {
+        match verify_input(tx, &cache, input_index, flags, &mut tx_checks) {
+            Ok(false) => return Ok(false),
+            Ok(true) => {}
+            Err(error) => return script_error_or_false(error),
+        }
+    }
+    checks.extend(tx_checks);
 
-            let mut outputs: Vec<u8> = Vec::new();
-            for output in tx.vout.iter() {
-                outputs.extend(output.value.to_le_bytes());
+    Ok(true)
+}
 
-                let scriptpubkey_bytes = hex::decode(&output.scriptpubkey)?;
-                outputs.push(scriptpubkey_bytes.len().try_into()?);
-                outputs.extend_from_slice(&scriptpubkey_bytes);
-            }
+// DIRECTS AN INPUT AS PER THE TYPE OF THE OUTPUT IT SPENDS, SO A TX CAN MIX INPUT TYPES
+// THE PREVOUT IS CLASSIFIED FROM ITS HEX, NOT FROM THE SUPPLIED scriptpubkey_type
+fn verify_input(
+    tx: &Transaction,
+    cache: &SighashCache,
+    input_index: usize,
+    flags: VerifyFlags,
+    checks: &mut Vec<SchnorrCheck>,
+) -> Result<bool> {
+    let script_pubkey = tx.vin[input_index].prevout.script_pubkey()?;
+    let script_sig = tx.vin[input_index].script_sig()?;
+    let script_type = script_pubkey.script_type();
+
+    // A WITNESS ON AN INPUT THAT ISN'T SEGWIT IS INVALID UNDER BIP141
+    // P2SH CHECKS IT ITSELF ONCE IT KNOWS WHETHER THE REDEEM SCRIPT IS A WITNESS PROGRAM
+    if flags.contains(VerifyFlags::WITNESS)
+        && script_type != ScriptType::P2sh
+        && script_pubkey.witness_program().is_none()
+        && !tx.vin[input_index].witness_items().is_empty()
+    {
+        return Ok(false);
+    }
 
-            let hash_outputs = double_sha256(&outputs);
+    match script_type {
+        ScriptType::P2pkh => input_verification_p2pkh(tx, cache, input_index, flags),
+        ScriptType::P2sh => input_verification_p2sh(input_index, tx, cache, flags),
+        ScriptType::P2wpkh => input_verification_p2wpkh(input_index, tx, cache, flags),
+        ScriptType::P2wsh => input_verification_p2wsh(input_index, tx, cache, flags),
+        ScriptType::P2tr => input_verification_p2tr(input_index, tx, cache, flags, checks),
+        _ => match script_pubkey.witness_program() {
+            // A V0 PROGRAM OF ANY OTHER LENGTH IS INVALID, A LATER VERSION IS ANYONE CAN SPEND,
+            // KEPT FOR SOFT FORKS BY THE POLICY. EITHER WAY THE SCRIPT SIG HAS TO BE EMPTY
+            Some((version, _)) if flags.contains(VerifyFlags::WITNESS) => Ok(version != 0
+                && script_sig.is_empty()
+                && !flags.contains(VerifyFlags::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM)),
+            // P2PK, BARE MULTISIG AND ANY OTHER SCRIPT RUN AS A PLAIN SCRIPT SIG + SCRIPT PUB KEY
+            _ => encoding_error_or_ok(verify_legacy_script(
+                &script_sig,
+                &script_pubkey,
+                tx,
+                cache,
+                input_index,
+                flags,
+            )),
+        },
+    }
+}
 
-            trimmed_tx.extend_from_slice(&hash_outputs);
+// WHY A TX WAS KEPT OUT OF THE VALID-MEMPOOL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    // AN EARLIER MEMPOOL TX ALREADY SPENDS THIS OUTPUT
+    DoubleSpend(OutPoint),
+    // AN ASM, TYPE OR ADDRESS FIELD OF THE JSON DISAGREES WITH ITS HEX
+    FieldMismatch(FieldMismatch),
+    // AN AMOUNT OR SUM OF AMOUNTS IS OUT OF RANGE, OR IT PAYS OUT MORE THAN IT SPENDS
+    Amounts(AmountError),
+    // THE LOCKTIME HAS NOT PASSED AT THE HEIGHT AND MTP OF THE BLOCK
+    NonFinal(u32),
+    // A RELATIVE LOCK (BIP68) OF ONE OF ITS INPUTS HAS NOT PASSED
+    SequenceLocked(SequenceLock),
+    // IT BREAKS A RULE OF THE MEMPOOL POLICY, A BLOCK COULD STILL INCLUDE IT
+    NonStandard(PolicyViolation),
+    // ITS FEE RATE IS BELOW THE MIN RELAY FEE, OR THE MEMPOOL MIN FEE ONCE THE MEMPOOL IS FULL
+    MinRelayFee(FeeRate),
+    MempoolMinFee(FeeRate),
+    // IT WAS ACCEPTED, THEN EVICTED TO MAKE ROOM FOR HIGHER FEE RATES
+    MempoolFull,
+    // A SCRIPT OF ONE OF ITS INPUTS BROKE THIS RULE
+    Script(ScriptError),
+    // FAILED THE SCRIPT CHECKS OF verify_tx
+    Invalid,
+}
 
-            // PUSHING THE LOCKTIME
-            trimmed_tx.extend(tx.locktime.to_le_bytes());
+impl RejectReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RejectReason::DoubleSpend(_) => "DOUBLE SPEND",
+            RejectReason::FieldMismatch(_) => "FIELD MISMATCH",
+            RejectReason::Amounts(error) => error.name(),
+            RejectReason::NonFinal(_) => "NON FINAL",
+            RejectReason::SequenceLocked(_) => "SEQUENCE LOCKED",
+            RejectReason::NonStandard(violation) => violation.name(),
+            RejectReason::MinRelayFee(_) => "MIN RELAY FEE",
+            RejectReason::MempoolMinFee(_) => "MEMPOOL MIN FEE",
+            RejectReason::MempoolFull => "MEMPOOL FULL",
+            RejectReason::Script(error) => error.name(),
+            RejectReason::Invalid => "INVALID",
         }
+    }
+}
 
-        if input_type == "P2WSH" {
-            // println!("P2WSH");
-            trimmed_tx.extend(&tx.version.to_le_bytes());
-
-            // PUSHING HASHPREVOUTS AND HASHSEQUENCE
-            let mut prevouts: Vec<u8> = Vec::new();
-            let mut sequence: Vec<u8> = Vec::new();
-            for input_index in 0..tx.vin.len() {
-                let mut txid_bytes_reversed = hex::decode(&tx.vin[input_index].txid)?;
-                txid_bytes_reversed.reverse();
-
-                prevouts.extend_from_slice(&txid_bytes_reversed);
-                prevouts.extend(&tx.vin[input_index].vout.to_le_bytes());
-
-                sequence.extend(&tx.vin[input_index].sequence.to_le_bytes());
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RejectReason::DoubleSpend(outpoint) => write!(f, "DOUBLE SPENDS {}", outpoint),
+            RejectReason::FieldMismatch(mismatch) => write!(f, "FIELD MISMATCH: {}", mismatch),
+            RejectReason::Amounts(error) => write!(f, "BAD AMOUNTS: {}", error),
+            RejectReason::NonFinal(locktime) => write!(f, "NOT FINAL UNTIL LOCKTIME {}", locktime),
+            RejectReason::SequenceLocked(lock) => write!(
+                f,
+                "SEQUENCE LOCKED UP TO HEIGHT {} AND MEDIAN TIME {}",
+                lock.min_height, lock.min_time
+            ),
+            RejectReason::NonStandard(violation) => write!(f, "NON-STANDARD: {}", violation),
+            RejectReason::MinRelayFee(fee_rate) => {
+                write!(f, "PAYS {}, BELOW THE MIN RELAY FEE", fee_rate)
             }
-            let hashprevouts = double_sha256(&prevouts);
-            let hashsequence = double_sha256(&sequence);
-
-            trimmed_tx.extend_from_slice(&hashprevouts);
-            trimmed_tx.extend_from_slice(&hashsequence);
-
-            // OUTPOINTS FOR THE INPUT BEING VERIFIED
-
-            // SUBPARTS :-
-
-            // PUSING THE REVERSED TXID
-            let mut txid_bytes_reversed_sig = hex::decode(&tx.vin[tx_input_index].txid)?;
-            txid_bytes_reversed_sig.reverse();
-
-            trimmed_tx.extend_from_slice(&txid_bytes_reversed_sig);
-            trimmed_tx.extend(tx.vin[tx_input_index].vout.to_le_bytes());
-
-            // SCRIPT CODE
-
-            // EXTRACTING THE WITNESS SCRIPT FROM THE WITNESS
-
-            let witness = tx.vin[tx_input_index].witness.clone().unwrap();
-
-            let witness_script = witness.last().cloned().unwrap();
-
-            let script_code_bytes = hex::decode(&witness_script)?;
-
-            trimmed_tx.push(script_code_bytes.len().try_into()?);
-            trimmed_tx.extend_from_slice(&script_code_bytes);
-
-            // PUSHING THE AMOUNT
-            trimmed_tx.extend(tx.vin[tx_input_index].prevout.value.to_le_bytes());
-
-            // PUSHING THE SEQUENCE
-            trimmed_tx.extend(tx.vin[tx_input_index].sequence.to_le_bytes());
-
-            // PUSHING THE OUTPUTS IN THE SEQUENCE
-
-            let mut outputs: Vec<u8> = Vec::new();
-            for output in tx.vout.iter() {
-                outputs.extend(output.value.to_le_bytes());
-
-                let scriptpubkey_bytes = hex::decode(&output.scriptpubkey)?;
-                outputs.push(scriptpubkey_bytes.len().try_into()?);
-                outputs.extend_from_slice(&scriptpubkey_bytes);
+            RejectReason::MempoolMinFee(fee_rate) => {
+                write!(f, "PAYS {}, BELOW THE MEMPOOL MIN FEE", fee_rate)
             }
-
-            let hash_outputs = double_sha256(&outputs);
-
-            trimmed_tx.extend_from_slice(&hash_outputs);
-
-            // PUSHING THE LOCKTIME
-            trimmed_tx.extend(tx.locktime.to_le_bytes());
+            RejectReason::MempoolFull => write!(f, "EVICTED FROM THE FULL MEMPOOL"),
+            RejectReason::Script(error) => write!(f, "SCRIPT ERROR: {}", error),
+            RejectReason::Invalid => write!(f, "FAILED VERIFICATION"),
         }
     }
-    Ok(trimmed_tx)
 }
 
-// FINAL VERIFICATION FUNCTION WHICH DIRECTS TXS AS PER THEIR SCRIPT TYPE
-pub fn verify_tx(tx: Transaction) -> Result<bool> {
-    let _p2pkh = "p2pkh".to_string();
-    let _p2sh = "p2sh".to_string();
-    let _p2wpkh = "v0_p2wpkh".to_string();
-    let _p2wsh = "v0_p2wsh".to_string();
-    let _p2tr = "v1_p2tr".to_string();
+// EVERY CHECK ON A SINGLE TX FOR THE GIVEN BLOCK, NONE IF IT CAN GO IN THE VALID-MEMPOOL
+// `utxos` SAYS WHEN THE SPENT COINS WERE MINED, FOR THE RELATIVE LOCKS
+// `flags` ARE THE SCRIPT RULES IT IS HELD TO, AND `policy` THE MEMPOOL POLICY IF THERE IS ONE
+// `schnorr` SAYS IF ITS TAPROOT SIGNATURES ARE CHECKED HERE OR LEFT FOR THE CALLER
+pub fn reject_reason(
+    tx: &Transaction,
+    context: &BlockContext,
+    utxos: &impl UtxoSource,
+    flags: VerifyFlags,
+    policy: Option<&Policy>,
+    schnorr: SchnorrChecks,
+) -> Result<Option<RejectReason>> {
+    if let Err(mismatch) = check_consistency(tx) {
+        return Ok(Some(RejectReason::FieldMismatch(mismatch)));
+    }
 
-    let tx_type = tx.vin[0].prevout.scriptpubkey_type.clone();
-    let mut v_result = false;
+    let fee = match check_amounts(tx) {
+        Ok(fee) => fee,
+        Err(error) => return Ok(Some(RejectReason::Amounts(error))),
+    };
 
-    // GAS FEES CHECK
-    if gas_fees_check(&tx) != true {
-        return Ok(false);
+    if !is_final_tx(tx, context) {
+        return Ok(Some(RejectReason::NonFinal(tx.locktime)));
     }
 
-    for input_index in 0..tx.vin.len() {
-        if tx.vin[input_index].prevout.scriptpubkey_type != tx_type {
-            return Ok(false);
-        }
+    let lock = sequence_locks(tx, utxos, context);
+    if !lock.is_satisfied(context) {
+        return Ok(Some(RejectReason::SequenceLocked(lock)));
     }
 
-    if tx_type == _p2pkh {
-        for input_index in 0..tx.vin.len() {
-            match input_verification_p2pkh(tx.clone(), input_index) {
-                Ok(false) => {
-                    return Ok(false);
-                }
-                Ok(true) => {
-                    v_result = true;
-                }
-                Err(_) => {
-                    return Ok(false);
-                }
-            }
-        }
+    if let Some(Err(violation)) = policy.map(|policy| policy.check_tx(tx)) {
+        return Ok(Some(RejectReason::NonStandard(violation)));
     }
-    // if tx_type == _p2sh {
-    //     if tx.vin[0].witness != None {
-    //         if tx.vin[0].witness.clone().unwrap().len() > 2 {
-    //             return Ok(false);
-    //         }
-    //     }
-
-    //     for input_index in 0..tx.vin.len() {
-    //         match input_verification_p2sh(input_index, tx.clone()) {
-    //             Ok(false) => {
-    //                 return Ok(false);
-    //             }
-
-    //             Ok(true) => {
-    //                 v_result = true;
-    //             }
-
-    //             Err(_) => {
-    //                 return Ok(false);
-    //             }
-    //         }
-    //     }
-    // }
-    if tx_type == _p2wpkh {
-        for input_index in 0..tx.vin.len() {
-            match input_verification_p2wpkh(input_index, tx.clone()) {
-                Ok(false) => {
-                    return Ok(false);
-                }
 
-                Ok(true) => {
-                    v_result = true;
-                }
+    let verified = match schnorr {
+        SchnorrChecks::Verify(mode) => verify_tx(tx.clone(), flags, mode),
+        SchnorrChecks::Collect(checks) => verify_tx_scripts(tx, flags, checks),
+    };
+    match verified {
+        Ok(true) => {}
+        Ok(false) => return Ok(Some(RejectReason::Invalid)),
+        Err(error) => {
+            let script_error = error.downcast::<ScriptError>()?;
+            return Ok(Some(RejectReason::Script(script_error)));
+        }
+    }
 
-                Err(_) => {
-                    return Ok(false);
-                }
-            }
+    // CHECKED LAST, SO A TX LEFT OUT ONLY FOR ITS FEE CAN STILL GO IN AS PART OF A PACKAGE
+    if let Some(policy) = policy {
+        let fee_rate = FeeRate::from_fee(fee, tx.vsize()?);
+        if fee_rate < policy.min_relay_fee {
+            return Ok(Some(RejectReason::MinRelayFee(fee_rate)));
         }
     }
-    if tx_type == _p2wsh {
-        for input_index in 0..tx.vin.len() {
-            match input_verification_p2wsh(input_index, tx.clone()) {
-                Ok(false) => {
-                    return Ok(false);
-                }
 
-                Ok(true) => {
-                    v_result = true;
-                }
+    Ok(None)
+}
 
-                Err(_) => {
-                    return Ok(false);
-                }
-            }
+// THE VALID-MEMPOOL DIRECTORY AND THE FEES OF THE TXS IN IT
+struct ValidMempool<'a> {
+    dir: &'a Path,
+    fees: MempoolFees,
+    files: HashMap<Txid, PathBuf>,
+    evicted: usize,
+}
+
+impl<'a> ValidMempool<'a> {
+    fn new(dir: &'a Path, policy: &Policy) -> Self {
+        ValidMempool {
+            dir,
+            fees: MempoolFees::new(policy),
+            files: HashMap::new(),
+            evicted: 0,
         }
     }
-    if tx_type == _p2tr {
-        // CHECK IF THE WITNESS ITEMS LENGTH IS <255
 
-        for input in tx.vin.iter() {
-            let witness = input.witness.clone().unwrap();
-            for item in witness {
-                let item_bytes = hex::decode(&item)?;
-                if item_bytes.len() >= 255 {
-                    return Ok(false);
-                }
+    // COPIES A TX IN, THEN REMOVES WHAT IT EVICTED
+    fn accept(&mut self, txid: Txid, path: &Path, tx: &Transaction) -> Result<()> {
+        let file_name = match path.file_name() {
+            Some(file_name) => file_name,
+            None => return Ok(()),
+        };
+        let destination_path = self.dir.join(file_name);
+        fs::copy(path, &destination_path)?;
+        self.files.insert(txid, destination_path);
+
+        for evicted in self.fees.add(txid, tx, check_amounts(tx)?, tx.vsize()?) {
+            if let Some(evicted_path) = self.files.remove(&evicted) {
+                fs::remove_file(evicted_path)?;
+                self.evicted += 1;
             }
         }
-
-        v_result = true;
+        Ok(())
     }
-
-    Ok(v_result)
 }
 
-// REJECTS TXS IF GAS FEES IS LESS THAN 1500
-fn gas_fees_check(tx: &Transaction) -> bool {
-    let mut s_sats: u64 = 0;
-    let mut r_sats: u64 = 0;
+// A TX THAT PASSED EVERY CHECK BUT THE FEE
+struct BelowMinFee {
+    txid: Txid,
+    path: PathBuf,
+    tx: Transaction,
+    reason: RejectReason,
+}
 
-    for input_index in 0..tx.vin.len() {
-        if tx.vin[input_index].prevout.value <= 0 {
-            return false;
+// 1P1C PACKAGES: A TX BELOW THE MIN FEE GOES IN WITH THE CHILD THAT PAYS THE MOST FOR BOTH,
+// IF THEIR FEES TOGETHER PAY THE MEMPOOL MIN FEE FOR THEIR VSIZES TOGETHER
+// `candidates` ARE THE TXS THAT PASSED EVERYTHING BUT MAYBE THE FEE, WITH THEIR FEES AND VSIZES
+// RETURNS THE TXS STILL LEFT OUT
+fn accept_packages(
+    below_min_fee: Vec<BelowMinFee>,
+    candidates: &HashMap<Txid, (Transaction, Amount, usize)>,
+    valid_mempool: &mut ValidMempool,
+) -> Result<Vec<RejectReason>> {
+    let paths: HashMap<Txid, &Path> = below_min_fee
+        .iter()
+        .map(|entry| (entry.txid, entry.path.as_path()))
+        .collect();
+
+    for parent in below_min_fee.iter() {
+        if valid_mempool.fees.contains(&parent.txid) {
+            continue;
         }
-        s_sats += tx.vin[input_index].prevout.value;
-    }
-
-    for output_index in 0..tx.vout.len() {
-        if tx.vout[output_index].value <= 0 {
-            return false;
+        let (_, parent_fee, parent_vsize) = &candidates[&parent.txid];
+
+        // AN EVICTED CHILD CAN'T PAY FOR ITS PARENT
+        let best_child = candidates
+            .iter()
+            .filter(|(txid, _)| valid_mempool.fees.contains(txid) || paths.contains_key(txid))
+            .filter(|(_, (tx, _, _))| tx.vin.iter().any(|input| input.txid == parent.txid))
+            .filter_map(|(txid, (tx, fee, vsize))| {
+                let package_fee = parent_fee.checked_add(*fee)?;
+                let package_rate = FeeRate::from_fee(package_fee, parent_vsize + vsize);
+                Some((package_rate, *txid, tx))
+            })
+            .max_by_key(|(package_rate, txid, _)| (*package_rate, *txid));
+
+        if let Some((package_rate, child_txid, child)) = best_child {
+            if package_rate < valid_mempool.fees.min_fee() {
+                continue;
+            }
+            valid_mempool.accept(parent.txid, &parent.path, &parent.tx)?;
+            if let Some(child_path) = paths.get(&child_txid) {
+                if !valid_mempool.fees.contains(&child_txid) {
+                    valid_mempool.accept(child_txid, child_path, child)?;
+                }
+            }
         }
-        r_sats += tx.vout[output_index].value;
     }
 
-    if s_sats - r_sats < 1500 {
-        return false;
-    } else {
-        return true;
-    }
+    Ok(below_min_fee
+        .into_iter()
+        .filter(|entry| !valid_mempool.fees.contains(&entry.txid))
+        .map(|entry| entry.reason)
+        .collect())
 }
 
 // ITERATES THROUGH THE WHOLE MEMPOOL AND PUTS THE VALID TRANSACTIONS IN THE VALID-MEMPOOL FOLDER
-pub fn all_transaction_verification() -> Result<()> {
+// `context` IS THE NEXT BLOCK, SO TXS THAT CAN'T BE MINED IN IT YET ARE LEFT OUT
+// `policy` IS THE MEMPOOL POLICY, ON TOP OF VerifyFlags::STANDARD
+pub fn all_transaction_verification(
+    context: &BlockContext,
+    utxos: &impl UtxoSource,
+    policy: &Policy,
+) -> Result<()> {
     let mempool_dir = "./mempool";
 
     // THIS HASH-MAP WILL BE USED TO REJECT DOUBLE SPENDS
-    let mut spends: HashMap<String, String> = HashMap::new();
+    let mut spends: HashMap<OutPoint, String> = HashMap::new();
+
+    // NUMBER OF REJECTED TXS FOR EACH REASON
+    let mut rejected: BTreeMap<&'static str, usize> = BTreeMap::new();
+
+    // TXS LEFT OUT ONLY FOR POLICY: A BLOCK COULD STILL INCLUDE THEM
+    let mut non_standard = 0;
+
+    let mut valid_mempool = ValidMempool::new(Path::new("./valid-mempool"), policy);
+
+    // TXS THAT PASSED EVERY CHECK BUT MAYBE THE FEE, AND THOSE THAT ONLY MISSED THE FEE
+    let mut candidates: HashMap<Txid, (Transaction, Amount, usize)> = HashMap::new();
+    let mut below_min_fee: Vec<BelowMinFee> = Vec::new();
 
     // ITERATE THROUGH THE DIRECTORY
     'outer: for entry in WalkDir::new(mempool_dir).into_iter().filter_map(|e| e.ok()) {
@@ -684,13 +728,15 @@ pub fn all_transaction_verification() -> Result<()> {
                 Ok(contents) => match serde_json::from_str::<Transaction>(&contents) {
                     Ok(transaction) => {
                         for input in &transaction.vin {
-                            let input_key = format!("{}{}", input.txid, input.vout);
+                            let input_key = input.outpoint();
 
                             // CHECKS IF THE TXID IS ALREADY INN THE HASH-MAP
                             match spends.get(&input_key) {
                                 Some(existing_txid)
                                     if path.display().to_string() != *existing_txid =>
                                 {
+                                    let reason = RejectReason::DoubleSpend(input_key);
+                                    *rejected.entry(reason.name()).or_default() += 1;
                                     continue 'outer;
                                 }
                                 _ => {
@@ -699,12 +745,63 @@ pub fn all_transaction_verification() -> Result<()> {
                             }
                         }
                         // VERIFIES THE TX
-                        let result = verify_tx(transaction)?;
-                        if result == true {
-                            if let Some(filename) = path.file_name() {
-                                let valid_mempool_dir = Path::new("./valid-mempool");
-                                let destination_path = valid_mempool_dir.join(filename);
-                                fs::copy(&path, &destination_path)?;
+                        let standard = VerifyFlags::STANDARD;
+                        let schnorr = SchnorrChecks::Verify(policy.schnorr_mode);
+                        let mut reason = reject_reason(
+                            &transaction,
+                            context,
+                            utxos,
+                            standard,
+                            Some(policy),
+                            schnorr,
+                        )?;
+
+                        let fee_only = matches!(reason, None | Some(RejectReason::MinRelayFee(_)));
+                        if fee_only {
+                            let txid = transaction.txid()?;
+                            let fee = check_amounts(&transaction)?;
+                            let vsize = transaction.vsize()?;
+                            candidates.insert(txid, (transaction.clone(), fee, vsize));
+
+                            let fee_rate = FeeRate::from_fee(fee, vsize);
+                            if reason.is_none() && fee_rate < valid_mempool.fees.min_fee() {
+                                reason = Some(RejectReason::MempoolMinFee(fee_rate));
+                            }
+
+                            match reason {
+                                None => valid_mempool.accept(txid, path, &transaction)?,
+                                Some(reason) if policy.package_feerate => {
+                                    below_min_fee.push(BelowMinFee {
+                                        txid,
+                                        path: path.to_path_buf(),
+                                        tx: transaction,
+                                        reason,
+                                    });
+                                }
+                                Some(reason) => {
+                                    *rejected.entry(reason.name()).or_default() += 1;
+                                    non_standard += 1;
+                                }
+                            }
+                            continue 'outer;
+                        }
+
+                        if let Some(reason) = reason {
+                            *rejected.entry(reason.name()).or_default() += 1;
+
+                            let consensus = VerifyFlags::CONSENSUS;
+                            let schnorr = SchnorrChecks::Verify(policy.schnorr_mode);
+                            if reject_reason(
+                                &transaction,
+                                context,
+                                utxos,
+                                consensus,
+                                None,
+                                schnorr,
+                            )?
+                            .is_none()
+                            {
+                                non_standard += 1;
                             }
                         }
                     }
@@ -714,22 +811,332 @@ pub fn all_transaction_verification() -> Result<()> {
             }
         }
     }
+
+    // THE FEE IS THE ONLY POLICY RULE THESE BROKE
+    for reason in accept_packages(below_min_fee, &candidates, &mut valid_mempool)? {
+        *rejected.entry(reason.name()).or_default() += 1;
+        non_standard += 1;
+    }
+    if valid_mempool.evicted > 0 {
+        *rejected
+            .entry(RejectReason::MempoolFull.name())
+            .or_default() += valid_mempool.evicted;
+        non_standard += valid_mempool.evicted;
+    }
+
+    for (reason, count) in rejected.iter() {
+        println!("REJECTED ({}): {}", reason, count);
+    }
+    println!("CONSENSUS VALID BUT NON-STANDARD: {}", non_standard);
+    println!("SIGNATURE CACHE: {}", signature_cache().stats);
     Ok(())
 }
 
 // TO TEST MY CODE DURING DEVELOPMENT
 #[cfg(test)]
 mod test {
-    use std::{collections::HashMap, fs, path::Path};
+    use std::{collections::HashMap, fs};
 
     use walkdir::WalkDir;
 
     use super::*;
 
+    #[test]
+    fn package_feerate_test() -> Result<()> {
+        let dir = std::env::temp_dir().join("package-feerate-test");
+        fs::create_dir_all(&dir)?;
+        let mut valid_mempool = ValidMempool::new(&dir, &Policy::default());
+
+        let paths = [
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json",
+            "./mempool/0bec1aee6decd078b98553691be92f99ad12271241c6b6f7cf00433954d3f166.json",
+            "./mempool/001035505afbf143e51bd667099190943a38eee20092bb691e72eaa44992b2f7.json",
+        ]
+        .map(PathBuf::from);
+        let [parent_txid, child_txid, lone_txid] =
+            [1, 2, 3].map(|byte| Txid::from_internal_bytes([byte; 32]));
+
+        let parent: Transaction = serde_json::from_str(&fs::read_to_string(&paths[0])?)?;
+        let mut child: Transaction = serde_json::from_str(&fs::read_to_string(&paths[1])?)?;
+        child.vin[0].txid = parent_txid;
+
+        // 0.1 SAT/VB PARENTS, ONE WITH A 5 SAT/VB CHILD
+        let [low, high] = [100, 5000].map(Amount::from_sat);
+        let candidates: HashMap<Txid, (Transaction, Amount, usize)> = HashMap::from([
+            (parent_txid, (parent.clone(), low, 1000)),
+            (child_txid, (child.clone(), high, 1000)),
+            (lone_txid, (parent.clone(), low, 1000)),
+        ]);
+        let below_min_fee = vec![
+            BelowMinFee {
+                txid: parent_txid,
+                path: paths[0].clone(),
+                tx: parent.clone(),
+                reason: RejectReason::MinRelayFee(FeeRate::from_sat_per_kvb(100)),
+            },
+            BelowMinFee {
+                txid: lone_txid,
+                path: paths[2].clone(),
+                tx: parent,
+                reason: RejectReason::MinRelayFee(FeeRate::from_sat_per_kvb(100)),
+            },
+        ];
+        valid_mempool.accept(child_txid, &paths[1], &child)?;
+
+        // THE CHILD PAYS 2.55 SAT/VB FOR BOTH, THE LONE PARENT STAYS OUT
+        let left_out = accept_packages(below_min_fee, &candidates, &mut valid_mempool)?;
+        assert_eq!(
+            left_out,
+            vec![RejectReason::MinRelayFee(FeeRate::from_sat_per_kvb(100))]
+        );
+        assert!(valid_mempool.fees.contains(&parent_txid));
+        assert!(!valid_mempool.fees.contains(&lone_txid));
+        assert!(dir.join(paths[0].file_name().unwrap()).exists());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    fn read_mempool_tx(txid: &str) -> Result<Transaction> {
+        let path = format!("./mempool/{}.json", txid);
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    #[test]
+    fn sighash_types_test() -> Result<()> {
+        // (TX, INPUT, SIGHASH TYPE OF ITS SIGNATURE): ALL, NONE AND SINGLE WITH ANYONECANPAY
+        let signed = [
+            (
+                "b8af9b69c6ccbf6ac78cf2ce6a05da317971d4bf98afb7046b09186c7185089c",
+                0,
+                0x81,
+            ),
+            (
+                "bd9e30c74c020fec96b3506e9dc9bcbbb4f2b2ad1e323cfc3c60b9da870220ff",
+                0,
+                0x81,
+            ),
+            (
+                "9dce3b0796a8598a90c80b24c90ced42032b71a8b53641fcdf0729536aa371c6",
+                2,
+                0x83,
+            ),
+            (
+                "1f2ffc302fe5a514c150fca81e4912e7076033b3397355dc06663efe6a93fcc1",
+                6,
+                0x83,
+            ),
+            (
+                "1d792af99801cce57eba95f12d4cff0cc6dcafbb85974931677bbfffa871468f",
+                0,
+                0x82,
+            ),
+        ];
+        // A BROKEN SIGNATURE IS A NULLFAIL ERROR UNDER STANDARD, SO THE CHANGED TXS USE CONSENSUS
+        let verify_with = |tx: &Transaction, input_index: usize, flags| -> Result<bool> {
+            let cache = SighashCache::new(tx)?;
+            verify_input(tx, &cache, input_index, flags, &mut Vec::new())
+        };
+
+        let verify =
+            |tx: &Transaction, input_index| verify_with(tx, input_index, VerifyFlags::CONSENSUS);
+
+        for (txid, input_index, sighash_type) in signed {
+            let tx = read_mempool_tx(txid)?;
+            let input = &tx.vin[input_index];
+            let signature_suffix = format!("{:02x}", sighash_type);
+            let signatures: Vec<&str> = input
+                .scriptsig_asm
+                .iter()
+                .flat_map(|asm| asm.split_whitespace())
+                .chain(input.witness_items().iter().map(|item| item.as_str()))
+                .filter(|item| item.starts_with("30"))
+                .collect();
+            assert!(signatures
+                .iter()
+                .any(|signature| signature.ends_with(&signature_suffix)));
+            assert!(verify_with(&tx, input_index, VerifyFlags::STANDARD)?);
+
+            // A MULTISIG INPUT CAN ALSO CARRY A SIGHASH_ALL SIGNATURE, WHICH SIGNS EVERYTHING
+            if signatures
+                .iter()
+                .any(|signature| !signature.ends_with(&signature_suffix))
+            {
+                continue;
+            }
+
+            // ANYONECANPAY: THE OTHER INPUTS CAN CHANGE
+            let other_index = (input_index + 1) % tx.vin.len();
+            let mut changed = tx.clone();
+            changed.vin[other_index].vout += 1;
+            changed.vin[other_index].sequence ^= 1;
+            assert!(verify(&changed, input_index)?);
+
+            // BUT NOT THE SIGNED OUTPUTS: ALL OF THEM, NONE, OR THE ONE AT THE INPUT'S INDEX
+            let single = sighash_type == 0x83;
+            let mut changed = tx.clone();
+            for (output_index, output) in changed.vout.iter_mut().enumerate() {
+                if !single || output_index != input_index {
+                    output.value += 1;
+                }
+            }
+            assert_eq!(verify(&changed, input_index)?, sighash_type != 0x81);
+            if single {
+                let mut changed = tx.clone();
+                changed.vout[input_index].value += 1;
+                assert!(!verify(&changed, input_index)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn mixed_input_types_test() -> Result<()> {
+        // P2SH + P2TR, P2WPKH + P2TR, P2PKH + P2WPKH AND P2PKH + P2SH + P2WPKH
+        let mixed = [
+            "c053a958c5b69372ff05d34076d61d6f4e575b57aa33d49131cb67d68783c99e",
+            "4a182e4d00d047237dc773db2bca6414d60529557dd2b072f39bb9fbd56a1262",
+            "753b075bd84cdc7400acaf8b52cea866f099de2ebb70ea89fc98abe5f1162d5f",
+            "ad69eab9df11b00e401bcb9b546bb0f88cb2811a76d1cbe96a5e889aa82d15fe",
+        ];
+        for txid in mixed {
+            let tx = read_mempool_tx(txid)?;
+            for flags in [VerifyFlags::CONSENSUS, VerifyFlags::STANDARD] {
+                assert!(
+                    verify_tx(tx.clone(), flags, SchnorrMode::Batch)?,
+                    "{}",
+                    txid
+                );
+            }
+        }
+
+        // A WITNESS ON THE P2PKH INPUT IS ONLY INVALID ONCE BIP141 IS ACTIVE
+        let mut tx = read_mempool_tx(mixed[2])?;
+        let p2pkh_index = tx
+            .vin
+            .iter()
+            .position(|input| input.prevout.scriptpubkey_type == "p2pkh")
+            .unwrap();
+        tx.vin[p2pkh_index].witness = Some(vec!["01".to_string()]);
+        let cache = SighashCache::new(&tx)?;
+        let verify = |flags| verify_input(&tx, &cache, p2pkh_index, flags, &mut Vec::new());
+        assert!(!verify(VerifyFlags::CONSENSUS)?);
+        assert!(verify(
+            VerifyFlags::CONSENSUS.difference(VerifyFlags::WITNESS)
+        )?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sighash_none_single_test() -> Result<()> {
+        let tx =
+            read_mempool_tx("9dce3b0796a8598a90c80b24c90ced42032b71a8b53641fcdf0729536aa371c6")?;
+        let script_pubkey = tx.vin[1].prevout.script_pubkey()?;
+
+        let mut other_output = tx.clone();
+        other_output.vout[0].value += 1;
+        let mut own_output = tx.clone();
+        own_output.vout[1].value += 1;
+        let mut other_sequence = tx.clone();
+        other_sequence.vin[0].sequence ^= 1;
+        let mut other_outpoint = tx.clone();
+        other_outpoint.vin[0].vout += 1;
+
+        for (input_type, script_code) in [
+            ("NON_SEGWIT", Some(script_pubkey.as_bytes())),
+            ("P2WPKH", None),
+        ] {
+            let sighash = |tx: &Transaction, sighash_type: u32| -> Result<Vec<u8>> {
+                let cache = SighashCache::new(tx)?;
+                signature_hash(tx, &cache, 1, input_type, sighash_type, script_code)
+            };
+            // WHICH CHANGES TO THE TX LEAVE THE SIGHASH OF INPUT 1 AS IT IS
+            let unchanged = |sighash_type: u32| -> Result<[bool; 4]> {
+                let original = sighash(&tx, sighash_type)?;
+                let mut unchanged = [false; 4];
+                for (index, changed) in
+                    [&other_output, &own_output, &other_sequence, &other_outpoint]
+                        .into_iter()
+                        .enumerate()
+                {
+                    unchanged[index] = sighash(changed, sighash_type)? == original;
+                }
+                Ok(unchanged)
+            };
+
+            assert_eq!(unchanged(0x01)?, [false, false, false, false]);
+            assert_eq!(unchanged(0x02)?, [true, true, true, false]);
+            assert_eq!(unchanged(0x03)?, [true, false, true, false]);
+            assert_eq!(unchanged(0x81)?, [false, false, true, true]);
+            assert_eq!(unchanged(0x82)?, [true, true, true, true]);
+            assert_eq!(unchanged(0x83)?, [true, false, true, true]);
+            // UNDEFINED TYPES SIGN LIKE SIGHASH_ALL
+            assert_eq!(unchanged(0x04)?, [false, false, false, false]);
+        }
+
+        // A LEGACY SIGHASH_SINGLE PAST THE LAST OUTPUT SIGNS 1, A SEGWIT ONE SIGNS NO OUTPUT
+        let tx =
+            read_mempool_tx("bd9e30c74c020fec96b3506e9dc9bcbbb4f2b2ad1e323cfc3c60b9da870220ff")?;
+        let cache = SighashCache::new(&tx)?;
+        let script_code = Some(script_pubkey.as_bytes());
+        let mut one = vec![0u8; 32];
+        one[0] = 1;
+        assert_eq!(
+            signature_hash(&tx, &cache, 5, "NON_SEGWIT", 0x03, script_code)?,
+            one
+        );
+        let segwit = signature_hash(&tx, &cache, 5, "P2WPKH", 0x03, None)?;
+        assert_ne!(segwit, one);
+        let mut changed = tx.clone();
+        changed.vout[0].value += 1;
+        let changed_cache = SighashCache::new(&changed)?;
+        assert_eq!(
+            signature_hash(&changed, &changed_cache, 5, "P2WPKH", 0x03, None)?,
+            segwit
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn op_checksig_nullfail_test() -> Result<()> {
+        let tx =
+            read_mempool_tx("bd9e30c74c020fec96b3506e9dc9bcbbb4f2b2ad1e323cfc3c60b9da870220ff")?;
+        let cache = SighashCache::new(&tx)?;
+        let witness = tx.vin[0]
+            .witness_items()
+            .iter()
+            .map(hex::decode)
+            .collect::<std::result::Result<Vec<Vec<u8>>, _>>()?;
+        let checksig = |mut stack: Vec<Vec<u8>>, flags| {
+            op_checksig(&mut stack, &tx, &cache, 0, "P2WPKH", None, flags)
+        };
+
+        assert!(checksig(witness.clone(), VerifyFlags::STANDARD)?);
+
+        // A WRONG R IS STILL STRICT DER, THE CHECK JUST FAILS
+        let mut wrong = witness.clone();
+        wrong[0][6] ^= 1;
+        assert!(!checksig(wrong.clone(), VerifyFlags::CONSENSUS)?);
+        let error = checksig(wrong, VerifyFlags::STANDARD).unwrap_err();
+        assert_eq!(error.downcast::<ScriptError>()?, ScriptError::SigNullFail);
+
+        // AN EMPTY SIGNATURE IS HOW A SCRIPT SAYS NO
+        let mut empty = witness;
+        empty[0].clear();
+        assert!(!checksig(empty, VerifyFlags::STANDARD)?);
+
+        Ok(())
+    }
+
     #[test]
     fn test_all_transaction_verification() -> Result<()> {
         let mempool_dir = "./mempool";
-        let mut spends: HashMap<String, String> = HashMap::new();
+        let valid_mempool_dir = std::env::temp_dir().join("all-transaction-verification-test");
+        fs::create_dir_all(&valid_mempool_dir)?;
+        let mut spends: HashMap<OutPoint, String> = HashMap::new();
         'outer: for entry in WalkDir::new(mempool_dir).into_iter().filter_map(|e| e.ok()) {
             let path = entry.path();
             if path.is_file() {
@@ -741,7 +1148,7 @@ mod test {
                                 let all_p2sh = true;
                                 if all_p2sh {
                                     for input in &transaction.vin {
-                                        let input_key = format!("{}{}", input.txid, input.vout);
+                                        let input_key = input.outpoint();
                                         match spends.get(&input_key) {
                                             Some(existing_txid)
                                                 if path.display().to_string() != *existing_txid =>
@@ -756,11 +1163,14 @@ mod test {
                                         }
                                     }
 
-                                    let result = verify_tx(transaction)?;
+                                    let result = verify_tx(
+                                        transaction,
+                                        VerifyFlags::STANDARD,
+                                        SchnorrMode::Serial,
+                                    )?;
 
                                     if result == true {
                                         if let Some(filename) = path.file_name() {
-                                            let valid_mempool_dir = Path::new("./valid-mempool");
                                             let destination_path = valid_mempool_dir.join(filename);
                                             fs::copy(&path, &destination_path)?;
                                         }
@@ -776,6 +1186,7 @@ mod test {
             }
         }
 
+        fs::remove_dir_all(&valid_mempool_dir)?;
         Ok(())
     }
 }
@@ -1,194 +1,49 @@
-// OPERATE ON THE P2PKH TRANSACTIONS 
-use hex;
-use ripemd::Ripemd160;
-use secp256k1::{ecdsa::Signature, Message, PublicKey, Secp256k1};
-use sha2::{Digest, Sha256};
-
+// OPERATE ON THE P2PKH TRANSACTIONS
 use crate::error::Result;
+use crate::script::Script;
 use crate::transaction::Transaction;
+use crate::validation_checks::interpreter::{encoding_error_or_ok, verify_legacy_script};
+use crate::validation_checks::sighash_cache::SighashCache;
+use crate::validation_checks::verify_flags::VerifyFlags;
 
-pub fn input_verification_p2pkh(tx: Transaction, tx_input_index: usize) -> Result<bool> {
-    // EXTRACT THE SCRIPT PUB KEY ASM AND SCRIPT-SIG ASM FROM THE INPUT
-
-    let scriptsig_asm = match tx.vin[tx_input_index].scriptsig_asm.clone() {
-        Some(value) => value,
-        None => {
-            return Ok(false);
-        }
-    };
-
-    let scriptpubkey_asm = tx.vin[tx_input_index].prevout.scriptpubkey_asm.clone();
-
-    Ok(script_execution(
-        scriptpubkey_asm,
-        scriptsig_asm,
+pub fn input_verification_p2pkh(
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    flags: VerifyFlags,
+) -> Result<bool> {
+    // PARSE THE SCRIPT PUB KEY AND SCRIPT-SIG OF THE INPUT FROM THEIR HEX
+    let script_sig = tx.vin[tx_input_index].script_sig()?;
+    let script_pubkey = tx.vin[tx_input_index].prevout.script_pubkey()?;
+
+    script_execution(
+        &script_pubkey,
+        &script_sig,
         tx,
+        cache,
         tx_input_index,
-    ))
+        flags,
+    )
 }
 
-// EXECUTE THE SCRIPT SIG ASM
+// EXECUTE THE SCRIPT SIG, <SIG> <PUBKEY>, THEN THE SCRIPT PUB KEY ON WHAT IT PUSHED:
+// OP_DUP OP_HASH160 <PUBKEY HASH> OP_EQUALVERIFY OP_CHECKSIG
 fn script_execution(
-    scriptpubkey_asm: String,
-    scriptsig_asm: String,
-    tx: Transaction,
+    script_pubkey: &Script,
+    script_sig: &Script,
+    tx: &Transaction,
+    cache: &SighashCache,
     tx_input_index: usize,
-) -> bool {
-    let sigscript_asm_slices: Vec<&str> = scriptsig_asm.split_whitespace().collect();
-
-    let signature = *sigscript_asm_slices.get(1).expect("Signature missing");
-    let pubkey = *sigscript_asm_slices.get(3).expect("Public key missing");
-
-    let sig = hex::decode(signature).expect("Failed to decode signature");
-    let pubkey = hex::decode(pubkey).expect("Failed to decode public key");
-
-    let mut stack: Vec<Vec<u8>> = Vec::new();
-
-    // PUSH THE SIGNATURE AND PUBLIC IN THE STACK
-
-    stack.push(sig);
-    stack.push(pubkey);
-
-    let op_codes: Vec<&str> = scriptpubkey_asm.split_whitespace().collect();
-
-    // LOGIC IMPLEMENTATION OF THE OPCODES THAT COME IN THE PATH 
-    for op_code in op_codes.iter() {
-        match *op_code {
-            "OP_DUP" => {
-                let top = stack.last().cloned().expect("STACK UNDEFLOW: OP_DUP");
-                stack.push(top);
-            }
-            "OP_HASH160" => {
-                let top = stack.pop().expect("STACK UNDERFLOW: OP_HASH160");
-                let hash = hash160(&top);
-                stack.push(hash);
-            }
-            "OP_PUSHBYTES_20" => {
-                // The next iteration will have the actual bytes to push
-                continue;
-            }
-            _ => {
-                // Assuming the curernt op_code is the bytes pushed by OP_PUSHBYTES_20
-                if op_code.len() == 40 {
-                    stack.push(hex::decode(op_code).unwrap());
-                } else if *op_code == "OP_EQUALVERIFY" {
-                    let a = stack.pop().expect("STACK UNDERFLOW: OP_EQUALVERIFY");
-                    let b = stack.pop().expect("STACK UNDERFLOW: OP_EQUALVERIFY");
-
-                    if a != b {
-                        return false;
-                    }
-                } else if *op_code == "OP_CHECKSIG" {
-                    let result = op_checksig(&tx, tx_input_index);
-
-                    if result == true {
-                        continue;
-                    } else {
-                        return false;
-                    }
-                }
-            }
-        }
-    }
-    true
-}
-
-fn hash160(data: &[u8]) -> Vec<u8> {
-    Ripemd160::digest(&Sha256::digest(data)).to_vec()
-}
-
-fn double_sha256(data: &[u8]) -> Vec<u8> {
-    Sha256::digest(&Sha256::digest(data)).to_vec()
-}
-
-// OPCHECK_SIG OPERATION AND TRIMMED TX CREATION FOR P2PKH 
-fn op_checksig(tx: &Transaction, tx_input_index: usize) -> bool {
-    let mut trimmed_tx = Vec::new();
-
-    trimmed_tx.extend(&tx.version.to_le_bytes());
-    trimmed_tx.push(tx.vin.len() as u8);
-
-    for input_index in 0..tx.vin.len() {
-        let mut txid_bytes_reversed =
-            hex::decode(&tx.vin[input_index].txid).expect("DECODING FAILED");
-
-        txid_bytes_reversed.reverse();
-
-        trimmed_tx.extend_from_slice(&txid_bytes_reversed);
-        trimmed_tx.extend(&tx.vin[input_index].vout.to_le_bytes());
-
-        if input_index == tx_input_index {
-            let script_pub_key_bytes =
-                hex::decode(&tx.vin[input_index].prevout.scriptpubkey).expect("DECODING FAILED");
-            trimmed_tx.push(script_pub_key_bytes.len().try_into().unwrap());
-            trimmed_tx.extend_from_slice(&script_pub_key_bytes);
-        } else {
-            trimmed_tx.push(0 as u8);
-        }
-
-        trimmed_tx.extend(&tx.vin[input_index].sequence.to_le_bytes());
-    }
-
-    trimmed_tx.push(tx.vout.len() as u8);
-
-    for tx_ouput in tx.vout.iter() {
-        let script_pub_key_bytes =
-            hex::decode(tx_ouput.scriptpubkey.clone()).expect("DECODING FAILED");
-
-        trimmed_tx.extend(tx_ouput.value.to_le_bytes());
-        trimmed_tx.push(script_pub_key_bytes.len().try_into().unwrap());
-        trimmed_tx.extend_from_slice(&script_pub_key_bytes);
-    }
-
-    trimmed_tx.extend(&tx.locktime.to_le_bytes());
-
-    if let Some(sighash_type) = extract_sighash_type(
-        tx.vin[tx_input_index]
-            .scriptsig_asm
-            .clone()
-            .expect("SCRIPT SIG ASM: MISSING"),
-    ) {
-        trimmed_tx.extend(&sighash_type.to_le_bytes());
-    }
-
-    // THE TRIMMED TRANSACTION IS READY
-
-    let scriptsig_asm = tx.vin[tx_input_index]
-        .scriptsig_asm
-        .clone()
-        .expect("SCRIPT SIG ASM: MISSING");
-    let scriptsig_asm_slices: Vec<&str> = scriptsig_asm.split_whitespace().collect();
-
-    let signature = scriptsig_asm_slices[1];
-    let pubkey = scriptsig_asm_slices[3];
-
-    let trimmed_tx_hash = double_sha256(&trimmed_tx);
-    let signature_bytes = hex::decode(signature).expect("DECODING: FAILED");
-    let pubkey_bytes = hex::decode(pubkey).expect("DECODING: FAILED");
-
-    let secp = Secp256k1::new();
-    let public_key = PublicKey::from_slice(&pubkey_bytes).expect("ERROR PARSING: PUBLIC KEY");
-    let signature = Signature::from_der(&signature_bytes[..signature_bytes.len() - 1]).unwrap();
-
-    let message =
-        Message::from_digest_slice(&trimmed_tx_hash).expect("ERROR CREATING MESSAGE FROM TX_HASH");
-
-    match secp.verify_ecdsa(&message, &signature, &public_key) {
-        Ok(_) => {
-            return true;
-        }
-        Err(_) => return false,
-    }
-}
-
-// EXTRACTS THE SIGHASH TYPE FROM THE LAST OF THE SIGNATURE
-fn extract_sighash_type(scriptsig_asm: String) -> Option<u32> {
-    let scriptsig_slices: Vec<&str> = scriptsig_asm.split_whitespace().collect();
-    let signature = scriptsig_slices[1];
-    let sig_bytes = hex::decode(signature).ok()?;
-    let sighash_type = sig_bytes.last().copied().expect("NOT FOUND") as u32;
-
-    Some(sighash_type)
+    flags: VerifyFlags,
+) -> Result<bool> {
+    encoding_error_or_ok(verify_legacy_script(
+        script_sig,
+        script_pubkey,
+        tx,
+        cache,
+        tx_input_index,
+        flags,
+    ))
 }
 
 // TO TEST MY CODE DURING DEVELOPMENT
@@ -197,6 +52,8 @@ mod test {
     use std::fs;
 
     use super::*;
+    use crate::script::{OP_1, OP_NOP};
+    use crate::validation_checks::script_error::ScriptError;
     use walkdir::WalkDir;
 
     #[test]
@@ -209,49 +66,44 @@ mod test {
             let path = entry.path();
             if path.is_file() {
                 match fs::read_to_string(path) {
-                    Ok(contents) => {
-                        match serde_json::from_str::<Transaction>(&contents) {
-                            Ok(transaction) => {
-                                let all_p2sh = transaction.clone().vin.iter().all(|input| {
-                                    input.prevout.scriptpubkey_type == "p2pkh".to_string()
-                                });
-
-                                let mut tx_result = true;
-
-                                if all_p2sh {
-                                    for input_index in 0..transaction.vin.len() {
-                                        let scriptsig_asm = transaction.clone().vin[input_index]
-                                            .scriptsig_asm
-                                            .clone()
-                                            .expect("ASM: MISSING");
-
-                                        let tx = transaction.clone();
-                                        let result = script_execution(
-                                            tx.vin[input_index].prevout.scriptpubkey_asm.clone(),
-                                            scriptsig_asm,
-                                            tx,
-                                            input_index,
-                                        );
-                                        if result == false {
-                                            tx_result = false;
-                                            break;
-                                        }
-                                    }
-
-                                    if tx_result == true {
-                                        s_count += 1;
-                                    } else {
-                                        f_count += 1;
+                    Ok(contents) => match serde_json::from_str::<Transaction>(&contents) {
+                        Ok(transaction) => {
+                            let all_p2sh = transaction.clone().vin.iter().all(|input| {
+                                input.prevout.scriptpubkey_type == "p2pkh".to_string()
+                            });
+
+                            let mut tx_result = true;
+
+                            if all_p2sh {
+                                let cache = SighashCache::new(&transaction)?;
+                                for input_index in 0..transaction.vin.len() {
+                                    let tx = transaction.clone();
+                                    let result = script_execution(
+                                        &tx.vin[input_index].prevout.script_pubkey()?,
+                                        &tx.vin[input_index].script_sig()?,
+                                        &tx,
+                                        &cache,
+                                        input_index,
+                                        VerifyFlags::STANDARD,
+                                    )
+                                    .unwrap_or(false);
+                                    if result == false {
+                                        tx_result = false;
+                                        break;
                                     }
+                                }
 
-                                    // println!("\n\n");
+                                if tx_result == true {
+                                    s_count += 1;
+                                } else {
+                                    f_count += 1;
                                 }
                             }
-                            Err(e) => {
-                                println!("Failed to parse JSON: {}", e);
-                            }
                         }
-                    }
+                        Err(e) => {
+                            println!("Failed to parse JSON: {}", e);
+                        }
+                    },
                     Err(e) => eprintln!("Failed to read file: {}", e),
                 }
             }
@@ -259,6 +111,7 @@ mod test {
 
         println!("success: {}", s_count);
         println!("failure: {}", f_count);
+        assert!(s_count > 0);
 
         Ok(())
     }
@@ -274,22 +127,82 @@ mod test {
         // Deserialize JSON into Rust data structures
         let transaction: Transaction = serde_json::from_str(&data)?;
 
-        let scriptsig_asm = transaction.clone().vin[0]
-            .scriptsig_asm
-            .clone()
-            .expect("ASM: MISSING");
-
         let tx = transaction.clone();
+        let cache = SighashCache::new(&tx)?;
         let result = script_execution(
-            tx.vin[0].prevout.scriptpubkey_asm.clone(),
-            scriptsig_asm,
-            tx,
+            &tx.vin[0].prevout.script_pubkey()?,
+            &tx.vin[0].script_sig()?,
+            &tx,
+            &cache,
             0,
-        );
+            VerifyFlags::STANDARD,
+        )?;
 
         println!("{}", result);
 
         Ok(())
     }
-}
 
+    #[test]
+    fn policy_script_sig_test() -> Result<()> {
+        let path =
+            "./mempool/01f16e8312f9c882e869d31a3ab386b94a38f6091f7e947c6f2ed2b3389f4406.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let script_pubkey = tx.vin[0].prevout.script_pubkey()?;
+        let script_sig = tx.vin[0].script_sig()?;
+        let cache = SighashCache::new(&tx)?;
+        let run = |script_sig: Vec<u8>, flags| {
+            verify_legacy_script(
+                &Script::new(script_sig),
+                &script_pubkey,
+                &tx,
+                &cache,
+                0,
+                flags,
+            )
+        };
+
+        // THE SIGNATURE DOESN'T COMMIT TO THE SCRIPT SIG, SO THESE STAY VALID UNDER CONSENSUS
+        // <SIG> <PUBKEY> OP_NOP
+        let mut not_push_only = script_sig.as_bytes().to_vec();
+        not_push_only.push(OP_NOP);
+        // OP_1 <SIG> <PUBKEY> LEAVES THE OP_1 UNDER THE RESULT
+        let mut not_clean = vec![OP_1];
+        not_clean.extend(script_sig.as_bytes());
+
+        assert_eq!(
+            run(script_sig.as_bytes().to_vec(), VerifyFlags::STANDARD),
+            Ok(())
+        );
+        assert_eq!(run(not_push_only.clone(), VerifyFlags::CONSENSUS), Ok(()));
+        assert_eq!(
+            run(not_push_only, VerifyFlags::STANDARD),
+            Err(ScriptError::SigPushOnly)
+        );
+        assert_eq!(run(not_clean.clone(), VerifyFlags::CONSENSUS), Ok(()));
+        assert_eq!(
+            run(not_clean, VerifyFlags::STANDARD),
+            Err(ScriptError::CleanStack)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_script_sig_test() -> Result<()> {
+        let path =
+            "./mempool/01f16e8312f9c882e869d31a3ab386b94a38f6091f7e947c6f2ed2b3389f4406.json";
+        let mut tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        // A SINGLE PUSH, OP_0 OP_0, AN EMPTY SCRIPT SIG AND A TRUNCATED PUSH FAIL WITHOUT A PANIC
+        for script_sig in ["01ab", "0000", "", "4c"] {
+            tx.vin[0].scriptsig = Some(script_sig.to_string());
+            let cache = SighashCache::new(&tx)?;
+            for flags in [VerifyFlags::CONSENSUS, VerifyFlags::STANDARD] {
+                assert!(!input_verification_p2pkh(&tx, &cache, 0, flags)?);
+            }
+        }
+
+        Ok(())
+    }
+}
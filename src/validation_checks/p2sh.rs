@@ -1,407 +1,193 @@
-use std::vec;
-
 use hex;
-use log::info;
 
+use crate::script::{encode_push, Script};
 use crate::validation_checks::hash160;
-use crate::validation_checks::op_checkmultisig;
+use crate::validation_checks::interpreter::{
+    cast_to_bool, encoding_error_or_ok, eval_script, verify_redeem_script, verify_witness_script,
+};
 use crate::validation_checks::op_checksig;
+use crate::validation_checks::sighash_cache::SighashCache;
+use crate::validation_checks::single_sha256;
+use crate::validation_checks::verify_flags::VerifyFlags;
 
 use crate::{error::Result, transaction::Transaction};
 
 // IMPUT VERIFICATION FOR P2SH
-pub fn input_verification_p2sh(tx_input_index: usize, tx: Transaction) -> Result<bool> {
-    let scriptpubkey_asm = tx.vin[tx_input_index].prevout.scriptpubkey_asm.clone();
-
-    let witness = match tx.vin[tx_input_index].witness.clone() {
-        Some(value) => value,
-        None => Vec::new(),
-    };
-
-    let scriptsig_asm = match tx.vin[tx_input_index].scriptsig_asm.clone() {
-        Some(value) => value,
-        None => {
-            return Ok(false);
-        }
-    };
-
-    let inner_redeemscript_asm = match tx.vin[tx_input_index].inner_redeemscript_asm.clone() {
-        Some(value) => value,
-        None => {
-            return Ok(false);
-        }
-    };
+pub fn input_verification_p2sh(
+    tx_input_index: usize,
+    tx: &Transaction,
+    cache: &SighashCache,
+    flags: VerifyFlags,
+) -> Result<bool> {
+    let witness = tx.vin[tx_input_index]
+        .witness_items()
+        .iter()
+        .map(hex::decode)
+        .collect::<std::result::Result<Vec<Vec<u8>>, _>>()?;
+
+    let script_sig = tx.vin[tx_input_index].script_sig()?;
+    if script_sig.is_empty() {
+        return Ok(false);
+    }
 
-    Ok(script_execution_p2sh(
-        scriptpubkey_asm,
-        witness,
-        scriptsig_asm,
-        inner_redeemscript_asm,
-        tx,
-        tx_input_index,
-    )?)
+    script_execution_p2sh(witness, &script_sig, tx, cache, tx_input_index, flags)
 }
 
 // SCRIPT EXECUTION
 fn script_execution_p2sh(
-    scriptpubkey_asm: String,
-    witness: Vec<String>,
-    scriptsig_asm: String,
-    inner_redeemscript_asm: String,
-    tx: Transaction,
+    witness: Vec<Vec<u8>>,
+    script_sig: &Script,
+    tx: &Transaction,
+    cache: &SighashCache,
     tx_input_index: usize,
+    flags: VerifyFlags,
 ) -> Result<bool> {
-    let mut script_result: bool = false;
-    let input_type: &str;
-
-    // DEFINING THE SCRIPT TYPE OF THE VERIFYING INPUT
-    if witness.len() == 0 {
-        input_type = "NON_SEGWIT";
-    } else if witness.len() == 2 {
-        input_type = "P2SH-P2WPKH";
-    } else {
-        input_type = "P2SH-P2WSH";
+    // UNDER SIGPUSHONLY THE SCRIPT SIG MAY ONLY PUSH, WHETHER OR NOT BIP16 APPLIES
+    if flags.contains(VerifyFlags::SIGPUSHONLY) && !script_sig.is_push_only() {
+        return Ok(false);
     }
 
-    let mut stack = Vec::new();
-
-    // EXECUTING SCRIPT SIG ASM
-    let scriptsig_asm_opcodes: Vec<&str> = scriptsig_asm.split_whitespace().collect();
-    for opcode_index in 0..scriptsig_asm_opcodes.len() {
-        let is_pushbytes = scriptsig_asm_opcodes[opcode_index].starts_with("OP_PUSHBYTES");
-        let is_pushdata = scriptsig_asm_opcodes[opcode_index].starts_with("OP_PUSHDATA");
-
-        match scriptsig_asm_opcodes[opcode_index] {
-            "OP_0" => {
-                // Push an empty array of bytes in the stack
-                stack.push(vec![0 as u8]);
-            }
-            _ if is_pushbytes => {
-                // PUSHING SIGNATURES OR REDEEM SCRIPT IN SCRIPT SIG ASM
-                stack.push(hex::decode(&scriptsig_asm_opcodes[opcode_index + 1])?);
-            }
-
-            _ if is_pushdata => {
-                // REDEEM SCRIPT
-                stack.push(hex::decode(&scriptsig_asm_opcodes[opcode_index + 1])?);
-            }
-            _ => continue,
-        }
+    // EXECUTING THE SCRIPT SIG, OP_0 PUSHES AN EMPTY ITEM
+    let mut stack: Vec<Vec<u8>> = Vec::new();
+    if !encoding_error_or_ok(eval_script(
+        &mut stack,
+        script_sig.as_bytes(),
+        tx,
+        cache,
+        tx_input_index,
+        "NON_SEGWIT",
+        flags,
+    ))? {
+        return Ok(false);
     }
+    let redeem_stack = stack.clone();
 
-    // EXECUTING SCRIPT PUB KEY
-    let scriptpubkey_asm_opcodes: Vec<&str> = scriptpubkey_asm.split_whitespace().collect();
-    for opcode_index in 0..scriptpubkey_asm_opcodes.len() {
-        match scriptpubkey_asm_opcodes[opcode_index] {
-            "OP_HASH160" => {
-                let hash = hash160(&stack.pop().expect("STACK UNDERFLOW: OP_HASH160"));
-                stack.push(hash);
-            }
-            "OP_PUSHBYTES_20" => {
-                stack.push(
-                    hex::decode(&scriptpubkey_asm_opcodes[opcode_index + 1])
-                        .expect("DECODING: FAILED"),
-                );
-            }
-            "OP_EQUAL" => {
-                let a = stack.pop().expect("STACK UNDERFLOW: OP_EQUAL");
-                let b = stack.pop().expect("STACK UNDERFLOW: OP_EQUAL");
-
-                if a != b {
-                    return Ok(false);
-                }
-            }
-            _ => continue,
-        }
+    // EXECUTING THE SCRIPT PUB KEY: OP_HASH160 <HASH> OP_EQUAL ON THE REDEEM SCRIPT
+    let script_pubkey = tx.vin[tx_input_index].prevout.script_pubkey()?;
+    if !encoding_error_or_ok(eval_script(
+        &mut stack,
+        script_pubkey.as_bytes(),
+        tx,
+        cache,
+        tx_input_index,
+        "NON_SEGWIT",
+        flags,
+    ))? {
+        return Ok(false);
     }
-
-
-    // EXECUTING THE INNER REDEEM SCRIPT AS PER THE SCRIPT TYPE
-    if input_type == "NON_SEGWIT" {
-
-        // EXECUTE INNER REDEEM SCRIPT ASM
-        let inner_redeemscript_asm_opcodes: Vec<&str> =
-            inner_redeemscript_asm.split_whitespace().collect();
-
-        for opcode_index in 0..inner_redeemscript_asm_opcodes.len() {
-            let is_pushbytes =
-                inner_redeemscript_asm_opcodes[opcode_index].starts_with("OP_PUSHBYTES");
-
-            let is_pushdata =
-                inner_redeemscript_asm_opcodes[opcode_index].starts_with("OP_PUSHDATA");
-
-            let is_equal = inner_redeemscript_asm_opcodes[opcode_index].starts_with("OP_EQUAL");
-
-            match inner_redeemscript_asm_opcodes[opcode_index] {
-                "OP_PUSHNUM_2" => stack.push(vec![2u8]),
-                "OP_PUSHNUM_3" => stack.push(vec![3u8]),
-                "OP_PUSHNUM_4" => stack.push(vec![4u8]),
-
-                _ if is_pushbytes => {
-                    // PUSHING THE PUBLIC KEYS, SIGNATURES IN THE STACK
-                    stack.push(
-                        hex::decode(&inner_redeemscript_asm_opcodes[opcode_index + 1])
-                            .expect("DECODING: FAILED"),
-                    );
-                }
-
-                _ if is_pushdata => {
-                    // PUSHING DATA IN THE STACK
-                    stack.push(
-                        hex::decode(&inner_redeemscript_asm_opcodes[opcode_index + 1])
-                            .expect("DECODING: FAILED"),
-                    );
-                }
-
-                "OP_0" => {
-                    // Push an empty array of bytes in the stack
-                    stack.push(vec![0 as u8]);
-                }
-
-                "OP_CSV" => {
-                    // CHECK THE PREV PUSHED DATA WITH THE SEQUENCE OF THE INPUT, LATER
-                    continue;
-                }
-
-                "OP_DROP" => {
-                    // POPS THE TOP ELEMENT OF THE STACK
-                    stack.pop().expect("SATCK UNDERFLOW: OP_DROP");
-                }
-
-                "OP_DUP" => stack.push(stack.last().cloned().expect("STACK UNDERFLOW")),
-
-                "OP_HASH160" => {
-                    let pk = stack.pop().expect("STACK UNDERFLOW");
-                    stack.push(hash160(&pk));
-                }
-
-                _ if is_equal => {
-                    let a = stack.pop().expect("STACK UNDERFLOW: OP_EQUALVERIFY");
-                    let b = stack.pop().expect("STACK UNDERFLOW: OP_EQUALVERIFY");
-
-                    if a == b {
-                        // println!("REDEEM EQUAL: SUCCESSFULL");
-                        script_result = true;
-                    } else {
-                        // println!("REDEEM EQUAL: FAILED");
-                        return Ok(false);
-                    }
-                }
-
-                "OP_CHECKSIGVERIFY" => {
-                    let result = op_checksig(&mut stack, tx.clone(), tx_input_index, input_type)?;
-
-                    if result == false {
-                        // println!("OP_CHECKSIGVERIFY: FAILED");
-                        return Ok(false);
-                    }
-                }
-
-                "OP_DEPTH" => {
-                    // PUSH THE EXISTING LENGTH OF THE STACK
-                    stack.push(vec![0 as u8]);
-                }
-
-                "OP_CHECKSIG" => {
-                    // IMPLEMENT CHECKSIG OPCODE
-                    script_result =
-                        op_checksig(&mut stack, tx.clone(), tx_input_index, input_type)?;
-                }
-
-                "OP_CHECKMULTISIG" => {
-                    // IMPLEMENT CHECKMULTISIG OPCODE
-                    script_result =
-                        op_checkmultisig(&mut stack, tx.clone(), tx_input_index, input_type)?;
-                }
-                _ => continue,
-            }
-        }
+    match stack.last() {
+        Some(top) if cast_to_bool(top) => {}
+        _ => return Ok(false),
     }
 
-    if input_type == "P2SH-P2WPKH" {
-        // EXECUTE WITNESS
-
-        // PUSH SIGNATURE
-        stack.push(hex::decode(&witness[0]).expect("DECODING: FAILED"));
-
-        // PUSH PUBLIC KEY
-        stack.push(hex::decode(&witness[1]).expect("DECODING: FAILED"));
-
-        // IMPLICIT OPCODE EXECUTION (OP_DUP, OP_HASH160, OP_PUSHBYTES_20, OP_EQUALVERIFY, OP_CHECKSIG)
-
-        // OP_DUP
-        stack.push(stack.last().cloned().expect("STACK UNDERFLOW"));
-
-        // OP_HASH160
-        let pk = stack.pop().expect("STACK UNDERFLOW");
-        stack.push(hash160(&pk));
-
-        // EXECUTE INNER REDEEM SCRIPT (OP_PUSHBYTES_20)
-        let inner_redeemscript_opcodes: Vec<&str> =
-            inner_redeemscript_asm.split_whitespace().collect();
+    // WITHOUT BIP16 ONLY THE HASH IS CHECKED
+    if !flags.contains(VerifyFlags::P2SH) {
+        return Ok(true);
+    }
 
-        for opcode_index in 0..inner_redeemscript_opcodes.len() {
-            match inner_redeemscript_opcodes[opcode_index] {
-                "OP_0" => info!("SEGWIT VERSION: 0"),
+    // THE SCRIPT SIG MAY ONLY PUSH, ITS LAST PUSH IS THE REDEEM SCRIPT
+    if !script_sig.is_push_only() {
+        return Ok(false);
+    }
+    let mut redeem_stack = redeem_stack;
+    let redeem_script = match redeem_stack.pop() {
+        Some(redeem_script) => Script::new(redeem_script),
+        None => return Ok(false),
+    };
 
-                "OP_PUSHBYTES_20" => {
-                    stack.push(
-                        hex::decode(&inner_redeemscript_opcodes[opcode_index + 1])
-                            .expect("DECODING: FAILED"),
-                    );
-                }
-                _ => continue,
+    // DEFINING THE SCRIPT TYPE OF THE VERIFYING INPUT FROM THE REDEEM SCRIPT
+    let (version, program) = match redeem_script.witness_program() {
+        Some(witness_program) if flags.contains(VerifyFlags::WITNESS) => witness_program,
+        // WITHOUT BIP141 THE WITNESS PROGRAM IS JUST PUSHES
+        Some(_) => return Ok(true),
+        None => {
+            // A WITNESS ON AN INPUT THAT ISN'T SEGWIT IS INVALID UNDER BIP141
+            if !witness.is_empty() && flags.contains(VerifyFlags::WITNESS) {
+                return Ok(false);
             }
+            return encoding_error_or_ok(verify_redeem_script(
+                redeem_stack,
+                redeem_script.as_bytes(),
+                tx,
+                cache,
+                tx_input_index,
+                "NON_SEGWIT",
+                flags,
+            ));
         }
+    };
 
-        // OP_EQUALVERIFY
-        let a = stack.pop().expect("STACK UNDERFLOW");
-        let b = stack.pop().expect("STACK UNDERFLOW");
-
-        if a != b {
-            return Ok(false);
-        }
-
-        // OP_CHECKSIG
-        script_result = op_checksig(&mut stack, tx.clone(), tx_input_index, input_type)?;
+    // A NESTED WITNESS PROGRAM IS THE ONLY PUSH OF THE SCRIPT SIG, SO IT CAN'T BE MALLEATED
+    if script_sig.as_bytes() != encode_push(redeem_script.as_bytes()).as_slice() {
+        return Ok(false);
     }
 
-    if input_type == "P2SH-P2WSH" {
-        // EXECUTE WITNESS
-
-        // PUSH SIGNATURES
-
-        for index in 0..witness.len() - 1 {
-            stack.push(hex::decode(&witness[index])?);
-        }
-
-        // EXECUTING THE WITNESS SCRIPT
-
-        let witness_script_bytes = hex::decode(&witness.last().cloned().expect("SCRIPT MISSING"))?;
-
-        let mut index = 0;
-
-        while index < witness_script_bytes.len() {
-            let opcode = witness_script_bytes[index];
-            index += 1;
-
-            match opcode {
-                82 => {
-                    // OP_2 SIGNATURE COUNT
-                    stack.push(vec![2u8]);
-                }
-                _ if opcode <= 75 as u8 && opcode >= 1 as u8 => {
-                    // OP_PUSHBYTES_33
-                    if index + opcode as usize <= witness_script_bytes.len() {
-                        let bytes = witness_script_bytes[index..index + opcode as usize].to_vec();
-                        stack.push(bytes);
-                        index += opcode as usize;
-                    }
-                }
-
-                83 => {
-                    // OP_3 PUBLIC KEY COUNT
-                    stack.push(vec![3u8]);
-                }
-
-                174 => {
-                    // OP_CHECKMULTISIG
-                    script_result =
-                        op_checkmultisig(&mut stack, tx.clone(), tx_input_index, input_type)?;
-
-                    if script_result == true {
-                        stack.push(vec![1u8]);
-                    } else {
-                        stack.push(vec![0u8])
-                    }
-                }
-
-                173 => {
-                    // OP_CHECKSIGVERIFY
-                    let result = op_checksig(&mut stack, tx.clone(), tx_input_index, input_type)?;
-
-                    if result == true {
-                        stack.push(vec![1u8]);
-                    } else {
-                        stack.push(vec![0u8]);
-                    }
-
-                    // OP_VERIFY
-
-                    let top = stack.pop().unwrap();
-                    if top == vec![1u8] {
-                        script_result = true;
-                        continue;
-                    } else {
-                        return Ok(false);
-                    }
-                }
-
-                172 => {
-                    // OP_CHECKSIG
-
-                    let sig_length = stack[stack.len() - 1].len();
-
-                    if sig_length <= 75 && sig_length >= 70 {
-                        script_result =
-                            op_checksig(&mut stack, tx.clone(), tx_input_index, input_type)?;
-
-                        if script_result == true {
-                            stack.push(vec![1u8]);
-                        } else {
-                            stack.push(vec![0u8])
-                        }
-                    } else {
-                        stack.push(vec![0u8]);
-                    }
-                }
+    match (version, program.len()) {
+        (0, 20) => script_execution_p2sh_p2wpkh(witness, program, tx, cache, tx_input_index, flags),
+        (0, 32) => script_execution_p2sh_p2wsh(witness, program, tx, cache, tx_input_index, flags),
+        (0, _) => Ok(false),
+        // A LATER WITNESS VERSION IS ANYONE CAN SPEND, KEPT FOR SOFT FORKS BY THE POLICY
+        _ => Ok(!flags.contains(VerifyFlags::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM)),
+    }
+}
 
-                100 => {
-                    // OP_NOTIF
-                    if stack.last().cloned().unwrap_or(vec![254u8]) == vec![0u8] {
-                    } else {
-                        if witness_script_bytes[index] <= 75 {
-                            if witness_script_bytes[index] >= 1 {
-                                index += witness_script_bytes[index] as usize;
-                            }
-                        } else if witness_script_bytes[index] == 103 {
-                            // EXECUTE THE NEXT STATEMENT
-                        } else if witness_script_bytes[index] == 104 {
-                            stack.pop();
-                            continue;
-                        }
-                    }
-                }
+// P2SH-P2WPKH: THE WITNESS IS A SIGNATURE AND A PUBLIC KEY THAT HASHES TO THE PROGRAM
+fn script_execution_p2sh_p2wpkh(
+    witness: Vec<Vec<u8>>,
+    pubkey_hash: &[u8],
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    flags: VerifyFlags,
+) -> Result<bool> {
+    if witness.len() != 2 {
+        return Ok(false);
+    }
 
-                99 => {
-                    // OP_IF
-                    if stack.last().cloned().unwrap_or(vec![254u8]) == vec![1u8] {
-                    } else {
-                        if witness_script_bytes[index] <= 75 {
-                            if witness_script_bytes[index] >= 1 {
-                                index += witness_script_bytes[index] as usize;
-                            }
-                        } else if witness_script_bytes[index] == 103 {
-                            // EXECUTE THE NEXT STATEMENT
-                        } else if witness_script_bytes[index] == 104 {
-                            stack.pop();
-                            continue;
-                        }
-                    }
-                }
+    // IMPLICIT OPCODE EXECUTION (OP_DUP, OP_HASH160, OP_PUSHBYTES_20, OP_EQUALVERIFY, OP_CHECKSIG)
+    if hash160(&witness[1]) != pubkey_hash {
+        return Ok(false);
+    }
 
-                115 => {
-                    // OP_IFDUP
-                    if stack.last().cloned().unwrap_or(vec![254u8]) != vec![0u8] {
-                        stack.push(stack.last().cloned().expect("STACK UNDERFLOW"))
-                    }
-                }
+    let mut stack = witness;
+    op_checksig(
+        &mut stack,
+        tx,
+        cache,
+        tx_input_index,
+        "P2SH-P2WPKH",
+        None,
+        flags,
+    )
+}
 
-                _ => continue,
-            }
-        }
+// P2SH-P2WSH: THE LAST WITNESS ITEM IS THE WITNESS SCRIPT, ITS SHA256 IS THE PROGRAM
+fn script_execution_p2sh_p2wsh(
+    mut witness: Vec<Vec<u8>>,
+    script_hash: &[u8],
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    flags: VerifyFlags,
+) -> Result<bool> {
+    let witness_script = match witness.pop() {
+        Some(witness_script) => witness_script,
+        None => return Ok(false),
+    };
+    if single_sha256(&witness_script) != script_hash {
+        return Ok(false);
     }
-    Ok(script_result)
+
+    // EXECUTING THE WITNESS SCRIPT ON THE OTHER WITNESS ITEMS
+    encoding_error_or_ok(verify_witness_script(
+        witness,
+        &witness_script,
+        tx,
+        cache,
+        tx_input_index,
+        "P2SH-P2WSH",
+        flags,
+    ))
 }
 
 // TO TEST MY CODE DURING DEVELOPMENT
@@ -415,43 +201,58 @@ mod test {
 
     #[test]
     fn test_script_execution_p2sh() -> Result<()> {
+        // VALID INPUTS PER KIND: LEGACY, P2SH-P2WPKH, P2SH-P2WSH
+        let mut valid = [0; 3];
+
         let mempool_dir = "./mempool";
         for entry in WalkDir::new(mempool_dir).into_iter().filter_map(|e| e.ok()) {
             let path = entry.path();
-            if path.is_file() {
-                match fs::read_to_string(path) {
-                    Ok(contents) => {
-                        match serde_json::from_str::<Transaction>(&contents) {
-                            Ok(transaction) => {
-                                // Check if all inputs' prevout scriptpubkey_type are .p2sh
-                                let all_p2sh = transaction.vin.iter().all(|input| {
-                                    input.prevout.scriptpubkey_type == "p2sh".to_string()
-                                });
-                                if all_p2sh {
-                                    let result = script_execution_p2sh(
-                                        transaction.vin[0].prevout.scriptpubkey_asm.clone(),
-                                        transaction.vin[0]
-                                            .witness
-                                            .clone()
-                                            .unwrap_or(vec!["".to_string()]),
-                                        transaction.vin[0].scriptsig_asm.clone().unwrap(),
-                                        transaction.vin[0].inner_redeemscript_asm.clone().unwrap(),
-                                        transaction,
-                                        0,
-                                    )?;
+            if !path.is_file() {
+                continue;
+            }
+            let transaction: Transaction = match fs::read_to_string(path)
+                .ok()
+                .and_then(|contents| serde_json::from_str(&contents).ok())
+            {
+                Some(transaction) => transaction,
+                None => continue,
+            };
+
+            // Check if all inputs' prevout scriptpubkey_type are .p2sh
+            let all_p2sh = transaction
+                .vin
+                .iter()
+                .all(|input| input.prevout.scriptpubkey_type == "p2sh");
+            if !all_p2sh {
+                continue;
+            }
 
-                                    if result == true {
-                                    } else {
-                                    }
-                                }
-                            }
-                            Err(_e) => {}
-                        }
-                    }
-                    Err(_e) => {}
-                }
+            let cache = SighashCache::new(&transaction)?;
+            let input = &transaction.vin[0];
+            if !input_verification_p2sh(0, &transaction, &cache, VerifyFlags::STANDARD)? {
+                continue;
             }
+
+            let redeem_script = input.redeem_script()?.expect("REDEEM SCRIPT");
+            match redeem_script.witness_program() {
+                None => valid[0] += 1,
+                Some((0, program)) if program.len() == 20 => valid[1] += 1,
+                Some(_) => valid[2] += 1,
+            }
+
+            // THE REDEEM SCRIPT MUST HASH TO THE SCRIPT PUB KEY
+            let mut wrong_prevout = transaction.clone();
+            wrong_prevout.vin[0].prevout.scriptpubkey = format!("a914{}87", "00".repeat(20));
+            assert!(!input_verification_p2sh(
+                0,
+                &wrong_prevout,
+                &cache,
+                VerifyFlags::STANDARD
+            )?);
         }
+
+        // THE MEMPOOL HAS VALID LEGACY, P2SH-P2WPKH AND P2SH-P2WSH SPENDS
+        assert!(valid.iter().all(|count| *count > 0), "{:?}", valid);
         Ok(())
     }
 }
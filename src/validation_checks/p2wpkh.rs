@@ -1,24 +1,34 @@
 use hex;
 
 use crate::validation_checks::op_checksig;
+use crate::validation_checks::sighash_cache::SighashCache;
+use crate::validation_checks::verify_flags::VerifyFlags;
 
 use crate::{error::Result, transaction::Transaction};
 
-pub fn input_verification_p2wpkh(tx_input_index: usize, tx: Transaction) -> Result<bool> {
+pub fn input_verification_p2wpkh(
+    tx_input_index: usize,
+    tx: &Transaction,
+    cache: &SighashCache,
+    flags: VerifyFlags,
+) -> Result<bool> {
     let witness = match tx.vin[tx_input_index].witness.clone() {
         Some(value) => value,
         None => Vec::new(),
     };
 
-    Ok(script_execution_p2wpkh(witness, tx, tx_input_index)?)
+    script_execution_p2wpkh(witness, tx, cache, tx_input_index, flags)
 }
 
 fn script_execution_p2wpkh(
     witness: Vec<String>,
-    tx: Transaction,
+    tx: &Transaction,
+    cache: &SighashCache,
     tx_input_index: usize,
+    flags: VerifyFlags,
 ) -> Result<bool> {
-    if witness.len() == 0 {
+    // A SIGNATURE AND A PUBLIC KEY, NOTHING ELSE
+    if witness.len() != 2 {
         return Ok(false);
     }
 
@@ -26,6 +36,11 @@ fn script_execution_p2wpkh(
         return Ok(false);
     }
 
+    // BEFORE BIP141 A WITNESS PROGRAM IS ANYONE CAN SPEND
+    if !flags.contains(VerifyFlags::WITNESS) {
+        return Ok(true);
+    }
+
     let input_type = "P2WPKH";
 
     let mut stack = Vec::new();
@@ -35,7 +50,15 @@ fn script_execution_p2wpkh(
     stack.push(hex::decode(&witness[1])?);
 
     // OP_CHECKSIG
-    let script_result = op_checksig(&mut stack, tx.clone(), tx_input_index, input_type)?;
+    let script_result = op_checksig(
+        &mut stack,
+        tx,
+        cache,
+        tx_input_index,
+        input_type,
+        None,
+        flags,
+    )?;
 
     Ok(script_result)
 }
@@ -65,10 +88,13 @@ mod test {
                                     input.prevout.scriptpubkey_type == "v0_p2wpkh".to_string()
                                 });
                                 if all_p2sh {
+                                    let cache = SighashCache::new(&transaction)?;
                                     let result = script_execution_p2wpkh(
                                         transaction.vin[0].witness.clone().unwrap(),
-                                        transaction,
+                                        &transaction,
+                                        &cache,
                                         0,
+                                        VerifyFlags::STANDARD,
                                     )?;
 
                                     if result == true {
@@ -100,7 +126,9 @@ mod test {
         let transaction: Transaction = serde_json::from_str(&data)?;
 
         let tx = transaction.clone();
-        let result = script_execution_p2wpkh(tx.vin[0].witness.clone().unwrap(), tx, 0)?;
+        let witness = tx.vin[0].witness.clone().unwrap();
+        let cache = SighashCache::new(&tx)?;
+        let result = script_execution_p2wpkh(witness, &tx, &cache, 0, VerifyFlags::STANDARD)?;
 
         println!("{}", result);
 
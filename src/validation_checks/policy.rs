@@ -0,0 +1,598 @@
+// MEMPOOL POLICY ON TOP OF THE CONSENSUS RULES, LIKE BITCOIN CORE'S IsStandardTx, AreInputsStandard
+// AND IsWitnessStandard. A BLOCK MAY STILL INCLUDE A TX THAT BREAKS IT
+// EVERY LIMIT IS READ FROM policy.json, A FIELD LEFT OUT KEEPS BITCOIN CORE'S DEFAULT
+
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block_mine::serialise_tx::compact_size,
+    error::Result,
+    script::{Script, ScriptType, OP_1, OP_RETURN},
+    transaction::{Input, Transaction},
+    validation_checks::{
+        fees::FeeRate,
+        interpreter::MAX_SCRIPT_SIZE,
+        sigops::count_sigops,
+        taproot::{SchnorrMode, ANNEX_TAG, TAPROOT_LEAF_MASK, TAPROOT_LEAF_TAPSCRIPT},
+    },
+};
+
+pub const POLICY_FILE: &str = "./policy.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+    // TX VERSIONS A NODE RELAYS
+    pub min_version: i32,
+    pub max_version: i32,
+    pub max_tx_weight: usize,
+    // SMALLER TXS COULD BE CONFUSED WITH A 64 BYTE MERKLE TREE NODE
+    pub min_tx_nonwitness_size: usize,
+    pub max_scriptsig_size: usize,
+
+    // FEE RATES ARE IN SAT/KVB. A TX MUST PAY `min_relay_fee` FOR ITS VSIZE
+    pub min_relay_fee: FeeRate,
+    // ONCE THE MEMPOOL HOLDS `max_mempool_vsize` VBYTES, THE LOWEST FEE RATES ARE EVICTED AND A
+    // NEW TX MUST PAY `incremental_relay_fee` MORE THAN THEY DID
+    pub max_mempool_vsize: usize,
+    pub incremental_relay_fee: FeeRate,
+    // A TX BELOW THE MIN FEE IS STILL ACCEPTED IF IT AND ONE CHILD PAY IT TOGETHER
+    pub package_feerate: bool,
+
+    // AN OUTPUT IS DUST IF SPENDING IT COSTS MORE THAN IT IS WORTH AT THIS FEE RATE
+    pub dust_relay_fee: FeeRate,
+    // FIXED DUST THRESHOLDS BY SCRIPT TYPE ("p2pkh", "p2tr", ...) THAT OVERRIDE THE FEE RATE,
+    // NONE BY DEFAULT
+    pub dust_thresholds: BTreeMap<String, u64>,
+
+    // OP_RETURN OUTPUTS, AT MOST `max_datacarrier_bytes` OF SCRIPT EACH
+    pub datacarrier: bool,
+    pub max_datacarrier_bytes: usize,
+    pub max_datacarrier_outputs: usize,
+
+    // BARE m-OF-n MULTISIG OUTPUTS, WITH n AT MOST `max_bare_multisig_keys`
+    pub permit_bare_multisig: bool,
+    pub max_bare_multisig_keys: usize,
+
+    pub max_p2sh_sigops: usize,
+
+    // WITNESS LIMITS OF P2WSH (ALSO NESTED IN P2SH) AND TAPSCRIPT SPENDS
+    pub max_p2wsh_script_size: usize,
+    pub max_p2wsh_stack_items: usize,
+    pub max_p2wsh_stack_item_size: usize,
+    pub max_tapscript_stack_item_size: usize,
+
+    // HOW THE TAPROOT SIGNATURES OF A TX, OR OF A WHOLE BLOCK, ARE VERIFIED
+    pub schnorr_mode: SchnorrMode,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            min_version: 1,
+            max_version: 2,
+            max_tx_weight: 400_000,
+            min_tx_nonwitness_size: 65,
+            max_scriptsig_size: 1650,
+            min_relay_fee: FeeRate::from_sat_per_kvb(1000),
+            // -maxmempool IS 300 MB OF MEMORY, HERE IT COUNTS VBYTES
+            max_mempool_vsize: 300_000_000,
+            incremental_relay_fee: FeeRate::from_sat_per_kvb(1000),
+            package_feerate: true,
+            dust_relay_fee: FeeRate::from_sat_per_kvb(3000),
+            dust_thresholds: BTreeMap::new(),
+            datacarrier: true,
+            max_datacarrier_bytes: 83,
+            max_datacarrier_outputs: 1,
+            permit_bare_multisig: true,
+            max_bare_multisig_keys: 3,
+            max_p2sh_sigops: 15,
+            max_p2wsh_script_size: 3600,
+            max_p2wsh_stack_items: 100,
+            max_p2wsh_stack_item_size: 80,
+            max_tapscript_stack_item_size: 80,
+            schnorr_mode: SchnorrMode::Serial,
+        }
+    }
+}
+
+// THE FIRST POLICY RULE A TX BREAKS, WITH THE INPUT OR OUTPUT THAT BREAKS IT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    Version(i32),
+    TxSize(usize),
+    TxSizeSmall(usize),
+    ScriptSigSize(usize),
+    ScriptSigNotPushOnly(usize),
+    // AN OUTPUT SCRIPT OF NO STANDARD TYPE
+    ScriptPubkey(usize),
+    BareMultisig(usize),
+    Datacarrier(usize),
+    MultiOpReturn(usize),
+    Dust(usize, u64),
+    // A SPENT OUTPUT OF NO STANDARD TYPE, OR A P2SH REDEEM SCRIPT WITH TOO MANY SIGOPS
+    NonStandardInput(usize),
+    P2shSigops(usize, usize),
+    // A WITNESS ON A NON WITNESS INPUT, AN ANNEX OR AN EMPTY TAPROOT WITNESS
+    NonStandardWitness(usize),
+    WitnessScriptSize(usize),
+    WitnessStackItems(usize),
+    WitnessItemSize(usize),
+}
+
+impl PolicyViolation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PolicyViolation::Version(_) => "VERSION",
+            PolicyViolation::TxSize(_) => "TX SIZE",
+            PolicyViolation::TxSizeSmall(_) => "TX SIZE SMALL",
+            PolicyViolation::ScriptSigSize(_) => "SCRIPTSIG SIZE",
+            PolicyViolation::ScriptSigNotPushOnly(_) => "SCRIPTSIG NOT PUSHONLY",
+            PolicyViolation::ScriptPubkey(_) => "SCRIPTPUBKEY",
+            PolicyViolation::BareMultisig(_) => "BARE MULTISIG",
+            PolicyViolation::Datacarrier(_) => "DATACARRIER",
+            PolicyViolation::MultiOpReturn(_) => "MULTI OP_RETURN",
+            PolicyViolation::Dust(_, _) => "DUST",
+            PolicyViolation::NonStandardInput(_) => "NONSTANDARD INPUT",
+            PolicyViolation::P2shSigops(_, _) => "P2SH SIGOPS",
+            PolicyViolation::NonStandardWitness(_) => "NONSTANDARD WITNESS",
+            PolicyViolation::WitnessScriptSize(_) => "WITNESS SCRIPT SIZE",
+            PolicyViolation::WitnessStackItems(_) => "WITNESS STACK ITEMS",
+            PolicyViolation::WitnessItemSize(_) => "WITNESS ITEM SIZE",
+        }
+    }
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolicyViolation::Version(version) => write!(f, "VERSION {} IS NOT RELAYED", version),
+            PolicyViolation::TxSize(weight) => write!(f, "WEIGHT {} IS TOO BIG", weight),
+            PolicyViolation::TxSizeSmall(size) => {
+                write!(f, "{} BYTES WITHOUT WITNESS IS TOO SMALL", size)
+            }
+            PolicyViolation::ScriptSigSize(input) => {
+                write!(f, "SCRIPT SIG OF vin[{}] IS TOO BIG", input)
+            }
+            PolicyViolation::ScriptSigNotPushOnly(input) => {
+                write!(f, "SCRIPT SIG OF vin[{}] IS NOT PUSH ONLY", input)
+            }
+            PolicyViolation::ScriptPubkey(output) => {
+                write!(f, "vout[{}] IS NOT A STANDARD SCRIPT", output)
+            }
+            PolicyViolation::BareMultisig(output) => {
+                write!(f, "vout[{}] IS A BARE MULTISIG", output)
+            }
+            PolicyViolation::Datacarrier(output) => {
+                write!(f, "vout[{}] CARRIES TOO MUCH DATA", output)
+            }
+            PolicyViolation::MultiOpReturn(count) => write!(f, "{} OP_RETURN OUTPUTS", count),
+            PolicyViolation::Dust(output, value) => {
+                write!(f, "vout[{}] OF {} SATS IS DUST", output, value)
+            }
+            PolicyViolation::NonStandardInput(input) => {
+                write!(f, "vin[{}] SPENDS A NON STANDARD OUTPUT", input)
+            }
+            PolicyViolation::P2shSigops(input, sigops) => {
+                write!(f, "REDEEM SCRIPT OF vin[{}] HAS {} SIGOPS", input, sigops)
+            }
+            PolicyViolation::NonStandardWitness(input) => {
+                write!(f, "WITNESS OF vin[{}] IS NOT STANDARD", input)
+            }
+            PolicyViolation::WitnessScriptSize(input) => {
+                write!(f, "WITNESS SCRIPT OF vin[{}] IS TOO BIG", input)
+            }
+            PolicyViolation::WitnessStackItems(input) => {
+                write!(f, "TOO MANY WITNESS STACK ITEMS IN vin[{}]", input)
+            }
+            PolicyViolation::WitnessItemSize(input) => {
+                write!(f, "WITNESS STACK ITEM OF vin[{}] IS TOO BIG", input)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+impl Policy {
+    // THE DEFAULTS IF THERE IS NO FILE AT `path`
+    pub fn load(path: &str) -> Result<Self> {
+        if !Path::new(path).exists() {
+            return Ok(Policy::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    // GetDustThreshold: WHAT THE OUTPUT AND THE INPUT SPENDING IT COST AT `dust_relay_fee`
+    pub fn dust_threshold(&self, script_pubkey: &Script) -> u64 {
+        let script_type = script_pubkey.script_type();
+        if let Some(threshold) = self.dust_thresholds.get(&script_type.to_string()) {
+            return *threshold;
+        }
+
+        // OP_RETURN AND OVERSIZED SCRIPTS CAN NEVER BE SPENT
+        let bytes = script_pubkey.as_bytes();
+        if bytes.first() == Some(&OP_RETURN) || bytes.len() > MAX_SCRIPT_SIZE {
+            return 0;
+        }
+
+        let output_size = 8 + compact_size(bytes.len() as u64).len() + bytes.len();
+        // OUTPOINT, SCRIPT SIG, SEQUENCE, AND A 107 BYTE SIGNATURE AND KEY IN THE WITNESS OR NOT
+        let input_size = match script_pubkey.witness_program() {
+            Some(_) => 32 + 4 + 1 + 107 / 4 + 4,
+            None => 32 + 4 + 1 + 107 + 4,
+        };
+        self.dust_relay_fee.fee(output_size + input_size).to_sat()
+    }
+
+    // IsStandardTx, AreInputsStandard AND IsWitnessStandard
+    pub fn check_tx(&self, tx: &Transaction) -> std::result::Result<(), PolicyViolation> {
+        if tx.version < self.min_version || tx.version > self.max_version {
+            return Err(PolicyViolation::Version(tx.version));
+        }
+
+        // SIZES ARE 0 IF A HEX DOESN'T DECODE, THE SCRIPT CHECKS BELOW REJECT IT
+        let weight = tx.weight().unwrap_or_default();
+        if weight > self.max_tx_weight {
+            return Err(PolicyViolation::TxSize(weight));
+        }
+        let base_size = tx.base_size().unwrap_or_default();
+        if base_size < self.min_tx_nonwitness_size {
+            return Err(PolicyViolation::TxSizeSmall(base_size));
+        }
+
+        for (index, input) in tx.vin.iter().enumerate() {
+            let script_sig = input
+                .script_sig()
+                .map_err(|_| PolicyViolation::ScriptSigNotPushOnly(index))?;
+            if script_sig.len() > self.max_scriptsig_size {
+                return Err(PolicyViolation::ScriptSigSize(index));
+            }
+            if !script_sig.is_push_only() {
+                return Err(PolicyViolation::ScriptSigNotPushOnly(index));
+            }
+        }
+
+        let mut op_returns = 0;
+        for (index, output) in tx.vout.iter().enumerate() {
+            let script_pubkey = output
+                .script_pubkey()
+                .map_err(|_| PolicyViolation::ScriptPubkey(index))?;
+            self.check_output(index, &script_pubkey)?;
+
+            if script_pubkey.script_type() == ScriptType::OpReturn {
+                op_returns += 1;
+            } else if output.value < self.dust_threshold(&script_pubkey) {
+                return Err(PolicyViolation::Dust(index, output.value));
+            }
+        }
+        if op_returns > self.max_datacarrier_outputs {
+            return Err(PolicyViolation::MultiOpReturn(op_returns));
+        }
+
+        for (index, input) in tx.vin.iter().enumerate() {
+            self.check_input(index, input)?;
+            self.check_witness(index, input)?;
+        }
+
+        Ok(())
+    }
+
+    // IsStandard FOR ONE OUTPUT SCRIPT
+    fn check_output(
+        &self,
+        index: usize,
+        script_pubkey: &Script,
+    ) -> std::result::Result<(), PolicyViolation> {
+        match script_pubkey.script_type() {
+            ScriptType::NonStandard => Err(PolicyViolation::ScriptPubkey(index)),
+            ScriptType::Multisig => {
+                // OP_m <n KEYS> OP_n OP_CHECKMULTISIG
+                let bytes = script_pubkey.as_bytes();
+                let n_keys = (bytes[bytes.len() - 2] - OP_1 + 1) as usize;
+                if n_keys > self.max_bare_multisig_keys {
+                    return Err(PolicyViolation::ScriptPubkey(index));
+                }
+                if !self.permit_bare_multisig {
+                    return Err(PolicyViolation::BareMultisig(index));
+                }
+                Ok(())
+            }
+            ScriptType::OpReturn
+                if !self.datacarrier || script_pubkey.len() > self.max_datacarrier_bytes =>
+            {
+                Err(PolicyViolation::Datacarrier(index))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // AreInputsStandard FOR ONE INPUT
+    fn check_input(&self, index: usize, input: &Input) -> std::result::Result<(), PolicyViolation> {
+        let script_pubkey = input
+            .prevout
+            .script_pubkey()
+            .map_err(|_| PolicyViolation::NonStandardInput(index))?;
+
+        match script_pubkey.script_type() {
+            ScriptType::NonStandard | ScriptType::WitnessUnknown => {
+                Err(PolicyViolation::NonStandardInput(index))
+            }
+            ScriptType::P2sh => {
+                let redeem_script = input
+                    .redeem_script()
+                    .ok()
+                    .flatten()
+                    .ok_or(PolicyViolation::NonStandardInput(index))?;
+                let sigops = count_sigops(redeem_script.as_bytes(), true);
+                if sigops > self.max_p2sh_sigops {
+                    return Err(PolicyViolation::P2shSigops(index, sigops));
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // IsWitnessStandard FOR ONE INPUT
+    fn check_witness(
+        &self,
+        index: usize,
+        input: &Input,
+    ) -> std::result::Result<(), PolicyViolation> {
+        if input.witness_items().is_empty() {
+            return Ok(());
+        }
+
+        let non_standard = || PolicyViolation::NonStandardWitness(index);
+        let mut witness = input
+            .witness_items()
+            .iter()
+            .map(hex::decode)
+            .collect::<std::result::Result<Vec<Vec<u8>>, _>>()
+            .map_err(|_| non_standard())?;
+
+        // A P2SH INPUT IS A WITNESS INPUT IF ITS REDEEM SCRIPT IS A WITNESS PROGRAM
+        let script_pubkey = input.prevout.script_pubkey().map_err(|_| non_standard())?;
+        let is_p2sh = script_pubkey.script_type() == ScriptType::P2sh;
+        let program_script = match is_p2sh {
+            true => input
+                .redeem_script()
+                .ok()
+                .flatten()
+                .ok_or_else(non_standard)?,
+            false => script_pubkey,
+        };
+
+        match program_script.witness_program() {
+            None => Err(non_standard()),
+            Some((0, program)) if program.len() == 32 => {
+                let witness_script = witness.pop().ok_or_else(non_standard)?;
+                if witness_script.len() > self.max_p2wsh_script_size {
+                    return Err(PolicyViolation::WitnessScriptSize(index));
+                }
+                if witness.len() > self.max_p2wsh_stack_items {
+                    return Err(PolicyViolation::WitnessStackItems(index));
+                }
+                if witness
+                    .iter()
+                    .any(|item| item.len() > self.max_p2wsh_stack_item_size)
+                {
+                    return Err(PolicyViolation::WitnessItemSize(index));
+                }
+                Ok(())
+            }
+            Some((1, program)) if program.len() == 32 && !is_p2sh => {
+                if witness.len() >= 2
+                    && witness.last().and_then(|item| item.first()) == Some(&ANNEX_TAG)
+                {
+                    return Err(non_standard());
+                }
+
+                // A SCRIPT PATH SPEND: <STACK> <SCRIPT> <CONTROL BLOCK>
+                if witness.len() >= 2 {
+                    let control = witness.pop().unwrap_or_default();
+                    witness.pop();
+                    let leaf_version = control.first().map(|byte| byte & TAPROOT_LEAF_MASK);
+                    if leaf_version == Some(TAPROOT_LEAF_TAPSCRIPT)
+                        && witness
+                            .iter()
+                            .any(|item| item.len() > self.max_tapscript_stack_item_size)
+                    {
+                        return Err(PolicyViolation::WitnessItemSize(index));
+                    }
+                }
+                Ok(())
+            }
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::block_mine::serialise_tx::serialise_tx;
+
+    use super::*;
+
+    fn mempool_tx(txid: &str) -> Result<Transaction> {
+        let path = format!("./mempool/{}.json", txid);
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    #[test]
+    fn dust_threshold_test() -> Result<()> {
+        let policy = Policy::default();
+
+        // GetDustThreshold AT 3000 SAT/KVB
+        let thresholds = [
+            (format!("76a914{}88ac", "11".repeat(20)), "p2pkh", 546),
+            (format!("a914{}87", "11".repeat(20)), "p2sh", 540),
+            (format!("0014{}", "11".repeat(20)), "p2wpkh", 294),
+            (format!("0020{}", "11".repeat(32)), "p2wsh", 330),
+            (format!("5120{}", "11".repeat(32)), "p2tr", 330),
+        ];
+        for (script_hex, script_type, threshold) in thresholds.iter() {
+            let script = Script::from_hex(script_hex)?;
+            assert_eq!(script.script_type().to_string(), *script_type);
+            assert_eq!(policy.dust_threshold(&script), *threshold);
+        }
+
+        // THEY FOLLOW THE FEE RATE, UNLESS A TYPE IS GIVEN A FIXED THRESHOLD
+        let p2pkh = Script::from_hex(&thresholds[0].0)?;
+        let p2tr = Script::from_hex(&thresholds[4].0)?;
+        let doubled = Policy {
+            dust_relay_fee: FeeRate::from_sat_per_kvb(6000),
+            dust_thresholds: [("p2tr".to_string(), 1000)].into_iter().collect(),
+            ..Policy::default()
+        };
+        assert_eq!(doubled.dust_threshold(&p2pkh), 1092);
+        assert_eq!(doubled.dust_threshold(&p2tr), 1000);
+
+        assert_eq!(policy.dust_threshold(&Script::from_hex("6a0401020304")?), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn check_tx_test() -> Result<()> {
+        let policy = Policy::default();
+        let tx = mempool_tx("0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65")?;
+        assert_eq!(policy.check_tx(&tx), Ok(()));
+
+        let mut changed = tx.clone();
+        changed.version = 3;
+        assert_eq!(policy.check_tx(&changed), Err(PolicyViolation::Version(3)));
+
+        // EVERY THRESHOLD COMES FROM THE POLICY
+        let strict = Policy {
+            max_tx_weight: tx.weight()? - 1,
+            ..Policy::default()
+        };
+        assert_eq!(
+            strict.check_tx(&tx),
+            Err(PolicyViolation::TxSize(tx.weight()?))
+        );
+
+        let mut changed = tx.clone();
+        changed.vout[0].value = 1;
+        assert_eq!(policy.check_tx(&changed), Err(PolicyViolation::Dust(0, 1)));
+
+        // ONE OP_RETURN IS FINE, A SECOND OR A BIGGER ONE ISN'T
+        let mut changed = tx.clone();
+        let mut op_return = changed.vout[0].clone();
+        op_return.scriptpubkey = format!("6a4c50{}", "00".repeat(80));
+        op_return.value = 0;
+        changed.vout.push(op_return.clone());
+        assert_eq!(policy.check_tx(&changed), Ok(()));
+        changed.vout.push(op_return.clone());
+        assert_eq!(
+            policy.check_tx(&changed),
+            Err(PolicyViolation::MultiOpReturn(2))
+        );
+        changed.vout.pop();
+        op_return.scriptpubkey = format!("6a4c51{}", "00".repeat(81));
+        *changed.vout.last_mut().unwrap() = op_return;
+        let index = changed.vout.len() - 1;
+        assert_eq!(
+            policy.check_tx(&changed),
+            Err(PolicyViolation::Datacarrier(index))
+        );
+
+        // BARE MULTISIG UP TO 3 KEYS, IF IT IS PERMITTED AT ALL
+        let pubkey = format!("21{}", "02".repeat(33));
+        let mut changed = tx.clone();
+        changed.vout[0].scriptpubkey = format!("51{}{}52ae", pubkey, pubkey);
+        assert_eq!(policy.check_tx(&changed), Ok(()));
+        let no_multisig = Policy {
+            permit_bare_multisig: false,
+            ..Policy::default()
+        };
+        assert_eq!(
+            no_multisig.check_tx(&changed),
+            Err(PolicyViolation::BareMultisig(0))
+        );
+        changed.vout[0].scriptpubkey = format!("51{}54ae", pubkey.repeat(4));
+        assert_eq!(
+            policy.check_tx(&changed),
+            Err(PolicyViolation::ScriptPubkey(0))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn witness_standard_test() -> Result<()> {
+        let policy = Policy::default();
+
+        // A P2WSH SPEND HELD TO THE P2WSH WITNESS LIMITS
+        let tx = mempool_tx("0bec1aee6decd078b98553691be92f99ad12271241c6b6f7cf00433954d3f166")?;
+        let index = tx
+            .vin
+            .iter()
+            .position(|input| {
+                input.prevout.script_pubkey().unwrap().script_type() == ScriptType::P2wsh
+            })
+            .unwrap();
+        assert_eq!(policy.check_tx(&tx), Ok(()));
+        assert_eq!(tx.weight()?, serialise_tx(&tx)?.3);
+
+        let mut changed = tx.clone();
+        let witness = changed.vin[index].witness.as_mut().unwrap();
+        witness[0] = "00".repeat(81);
+        assert_eq!(
+            policy.check_tx(&changed),
+            Err(PolicyViolation::WitnessItemSize(index))
+        );
+        let looser = Policy {
+            max_p2wsh_stack_item_size: 81,
+            ..Policy::default()
+        };
+        assert_eq!(looser.check_tx(&changed), Ok(()));
+
+        let witness = changed.vin[index].witness.as_mut().unwrap();
+        witness.splice(0..0, vec![String::new(); 100]);
+        assert_eq!(
+            looser.check_tx(&changed),
+            Err(PolicyViolation::WitnessStackItems(index))
+        );
+
+        // A WITNESS ON A LEGACY INPUT
+        let mut changed = tx.clone();
+        let legacy = changed
+            .vin
+            .iter()
+            .position(|input| input.witness_items().is_empty());
+        if let Some(legacy) = legacy {
+            changed.vin[legacy].witness = Some(vec!["00".to_string()]);
+            assert_eq!(
+                policy.check_tx(&changed),
+                Err(PolicyViolation::NonStandardWitness(legacy))
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_policy_test() -> Result<()> {
+        // A MISSING FILE AND A MISSING FIELD BOTH KEEP THE DEFAULTS
+        assert_eq!(Policy::load("./no-such-policy.json")?, Policy::default());
+
+        let policy: Policy = serde_json::from_str(r#"{"max_version": 3}"#)?;
+        assert_eq!(
+            policy,
+            Policy {
+                max_version: 3,
+                ..Policy::default()
+            }
+        );
+        assert!(serde_json::from_str::<Policy>(r#"{"max_versoin": 3}"#).is_err());
+
+        // THE CHECKED IN policy.json IS THE DEFAULT POLICY
+        assert_eq!(Policy::load(POLICY_FILE)?, Policy::default());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,210 @@
+// WHY THE SCRIPT INTERPRETER STOPPED, ONE CODE PER BROKEN RULE LIKE BITCOIN CORE'S ScriptError
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    // THE SCRIPT RAN TO THE END BUT LEFT FALSE, OR NOTHING, ON TOP
+    EvalFalse,
+    OpReturn,
+
+    // RESOURCE LIMITS
+    ScriptSize,
+    PushSize,
+    OpCount,
+    StackSize,
+    // LEFT MORE THAN ONE ITEM, WITNESS SCRIPTS ALWAYS AND REDEEM SCRIPTS UNDER CLEANSTACK
+    CleanStack,
+
+    // A FAILED *VERIFY OPCODE
+    Verify,
+    EqualVerify,
+    NumEqualVerify,
+    CheckSigVerify,
+    CheckMultisigVerify,
+
+    // MALFORMED SCRIPTS AND OPERANDS
+    BadOpcode,
+    DisabledOpcode,
+    InvalidStackOperation,
+    InvalidAltstackOperation,
+    UnbalancedConditional,
+    MinimalIf,
+    MinimalData,
+    SigPushOnly,
+    NumOverflow,
+    UnsatisfiedLocktime,
+    DiscourageUpgradableNops,
+    OpCodeSeparator,
+    SigFindAndDelete,
+
+    // CHECKMULTISIG
+    PubkeyCount,
+    SigCount,
+    SigNullDummy,
+    SigNullFail,
+
+    // ENCODING RULES FOR SIGNATURES AND PUBLIC KEYS
+    SigDer,
+    SigHighS,
+    SigHashType,
+    PubkeyType,
+    WitnessPubkeyType,
+
+    // TAPROOT AND TAPSCRIPT
+    WitnessProgramWitnessEmpty,
+    WitnessProgramMismatch,
+    TaprootWrongControlSize,
+    SchnorrSigSize,
+    SchnorrSigHashType,
+    SchnorrSig,
+    TapscriptValidationWeight,
+    TapscriptCheckMultisig,
+    TapscriptMinimalIf,
+    DiscourageUpgradableTaprootVersion,
+    DiscourageOpSuccess,
+    DiscourageUpgradablePubkeyType,
+
+    // THE SIGHASH OF A SIGNATURE COULD NOT BE BUILT
+    SigHash(String),
+}
+
+impl ScriptError {
+    // BROKE ONE OF THE ENCODING RULES FOR SIGNATURES AND PUBLIC KEYS
+    pub fn is_encoding(&self) -> bool {
+        matches!(
+            self,
+            ScriptError::SigDer
+                | ScriptError::SigHighS
+                | ScriptError::SigHashType
+                | ScriptError::PubkeyType
+                | ScriptError::WitnessPubkeyType
+        )
+    }
+
+    // A SHORT LABEL TO GROUP REJECTED TXS BY
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptError::EvalFalse => "EVAL FALSE",
+            ScriptError::OpReturn => "OP_RETURN",
+            ScriptError::ScriptSize => "SCRIPT SIZE",
+            ScriptError::PushSize => "PUSH SIZE",
+            ScriptError::OpCount => "OP COUNT",
+            ScriptError::StackSize => "STACK SIZE",
+            ScriptError::CleanStack => "CLEANSTACK",
+            ScriptError::Verify => "VERIFY",
+            ScriptError::EqualVerify => "EQUALVERIFY",
+            ScriptError::NumEqualVerify => "NUMEQUALVERIFY",
+            ScriptError::CheckSigVerify => "CHECKSIGVERIFY",
+            ScriptError::CheckMultisigVerify => "CHECKMULTISIGVERIFY",
+            ScriptError::BadOpcode => "BAD OPCODE",
+            ScriptError::DisabledOpcode => "DISABLED OPCODE",
+            ScriptError::InvalidStackOperation => "INVALID STACK OPERATION",
+            ScriptError::InvalidAltstackOperation => "INVALID ALTSTACK OPERATION",
+            ScriptError::UnbalancedConditional => "UNBALANCED CONDITIONAL",
+            ScriptError::MinimalIf => "MINIMALIF",
+            ScriptError::MinimalData => "MINIMALDATA",
+            ScriptError::SigPushOnly => "SIG PUSHONLY",
+            ScriptError::NumOverflow => "NUM OVERFLOW",
+            ScriptError::UnsatisfiedLocktime => "UNSATISFIED LOCKTIME",
+            ScriptError::DiscourageUpgradableNops => "DISCOURAGE UPGRADABLE NOPS",
+            ScriptError::OpCodeSeparator => "OP_CODESEPARATOR",
+            ScriptError::SigFindAndDelete => "SIG FINDANDDELETE",
+            ScriptError::PubkeyCount => "PUBKEY COUNT",
+            ScriptError::SigCount => "SIG COUNT",
+            ScriptError::SigNullDummy => "SIG NULLDUMMY",
+            ScriptError::SigNullFail => "SIG NULLFAIL",
+            ScriptError::SigDer => "SIG DER",
+            ScriptError::SigHighS => "SIG HIGH S",
+            ScriptError::SigHashType => "SIG HASHTYPE",
+            ScriptError::PubkeyType => "PUBKEYTYPE",
+            ScriptError::WitnessPubkeyType => "WITNESS PUBKEYTYPE",
+            ScriptError::WitnessProgramWitnessEmpty => "WITNESS PROGRAM WITNESS EMPTY",
+            ScriptError::WitnessProgramMismatch => "WITNESS PROGRAM MISMATCH",
+            ScriptError::TaprootWrongControlSize => "TAPROOT WRONG CONTROL SIZE",
+            ScriptError::SchnorrSigSize => "SCHNORR SIG SIZE",
+            ScriptError::SchnorrSigHashType => "SCHNORR SIG HASHTYPE",
+            ScriptError::SchnorrSig => "SCHNORR SIG",
+            ScriptError::TapscriptValidationWeight => "TAPSCRIPT VALIDATION WEIGHT",
+            ScriptError::TapscriptCheckMultisig => "TAPSCRIPT CHECKMULTISIG",
+            ScriptError::TapscriptMinimalIf => "TAPSCRIPT MINIMALIF",
+            ScriptError::DiscourageUpgradableTaprootVersion => {
+                "DISCOURAGE UPGRADABLE TAPROOT VERSION"
+            }
+            ScriptError::DiscourageOpSuccess => "DISCOURAGE OP_SUCCESS",
+            ScriptError::DiscourageUpgradablePubkeyType => "DISCOURAGE UPGRADABLE PUBKEYTYPE",
+            ScriptError::SigHash(_) => "SIGHASH",
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            ScriptError::EvalFalse => "SCRIPT EVALUATED WITHOUT ERROR BUT FINISHED WITH FALSE",
+            ScriptError::OpReturn => "OP_RETURN WAS ENCOUNTERED",
+            ScriptError::ScriptSize => "SCRIPT IS TOO BIG",
+            ScriptError::PushSize => "PUSH VALUE SIZE LIMIT EXCEEDED",
+            ScriptError::OpCount => "OPERATION LIMIT EXCEEDED",
+            ScriptError::StackSize => "STACK SIZE LIMIT EXCEEDED",
+            ScriptError::CleanStack => "STACK SIZE MUST BE EXACTLY ONE AFTER EXECUTION",
+            ScriptError::Verify => "SCRIPT FAILED AN OP_VERIFY OPERATION",
+            ScriptError::EqualVerify => "SCRIPT FAILED AN OP_EQUALVERIFY OPERATION",
+            ScriptError::NumEqualVerify => "SCRIPT FAILED AN OP_NUMEQUALVERIFY OPERATION",
+            ScriptError::CheckSigVerify => "SCRIPT FAILED AN OP_CHECKSIGVERIFY OPERATION",
+            ScriptError::CheckMultisigVerify => "SCRIPT FAILED AN OP_CHECKMULTISIGVERIFY OPERATION",
+            ScriptError::BadOpcode => "OPCODE MISSING OR NOT UNDERSTOOD",
+            ScriptError::DisabledOpcode => "ATTEMPTED TO USE A DISABLED OPCODE",
+            ScriptError::InvalidStackOperation => "OPERATION NOT VALID WITH THE CURRENT STACK SIZE",
+            ScriptError::InvalidAltstackOperation => {
+                "OPERATION NOT VALID WITH THE CURRENT ALTSTACK SIZE"
+            }
+            ScriptError::UnbalancedConditional => "INVALID OP_IF CONSTRUCTION",
+            ScriptError::MinimalIf => "OP_IF/NOTIF ARGUMENT MUST BE MINIMAL",
+            ScriptError::MinimalData => "DATA PUSH LARGER THAN NECESSARY",
+            ScriptError::SigPushOnly => "ONLY PUSH OPERATORS ALLOWED IN SIGNATURES",
+            ScriptError::NumOverflow => "NUMERIC OPERAND LONGER THAN 4 BYTES",
+            ScriptError::UnsatisfiedLocktime => "LOCKTIME REQUIREMENT NOT SATISFIED",
+            ScriptError::DiscourageUpgradableNops => "NOPX RESERVED FOR SOFT-FORK UPGRADES",
+            ScriptError::OpCodeSeparator => "USING OP_CODESEPARATOR IN NON-WITNESS SCRIPT",
+            ScriptError::SigFindAndDelete => "SIGNATURE IS FOUND IN SCRIPTCODE",
+            ScriptError::PubkeyCount => "PUBKEY COUNT NEGATIVE OR LIMIT EXCEEDED",
+            ScriptError::SigCount => "SIGNATURE COUNT NEGATIVE OR GREATER THAN PUBKEY COUNT",
+            ScriptError::SigNullDummy => "DUMMY CHECKMULTISIG ARGUMENT MUST BE ZERO",
+            ScriptError::SigNullFail => {
+                "SIGNATURE MUST BE ZERO FOR FAILED CHECK(MULTI)SIG OPERATION"
+            }
+            ScriptError::SigDer => "NON-CANONICAL DER SIGNATURE",
+            ScriptError::SigHighS => "NON-CANONICAL SIGNATURE: S VALUE IS UNNECESSARILY HIGH",
+            ScriptError::SigHashType => "SIGNATURE HASH TYPE MISSING OR NOT UNDERSTOOD",
+            ScriptError::PubkeyType => "PUBLIC KEY IS NEITHER COMPRESSED OR UNCOMPRESSED",
+            ScriptError::WitnessPubkeyType => "USING NON-COMPRESSED KEYS IN SEGWIT",
+            ScriptError::WitnessProgramWitnessEmpty => {
+                "WITNESS PROGRAM WAS PASSED AN EMPTY WITNESS"
+            }
+            ScriptError::WitnessProgramMismatch => "WITNESS PROGRAM HASH MISMATCH",
+            ScriptError::TaprootWrongControlSize => "INVALID TAPROOT CONTROL BLOCK SIZE",
+            ScriptError::SchnorrSigSize => "INVALID SCHNORR SIGNATURE SIZE",
+            ScriptError::SchnorrSigHashType => "INVALID SCHNORR SIGNATURE HASH TYPE",
+            ScriptError::SchnorrSig => "INVALID SCHNORR SIGNATURE",
+            ScriptError::TapscriptValidationWeight => {
+                "TOO MUCH SIGNATURE VALIDATION RELATIVE TO WITNESS WEIGHT"
+            }
+            ScriptError::TapscriptCheckMultisig => {
+                "OP_CHECKMULTISIG(VERIFY) IS NOT AVAILABLE IN TAPSCRIPT"
+            }
+            ScriptError::TapscriptMinimalIf => "OP_IF/NOTIF ARGUMENT MUST BE MINIMAL IN TAPSCRIPT",
+            ScriptError::DiscourageUpgradableTaprootVersion => {
+                "TAPROOT VERSION RESERVED FOR SOFT-FORK UPGRADES"
+            }
+            ScriptError::DiscourageOpSuccess => "OP_SUCCESSX RESERVED FOR SOFT-FORK UPGRADES",
+            ScriptError::DiscourageUpgradablePubkeyType => {
+                "PUBLIC KEY VERSION RESERVED FOR SOFT-FORK UPGRADES"
+            }
+            ScriptError::SigHash(error) => return write!(f, "SIGHASH FAILED: {}", error),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
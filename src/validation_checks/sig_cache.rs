@@ -0,0 +1,262 @@
+// SIGNATURES ALREADY FOUND VALID, SO A TX CHECKED FOR THE MEMPOOL ISN'T CHECKED AGAIN IN A BLOCK
+// LIKE BITCOIN CORE'S SIGNATURE CACHE: ONLY VALID SIGNATURES ARE STORED, AND IT IS BOUNDED
+
+use std::{
+    cell::Cell,
+    collections::{HashSet, VecDeque},
+    fmt,
+    sync::{Mutex, MutexGuard, OnceLock},
+};
+
+use crate::validation_checks::single_sha256;
+
+// ENOUGH FOR EVERY SIGNATURE OF A FULL MEMPOOL, AT 32 BYTES A KEY
+pub const DEFAULT_MAX_ENTRIES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigKind {
+    Ecdsa,
+    Schnorr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    pub fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    // THE LOOKUPS MADE AFTER `earlier` WAS TAKEN
+    pub fn since(&self, earlier: CacheStats) -> CacheStats {
+        CacheStats {
+            hits: self.hits - earlier.hits,
+            misses: self.misses - earlier.misses,
+            evictions: self.evictions - earlier.evictions,
+        }
+    }
+
+    fn record(&mut self, found: bool) {
+        match found {
+            true => self.hits += 1,
+            false => self.misses += 1,
+        }
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        match self.lookups() {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} HITS / {} LOOKUPS ({:.1}%), {} EVICTIONS",
+            self.hits,
+            self.lookups(),
+            self.hit_rate() * 100.0,
+            self.evictions
+        )
+    }
+}
+
+// THE OLDEST ENTRY IS DROPPED ONCE `max_entries` IS REACHED
+#[derive(Debug)]
+pub struct SignatureCache {
+    entries: HashSet<[u8; 32]>,
+    order: VecDeque<[u8; 32]>,
+    max_entries: usize,
+    pub stats: CacheStats,
+}
+
+impl SignatureCache {
+    pub fn new(max_entries: usize) -> Self {
+        SignatureCache {
+            entries: HashSet::new(),
+            order: VecDeque::new(),
+            max_entries,
+            stats: CacheStats::default(),
+        }
+    }
+
+    // THE SIGHASH COMMITS TO THE TX AND THE INPUT, SO THE SAME SIGNATURE ELSEWHERE IS A MISS
+    fn key(kind: SigKind, sighash: &[u8], pubkey: &[u8], sig: &[u8]) -> [u8; 32] {
+        let mut preimage = vec![kind as u8];
+        for part in [sighash, pubkey, sig] {
+            preimage.extend((part.len() as u32).to_le_bytes());
+            preimage.extend_from_slice(part);
+        }
+
+        let mut key = [0u8; 32];
+        key.copy_from_slice(&single_sha256(&preimage));
+        key
+    }
+
+    pub fn contains(&mut self, kind: SigKind, sighash: &[u8], pubkey: &[u8], sig: &[u8]) -> bool {
+        let found = self
+            .entries
+            .contains(&Self::key(kind, sighash, pubkey, sig));
+        self.stats.record(found);
+        THREAD_STATS.with(|stats| {
+            let mut thread_stats = stats.get();
+            thread_stats.record(found);
+            stats.set(thread_stats);
+        });
+        found
+    }
+
+    pub fn insert(&mut self, kind: SigKind, sighash: &[u8], pubkey: &[u8], sig: &[u8]) {
+        if self.max_entries == 0 {
+            return;
+        }
+
+        let key = Self::key(kind, sighash, pubkey, sig);
+        if !self.entries.insert(key) {
+            return;
+        }
+        self.order.push_back(key);
+
+        while self.order.len() > self.max_entries {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+                self.stats.evictions += 1;
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+// ONE CACHE FOR THE WHOLE PROCESS, SHARED BY MEMPOOL AND BLOCK VALIDATION
+static SIGNATURE_CACHE: OnceLock<Mutex<SignatureCache>> = OnceLock::new();
+
+thread_local! {
+    // THE LOOKUPS OF ONE THREAD, SO IT CAN MEASURE ITS OWN WORK WHILE OTHERS SHARE THE CACHE
+    static THREAD_STATS: Cell<CacheStats> = Cell::new(CacheStats::default());
+}
+
+pub fn signature_cache() -> MutexGuard<'static, SignatureCache> {
+    SIGNATURE_CACHE
+        .get_or_init(|| Mutex::new(SignatureCache::new(DEFAULT_MAX_ENTRIES)))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub fn thread_stats() -> CacheStats {
+    THREAD_STATS.with(Cell::get)
+}
+
+// RUNS `verify` ONLY IF THE SIGNATURE ISN'T CACHED, AND CACHES IT IF IT IS VALID
+// THE LOCK ISN'T HELD WHILE VERIFYING
+pub fn verify_cached(
+    kind: SigKind,
+    sighash: &[u8],
+    pubkey: &[u8],
+    sig: &[u8],
+    verify: impl FnOnce() -> bool,
+) -> bool {
+    if signature_cache().contains(kind, sighash, pubkey, sig) {
+        return true;
+    }
+
+    let valid = verify();
+    if valid {
+        signature_cache().insert(kind, sighash, pubkey, sig);
+    }
+    valid
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::{
+        error::Result,
+        transaction::Transaction,
+        validation_checks::{taproot::SchnorrMode, verify_flags::VerifyFlags, verify_tx},
+    };
+
+    use super::*;
+
+    #[test]
+    fn signature_cache_test() {
+        let mut cache = SignatureCache::new(2);
+        let sighash = [0x11; 32];
+
+        assert!(!cache.contains(SigKind::Ecdsa, &sighash, &[0x02], &[0x30]));
+        cache.insert(SigKind::Ecdsa, &sighash, &[0x02], &[0x30]);
+        assert!(cache.contains(SigKind::Ecdsa, &sighash, &[0x02], &[0x30]));
+
+        // THE SAME BYTES AS A SCHNORR SIGNATURE, OR UNDER ANOTHER SIGHASH, ARE A DIFFERENT ENTRY
+        assert!(!cache.contains(SigKind::Schnorr, &sighash, &[0x02], &[0x30]));
+        assert!(!cache.contains(SigKind::Ecdsa, &[0x22; 32], &[0x02], &[0x30]));
+        // SPLITTING THE SAME BYTES DIFFERENTLY IS TOO
+        assert!(!cache.contains(SigKind::Ecdsa, &sighash, &[], &[0x02, 0x30]));
+
+        // THE OLDEST ENTRY MAKES ROOM
+        cache.insert(SigKind::Ecdsa, &sighash, &[0x03], &[0x30]);
+        cache.insert(SigKind::Ecdsa, &sighash, &[0x03], &[0x30]);
+        cache.insert(SigKind::Schnorr, &sighash, &[0x02], &[0x30]);
+        assert_eq!(cache.len(), 2);
+        assert!(!cache.contains(SigKind::Ecdsa, &sighash, &[0x02], &[0x30]));
+        assert!(cache.contains(SigKind::Schnorr, &sighash, &[0x02], &[0x30]));
+
+        assert_eq!(
+            cache.stats,
+            CacheStats {
+                hits: 2,
+                misses: 5,
+                evictions: 1
+            }
+        );
+        assert_eq!(cache.stats.lookups(), 7);
+    }
+
+    #[test]
+    fn verify_cached_test() -> Result<()> {
+        // ONLY A VALID SIGNATURE IS CACHED
+        let sighash = [0x33; 32];
+        assert!(!verify_cached(SigKind::Schnorr, &sighash, &[], &[], || {
+            false
+        }));
+        assert!(verify_cached(SigKind::Schnorr, &sighash, &[], &[], || true));
+        assert!(verify_cached(SigKind::Schnorr, &sighash, &[], &[], || {
+            unreachable!()
+        }));
+
+        // A TX VALIDATED AGAIN, AS IN A BLOCK, FINDS ITS SIGNATURES IN THE CACHE
+        let path =
+            "./mempool/0a5d6ddc87a9246297c1038d873eec419f04301197d67b9854fa2679dbe3bd65.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        assert!(verify_tx(
+            tx.clone(),
+            VerifyFlags::STANDARD,
+            SchnorrMode::Serial
+        )?);
+
+        let before = signature_cache().stats;
+        assert!(verify_tx(
+            tx.clone(),
+            VerifyFlags::CONSENSUS,
+            SchnorrMode::Serial
+        )?);
+        let after = signature_cache().stats;
+        assert!(after.hits >= before.hits + tx.vin.len() as u64);
+
+        Ok(())
+    }
+}
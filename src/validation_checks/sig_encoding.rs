@@ -0,0 +1,215 @@
+// ENCODING RULES FOR THE SIGNATURES AND PUBLIC KEYS GIVEN TO OP_CHECKSIG AND OP_CHECKMULTISIG
+// WHICH ONES APPLY DEPENDS ON THE VERIFY FLAGS, AS IN BITCOIN CORE'S CheckSignatureEncoding
+
+use secp256k1::ecdsa::Signature;
+
+use crate::validation_checks::{
+    interpreter::ScriptResult, script_error::ScriptError, verify_flags::VerifyFlags,
+};
+
+// SIGHASH_ALL, SIGHASH_NONE AND SIGHASH_SINGLE, WITH OR WITHOUT SIGHASH_ANYONECANPAY
+pub const SIGHASH_ALL: u8 = 0x01;
+pub const SIGHASH_NONE: u8 = 0x02;
+pub const SIGHASH_SINGLE: u8 = 0x03;
+pub const SIGHASH_ANYONECANPAY: u8 = 0x80;
+
+// BIP66: 0x30 [TOTAL LEN] 0x02 [R LEN] [R] 0x02 [S LEN] [S] [SIGHASH]
+// R AND S ARE POSITIVE, NON EMPTY AND WITHOUT NEEDLESS ZERO PADDING
+pub fn is_valid_signature_encoding(sig: &[u8]) -> bool {
+    if sig.len() < 9 || sig.len() > 73 {
+        return false;
+    }
+    if sig[0] != 0x30 || sig[1] as usize != sig.len() - 3 {
+        return false;
+    }
+
+    let len_r = sig[3] as usize;
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+    let len_s = sig[5 + len_r] as usize;
+    if len_r + len_s + 7 != sig.len() {
+        return false;
+    }
+
+    // R
+    if sig[2] != 0x02 || len_r == 0 || sig[4] & 0x80 != 0 {
+        return false;
+    }
+    if len_r > 1 && sig[4] == 0x00 && sig[5] & 0x80 == 0 {
+        return false;
+    }
+
+    // S
+    if sig[len_r + 4] != 0x02 || len_s == 0 || sig[len_r + 6] & 0x80 != 0 {
+        return false;
+    }
+    if len_s > 1 && sig[len_r + 6] == 0x00 && sig[len_r + 7] & 0x80 == 0 {
+        return false;
+    }
+
+    true
+}
+
+// A HIGH S CAN BE FLIPPED BY ANYONE, WHICH CHANGES THE TXID
+pub fn is_low_s(sig: &[u8]) -> bool {
+    let der = &sig[..sig.len().saturating_sub(1)];
+    match Signature::from_der_lax(der) {
+        Ok(signature) => {
+            let mut normalized = signature;
+            normalized.normalize_s();
+            normalized == signature
+        }
+        Err(_) => false,
+    }
+}
+
+pub fn is_defined_hashtype(sig: &[u8]) -> bool {
+    match sig.last() {
+        Some(sighash_type) => {
+            (SIGHASH_ALL..=SIGHASH_SINGLE).contains(&(sighash_type & !SIGHASH_ANYONECANPAY))
+        }
+        None => false,
+    }
+}
+
+// 33 BYTES STARTING 0x02 / 0x03, OR 65 BYTES STARTING 0x04
+pub fn is_compressed_or_uncompressed_pubkey(pubkey: &[u8]) -> bool {
+    match pubkey.first() {
+        Some(0x02 | 0x03) => pubkey.len() == 33,
+        Some(0x04) => pubkey.len() == 65,
+        _ => false,
+    }
+}
+
+pub fn is_compressed_pubkey(pubkey: &[u8]) -> bool {
+    pubkey.len() == 33 && matches!(pubkey[0], 0x02 | 0x03)
+}
+
+// THE INPUT TYPES WHOSE SIGNATURES ARE CHECKED BY A SEGWIT V0 SCRIPT
+pub fn is_witness_v0(input_type: &str) -> bool {
+    matches!(
+        input_type,
+        "P2WPKH" | "P2WSH" | "P2SH-P2WPKH" | "P2SH-P2WSH"
+    )
+}
+
+// AN EMPTY SIGNATURE IS ALWAYS WELL FORMED, IT IS HOW A SCRIPT SAYS NO
+pub fn check_signature_encoding(sig: &[u8], flags: VerifyFlags) -> ScriptResult<()> {
+    if sig.is_empty() {
+        return Ok(());
+    }
+    let der_rules = VerifyFlags::DERSIG | VerifyFlags::LOW_S | VerifyFlags::STRICTENC;
+    if flags.intersects(der_rules) && !is_valid_signature_encoding(sig) {
+        return Err(ScriptError::SigDer);
+    }
+    if flags.contains(VerifyFlags::LOW_S) && !is_low_s(sig) {
+        return Err(ScriptError::SigHighS);
+    }
+    if flags.contains(VerifyFlags::STRICTENC) && !is_defined_hashtype(sig) {
+        return Err(ScriptError::SigHashType);
+    }
+    Ok(())
+}
+
+// `witness_v0`: THE KEY IS USED BY A SEGWIT V0 SCRIPT
+pub fn check_pubkey_encoding(
+    pubkey: &[u8],
+    flags: VerifyFlags,
+    witness_v0: bool,
+) -> ScriptResult<()> {
+    if flags.contains(VerifyFlags::STRICTENC) && !is_compressed_or_uncompressed_pubkey(pubkey) {
+        return Err(ScriptError::PubkeyType);
+    }
+    if flags.contains(VerifyFlags::WITNESS_PUBKEYTYPE)
+        && witness_v0
+        && !is_compressed_pubkey(pubkey)
+    {
+        return Err(ScriptError::WitnessPubkeyType);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A LOW S SIGNATURE FROM THE MEMPOOL, WITH SIGHASH_ALL
+    const SIG: &str = "304402205112f96bf7423703c221976603307f0e33913d39efc3344d68376fd2b8c0bd20022003ea588c06fa1a3e262e07ed6bf01a36f78741fe7bc6a91ff43c38a0a14e43fe01";
+
+    #[test]
+    fn signature_encoding_test() {
+        let sig = hex::decode(SIG).unwrap();
+        assert_eq!(
+            check_signature_encoding(&sig, VerifyFlags::STANDARD),
+            Ok(())
+        );
+        assert_eq!(check_signature_encoding(&[], VerifyFlags::STANDARD), Ok(()));
+
+        // A PADDED R IS NOT STRICT DER, BUT ONLY FAILS WHEN A DER RULE IS ON
+        let mut padded = hex::decode(SIG).unwrap();
+        padded.insert(4, 0x00);
+        padded[3] += 1;
+        padded[1] += 1;
+        assert!(!is_valid_signature_encoding(&padded));
+        assert_eq!(
+            check_signature_encoding(&padded, VerifyFlags::CONSENSUS),
+            Err(ScriptError::SigDer)
+        );
+        assert_eq!(check_signature_encoding(&padded, VerifyFlags::NONE), Ok(()));
+
+        // S REPLACED BY n - S
+        let s = "03ea588c06fa1a3e262e07ed6bf01a36f78741fe7bc6a91ff43c38a0a14e43fe";
+        let high_s = "00fc15a773f905e5c1d9d1f812940fe5c7c3279ae83381f71bcb9625ec2ee7fd43";
+        let high = SIG.replace(&format!("0220{}", s), &format!("0221{}", high_s));
+        let high = hex::decode(high.replacen("3044", "3045", 1)).unwrap();
+        assert!(is_valid_signature_encoding(&high));
+        assert_eq!(
+            check_signature_encoding(&high, VerifyFlags::CONSENSUS),
+            Ok(())
+        );
+        assert_eq!(
+            check_signature_encoding(&high, VerifyFlags::STANDARD),
+            Err(ScriptError::SigHighS)
+        );
+
+        // SIGHASH TYPE 0x04 IS UNDEFINED, 0x83 IS SIGHASH_SINGLE | SIGHASH_ANYONECANPAY
+        let mut undefined = sig.clone();
+        *undefined.last_mut().unwrap() = 0x04;
+        assert_eq!(
+            check_signature_encoding(&undefined, VerifyFlags::STANDARD),
+            Err(ScriptError::SigHashType)
+        );
+        *undefined.last_mut().unwrap() = 0x83;
+        assert_eq!(
+            check_signature_encoding(&undefined, VerifyFlags::STANDARD),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn pubkey_encoding_test() {
+        let compressed =
+            hex::decode("03bf68f1ce783df58a2459d549d5c655a1edc0f0cf4d79421fe978d358d79ee42a")
+                .unwrap();
+        let mut uncompressed = vec![0x04];
+        uncompressed.extend([0x11; 64]);
+        let hybrid = [vec![0x06], uncompressed[1..].to_vec()].concat();
+
+        let flags = VerifyFlags::STANDARD;
+        assert_eq!(check_pubkey_encoding(&compressed, flags, true), Ok(()));
+        assert_eq!(check_pubkey_encoding(&uncompressed, flags, false), Ok(()));
+        assert_eq!(
+            check_pubkey_encoding(&uncompressed, flags, true),
+            Err(ScriptError::WitnessPubkeyType)
+        );
+        assert_eq!(
+            check_pubkey_encoding(&hybrid, flags, false),
+            Err(ScriptError::PubkeyType)
+        );
+        assert_eq!(
+            check_pubkey_encoding(&hybrid, VerifyFlags::CONSENSUS, true),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,104 @@
+// THE PARTS OF A SIGHASH THAT ARE THE SAME FOR EVERY INPUT, HASHED ONCE PER TRANSACTION
+// LIKE BITCOIN CORE'S PrecomputedTransactionData, SO SIGNING N INPUTS IS LINEAR IN N
+
+use crate::{
+    block_mine::serialise_tx::compact_size, error::Result, transaction::Transaction,
+    validation_checks::single_sha256,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SighashCache {
+    // BIP341: SINGLE SHA256 OF THE SERIALISED FIELDS OF EVERY INPUT AND OUTPUT
+    pub sha_prevouts: Vec<u8>,
+    pub sha_amounts: Vec<u8>,
+    pub sha_scriptpubkeys: Vec<u8>,
+    pub sha_sequences: Vec<u8>,
+    pub sha_outputs: Vec<u8>,
+
+    // BIP143: THE SAME HASHES, HASHED A SECOND TIME
+    pub hash_prevouts: Vec<u8>,
+    pub hash_sequence: Vec<u8>,
+    pub hash_outputs: Vec<u8>,
+}
+
+impl SighashCache {
+    pub fn new(tx: &Transaction) -> Result<Self> {
+        let mut prevouts: Vec<u8> = Vec::new();
+        let mut amounts: Vec<u8> = Vec::new();
+        let mut scriptpubkeys: Vec<u8> = Vec::new();
+        let mut sequences: Vec<u8> = Vec::new();
+        for input in tx.vin.iter() {
+            prevouts.extend_from_slice(&input.outpoint().consensus_bytes());
+            amounts.extend(input.prevout.value.to_le_bytes());
+
+            let script_pubkey = input.prevout.script_pubkey()?;
+            scriptpubkeys.extend(compact_size(script_pubkey.as_bytes().len() as u64));
+            scriptpubkeys.extend_from_slice(script_pubkey.as_bytes());
+
+            sequences.extend(input.sequence.to_le_bytes());
+        }
+
+        let mut outputs: Vec<u8> = Vec::new();
+        for output in tx.vout.iter() {
+            let script_pubkey = output.script_pubkey()?;
+            outputs.extend(output.value.to_le_bytes());
+            outputs.extend(compact_size(script_pubkey.as_bytes().len() as u64));
+            outputs.extend_from_slice(script_pubkey.as_bytes());
+        }
+
+        let sha_prevouts = single_sha256(&prevouts);
+        let sha_sequences = single_sha256(&sequences);
+        let sha_outputs = single_sha256(&outputs);
+
+        Ok(SighashCache {
+            hash_prevouts: single_sha256(&sha_prevouts),
+            hash_sequence: single_sha256(&sha_sequences),
+            hash_outputs: single_sha256(&sha_outputs),
+            sha_prevouts,
+            sha_amounts: single_sha256(&amounts),
+            sha_scriptpubkeys: single_sha256(&scriptpubkeys),
+            sha_sequences,
+            sha_outputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::validation_checks::double_sha256;
+
+    use super::*;
+
+    #[test]
+    fn sighash_cache_test() -> Result<()> {
+        let path =
+            "./mempool/0bec1aee6decd078b98553691be92f99ad12271241c6b6f7cf00433954d3f166.json";
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let cache = SighashCache::new(&tx)?;
+
+        // BIP143 HASHES ARE HASH256 OF THE SERIALISED FIELDS
+        let mut sequences: Vec<u8> = Vec::new();
+        for input in tx.vin.iter() {
+            sequences.extend(input.sequence.to_le_bytes());
+        }
+        assert_eq!(cache.hash_sequence, double_sha256(&sequences));
+        assert_eq!(cache.sha_sequences, single_sha256(&sequences));
+
+        let mut amounts: Vec<u8> = Vec::new();
+        for input in tx.vin.iter() {
+            amounts.extend(input.prevout.value.to_le_bytes());
+        }
+        assert_eq!(cache.sha_amounts, single_sha256(&amounts));
+
+        // EVERY INPUT AND OUTPUT IS COMMITTED TO
+        let mut changed = tx.clone();
+        changed.vout[0].value += 1;
+        let changed = SighashCache::new(&changed)?;
+        assert_ne!(changed.hash_outputs, cache.hash_outputs);
+        assert_eq!(changed.hash_prevouts, cache.hash_prevouts);
+
+        Ok(())
+    }
+}
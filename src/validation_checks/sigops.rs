@@ -0,0 +1,167 @@
+use crate::{
+    error::Result,
+    script::{
+        OP_1, OP_16, OP_CHECKMULTISIG, OP_CHECKMULTISIGVERIFY, OP_CHECKSIG, OP_CHECKSIGVERIFY,
+    },
+    transaction::Transaction,
+    validation_checks::interpreter::MAX_PUBKEYS_PER_MULTISIG,
+};
+
+// CONSENSUS LIMIT ON THE SIGOP COST OF A BLOCK
+pub const MAX_BLOCK_SIGOPS_COST: usize = 80000;
+
+// LEGACY AND P2SH SIGOPS COST 4, WITNESS SIGOPS COST 1
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+// SPLITS A SCRIPT INTO (OPCODE, PUSHED DATA), STOPS AT A TRUNCATED PUSH
+pub fn script_ops(script: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut ops: Vec<(u8, Vec<u8>)> = Vec::new();
+    let mut index = 0;
+
+    while index < script.len() {
+        let opcode = script[index];
+        index += 1;
+
+        let push_len = match opcode {
+            1..=75 => opcode as usize,
+            // OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4
+            76..=78 => {
+                let size_len = 1 << (opcode - 76);
+                if index + size_len > script.len() {
+                    break;
+                }
+                let mut size_bytes = [0u8; 4];
+                size_bytes[..size_len].copy_from_slice(&script[index..index + size_len]);
+                index += size_len;
+                u32::from_le_bytes(size_bytes) as usize
+            }
+            _ => 0,
+        };
+
+        if index + push_len > script.len() {
+            break;
+        }
+
+        ops.push((opcode, script[index..index + push_len].to_vec()));
+        index += push_len;
+    }
+
+    ops
+}
+
+// GetSigOpCount: CHECKMULTISIG COUNTS AS 20 UNLESS `accurate` AND PRECEDED BY OP_1..OP_16
+pub fn count_sigops(script: &[u8], accurate: bool) -> usize {
+    let mut count = 0;
+    let mut last_opcode = 0xff;
+
+    for (opcode, _) in script_ops(script) {
+        match opcode {
+            OP_CHECKSIG | OP_CHECKSIGVERIFY => count += 1,
+            OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+                if accurate && (OP_1..=OP_16).contains(&last_opcode) {
+                    count += (last_opcode - OP_1 + 1) as usize;
+                } else {
+                    count += MAX_PUBKEYS_PER_MULTISIG;
+                }
+            }
+            _ => {}
+        }
+        last_opcode = opcode;
+    }
+
+    count
+}
+
+// THE LAST PUSH OF A PUSH-ONLY SCRIPT SIG IS THE P2SH REDEEM SCRIPT
+fn redeem_script(script_sig: &[u8]) -> Option<Vec<u8>> {
+    let ops = script_ops(script_sig);
+    if ops.iter().any(|(opcode, _)| *opcode > 0x60) {
+        return None;
+    }
+    ops.last().map(|(_, data)| data.clone())
+}
+
+// SIGOPS OF A SEGWIT V0 PROGRAM: 1 FOR P2WPKH, THE WITNESS SCRIPT'S COUNT FOR P2WSH
+fn witness_sigops(program: &[u8], witness: &[Vec<u8>]) -> usize {
+    if program.len() < 4 || program[0] != 0x00 || program[1] as usize != program.len() - 2 {
+        return 0;
+    }
+
+    match program.len() - 2 {
+        20 => 1,
+        32 => witness
+            .last()
+            .map(|script| count_sigops(script, true))
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+// GetTransactionSigOpCost FOR A TRANSACTION WITH ITS PREVOUTS
+pub fn tx_sigop_cost(tx: &Transaction) -> Result<usize> {
+    let mut cost = 0;
+
+    for output in tx.vout.iter() {
+        cost += count_sigops(&hex::decode(&output.scriptpubkey)?, false) * WITNESS_SCALE_FACTOR;
+    }
+
+    for input in tx.vin.iter() {
+        let script_sig = hex::decode(input.scriptsig.clone().unwrap_or_default())?;
+        let scriptpubkey = hex::decode(&input.prevout.scriptpubkey)?;
+        let witness = input
+            .witness
+            .clone()
+            .unwrap_or_default()
+            .iter()
+            .map(hex::decode)
+            .collect::<std::result::Result<Vec<Vec<u8>>, _>>()?;
+
+        cost += count_sigops(&script_sig, false) * WITNESS_SCALE_FACTOR;
+
+        // P2SH: OP_HASH160 <20 BYTES> OP_EQUAL
+        let is_p2sh = scriptpubkey.len() == 23
+            && scriptpubkey[0] == 0xa9
+            && scriptpubkey[1] == 0x14
+            && scriptpubkey[22] == 0x87;
+
+        if is_p2sh {
+            if let Some(redeem_script) = redeem_script(&script_sig) {
+                cost += count_sigops(&redeem_script, true) * WITNESS_SCALE_FACTOR;
+                cost += witness_sigops(&redeem_script, &witness);
+            }
+        } else {
+            cost += witness_sigops(&scriptpubkey, &witness);
+        }
+    }
+
+    Ok(cost)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn count_sigops_test() {
+        // 2-OF-3 MULTISIG
+        let mut multisig = vec![0x52];
+        for _ in 0..3 {
+            multisig.push(33);
+            multisig.extend([0x02; 33]);
+        }
+        multisig.extend([0x53, 0xae]);
+
+        assert_eq!(count_sigops(&multisig, true), 3);
+        assert_eq!(count_sigops(&multisig, false), 20);
+
+        // P2PKH
+        let p2pkh = hex::decode("76a914edf10a7fac6b32e24daa5305c723f3de58db1bc888ac").unwrap();
+        assert_eq!(count_sigops(&p2pkh, false), 1);
+
+        // THE PUSHED 0xac IS DATA, NOT AN OPCODE
+        assert_eq!(count_sigops(&[0x01, 0xac], false), 0);
+
+        // TRUNCATED PUSHDATA STOPS THE COUNT
+        assert_eq!(count_sigops(&[0xac, 0x4c, 0x05, 0xac], false), 1);
+    }
+}
@@ -0,0 +1,676 @@
+// BIP341 / BIP342: KEY PATH AND SCRIPT PATH SPENDS OF V1_P2TR OUTPUTS
+// THEIR BIP340 SIGNATURES ARE COLLECTED WHILE THE SCRIPTS RUN AND CHECKED AT THE END, ONE BY ONE
+// OR AS A BATCH, WHICH IS SOUND BECAUSE A NON EMPTY SCHNORR SIGNATURE THAT FAILS FAILS THE INPUT
+
+use std::{fs, time::Instant};
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use secp256k1::{
+    constants::CURVE_ORDER, schnorr::Signature, Message, Parity, PublicKey, Scalar, Secp256k1,
+    SecretKey, XOnlyPublicKey,
+};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::{
+    block_mine::serialise_tx::compact_size,
+    error::Result,
+    script::ScriptType,
+    transaction::Transaction,
+    validation_checks::{
+        interpreter::{encoding_error_or_ok, verify_tapscript, ScriptResult},
+        script_error::ScriptError,
+        sig_cache::{signature_cache, SigKind},
+        sighash_cache::SighashCache,
+        single_sha256,
+        verify_flags::VerifyFlags,
+    },
+};
+
+pub const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+pub const TAPROOT_LEAF_MASK: u8 = 0xfe;
+
+// <LEAF VERSION | PARITY> <INTERNAL KEY> <UP TO 128 32 BYTE NODES OF THE MERKLE PATH>
+const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+// THE LAST OF TWO OR MORE WITNESS ITEMS IS THE ANNEX IF IT STARTS WITH 0x50
+pub const ANNEX_TAG: u8 = 0x50;
+
+// EACH SIGNATURE A TAPSCRIPT CHECKS USES UP 50 OF ITS WITNESS SIZE + 50
+pub const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
+// SIGHASH_DEFAULT ONLY EXISTS FOR 64 BYTE SIGNATURES, AND MEANS SIGHASH_ALL
+const SIGHASH_DEFAULT: u8 = 0x00;
+const SIGHASH_ALL: u8 = 0x01;
+const SIGHASH_SINGLE: u8 = 0x03;
+const SIGHASH_ANYONECANPAY: u8 = 0x80;
+
+// ONE BIP340 SIGNATURE TO CHECK, AND THE INPUT THAT FAILS IF IT IS INVALID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchnorrCheck {
+    pub input: usize,
+    pub sighash: [u8; 32],
+    pub pubkey: [u8; 32],
+    pub sig: [u8; 64],
+}
+
+// "serial" OR "batch" IN policy.json
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SchnorrMode {
+    // EVERY SIGNATURE ON ITS OWN
+    #[default]
+    Serial,
+    // ALL OF THEM IN ONE EQUATION, CHECKED ONE BY ONE ONLY IF IT FAILS
+    Batch,
+}
+
+// WHAT HAPPENS TO THE SIGNATURES OF A TX ONCE ALL ITS SCRIPTS HAVE PASSED
+#[derive(Debug)]
+pub enum SchnorrChecks<'a> {
+    // CHECKED RIGHT AWAY, IN THIS MODE
+    Verify(SchnorrMode),
+    // LEFT FOR THE CALLER, WHICH CHECKS THOSE OF A WHOLE BLOCK TOGETHER
+    Collect(&'a mut Vec<SchnorrCheck>),
+}
+
+// WHAT A TAPSCRIPT SIGNATURE COMMITS TO BESIDES THE TX, AND WHAT THE SCRIPT HAS USED UP
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tapscript {
+    pub leaf_hash: [u8; 32],
+    pub annex: Option<Vec<u8>>,
+    // OPCODE POSITION OF THE LAST EXECUTED OP_CODESEPARATOR, 0xffffffff FOR NONE
+    pub codesep_pos: u32,
+    pub validation_weight_left: i64,
+    // SIGNATURES THE SCRIPT ASSUMED VALID
+    pub checks: Vec<SchnorrCheck>,
+}
+
+// SHA256(SHA256(TAG) || SHA256(TAG) || DATA)
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag_hash = single_sha256(tag.as_bytes());
+    let mut preimage = tag_hash.clone();
+    preimage.extend_from_slice(&tag_hash);
+    preimage.extend_from_slice(data);
+
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&single_sha256(&preimage));
+    hash
+}
+
+pub fn tapleaf_hash(leaf_version: u8, script: &[u8]) -> [u8; 32] {
+    let mut leaf = vec![leaf_version];
+    leaf.extend(compact_size(script.len() as u64));
+    leaf.extend_from_slice(script);
+    tagged_hash("TapLeaf", &leaf)
+}
+
+fn sighash_error(error: failure::Error) -> ScriptError {
+    ScriptError::SigHash(error.to_string())
+}
+
+// BIP341 SIGNATURE MESSAGE, `leaf` IS THE TAPLEAF HASH AND CODESEPARATOR POSITION OF A TAPSCRIPT
+pub fn taproot_sighash(
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    hash_type: u8,
+    annex: Option<&[u8]>,
+    leaf: Option<(&[u8; 32], u32)>,
+) -> ScriptResult<[u8; 32]> {
+    if !matches!(hash_type, 0x00..=0x03 | 0x81..=0x83) {
+        return Err(ScriptError::SchnorrSigHashType);
+    }
+    let output_type = match hash_type {
+        SIGHASH_DEFAULT => SIGHASH_ALL,
+        _ => hash_type & 0x03,
+    };
+    let anyone_can_pay = hash_type & SIGHASH_ANYONECANPAY != 0;
+    let input = &tx.vin[tx_input_index];
+
+    // EPOCH 0
+    let mut msg = vec![0x00, hash_type];
+    msg.extend(tx.version.to_le_bytes());
+    msg.extend(tx.locktime.to_le_bytes());
+
+    if !anyone_can_pay {
+        msg.extend_from_slice(&cache.sha_prevouts);
+        msg.extend_from_slice(&cache.sha_amounts);
+        msg.extend_from_slice(&cache.sha_scriptpubkeys);
+        msg.extend_from_slice(&cache.sha_sequences);
+    }
+    if output_type == SIGHASH_ALL {
+        msg.extend_from_slice(&cache.sha_outputs);
+    }
+
+    let spend_type = 2 * leaf.is_some() as u8 + annex.is_some() as u8;
+    msg.push(spend_type);
+
+    if anyone_can_pay {
+        let script_pubkey = input.prevout.script_pubkey().map_err(sighash_error)?;
+        msg.extend_from_slice(&input.outpoint().consensus_bytes());
+        msg.extend(input.prevout.value.to_le_bytes());
+        msg.extend(compact_size(script_pubkey.as_bytes().len() as u64));
+        msg.extend_from_slice(script_pubkey.as_bytes());
+        msg.extend(input.sequence.to_le_bytes());
+    } else {
+        msg.extend((tx_input_index as u32).to_le_bytes());
+    }
+
+    if let Some(annex) = annex {
+        let mut serialised = compact_size(annex.len() as u64);
+        serialised.extend_from_slice(annex);
+        msg.extend(single_sha256(&serialised));
+    }
+
+    // SIGHASH_SINGLE SIGNS THE OUTPUT AT THE INPUT'S INDEX, WHICH MUST EXIST
+    if output_type == SIGHASH_SINGLE {
+        let output = tx
+            .vout
+            .get(tx_input_index)
+            .ok_or(ScriptError::SchnorrSigHashType)?;
+        let script_pubkey = output.script_pubkey().map_err(sighash_error)?;
+        let mut serialised = output.value.to_le_bytes().to_vec();
+        serialised.extend(compact_size(script_pubkey.as_bytes().len() as u64));
+        serialised.extend_from_slice(script_pubkey.as_bytes());
+        msg.extend(single_sha256(&serialised));
+    }
+
+    if let Some((leaf_hash, codesep_pos)) = leaf {
+        msg.extend_from_slice(leaf_hash);
+        // KEY VERSION 0
+        msg.push(0x00);
+        msg.extend(codesep_pos.to_le_bytes());
+    }
+
+    Ok(tagged_hash("TapSighash", &msg))
+}
+
+// A 64 BYTE SIGNATURE USES SIGHASH_DEFAULT, A 65 BYTE ONE NAMES ANY OTHER TYPE IN ITS LAST BYTE
+pub fn schnorr_check(
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    pubkey: &[u8],
+    sig: &[u8],
+    annex: Option<&[u8]>,
+    leaf: Option<(&[u8; 32], u32)>,
+) -> ScriptResult<SchnorrCheck> {
+    let hash_type = match sig.len() {
+        64 => SIGHASH_DEFAULT,
+        65 if sig[64] != SIGHASH_DEFAULT => sig[64],
+        65 => return Err(ScriptError::SchnorrSigHashType),
+        _ => return Err(ScriptError::SchnorrSigSize),
+    };
+
+    let mut check = SchnorrCheck {
+        input: tx_input_index,
+        sighash: taproot_sighash(tx, cache, tx_input_index, hash_type, annex, leaf)?,
+        pubkey: [0u8; 32],
+        sig: [0u8; 64],
+    };
+    check.pubkey.copy_from_slice(pubkey);
+    check.sig.copy_from_slice(&sig[..64]);
+    Ok(check)
+}
+
+pub fn verify_schnorr(check: &SchnorrCheck) -> bool {
+    let (sig, pubkey) = match (
+        Signature::from_slice(&check.sig),
+        XOnlyPublicKey::from_slice(&check.pubkey),
+    ) {
+        (Ok(sig), Ok(pubkey)) => (sig, pubkey),
+        _ => return false,
+    };
+
+    Secp256k1::verification_only()
+        .verify_schnorr(&sig, &Message::from_digest(check.sighash), &pubkey)
+        .is_ok()
+}
+
+fn to_bytes32(value: &BigUint) -> [u8; 32] {
+    let bytes = value.to_bytes_be();
+    let mut padded = [0u8; 32];
+    padded[32 - bytes.len()..].copy_from_slice(&bytes);
+    padded
+}
+
+// THE POINT WITH THIS X COORDINATE AND AN EVEN Y
+fn lift_x(x: &[u8]) -> Option<PublicKey> {
+    XOnlyPublicKey::from_slice(x)
+        .ok()
+        .map(|key| key.public_key(Parity::Even))
+}
+
+// BIP340 BATCH VERIFICATION: (s_1 + a_2 s_2 + ..) G == R_1 + a_2 R_2 + .. + e_1 P_1 + a_2 e_2 P_2 + ..
+// THE a_i COME FROM A HASH OF EVERYTHING BEING CHECKED, SO NO SIGNATURE CAN BE MADE TO CANCEL ANOTHER
+// FALSE ONLY SAYS THAT SOME SIGNATURE IS INVALID, NOT WHICH
+pub fn verify_schnorr_batch(checks: &[SchnorrCheck]) -> bool {
+    if checks.is_empty() {
+        return true;
+    }
+
+    let secp = Secp256k1::new();
+    let order = BigUint::from_bytes_be(&CURVE_ORDER);
+
+    let mut seed_data: Vec<u8> = Vec::new();
+    for check in checks.iter() {
+        seed_data.extend_from_slice(&check.pubkey);
+        seed_data.extend_from_slice(&check.sighash);
+        seed_data.extend_from_slice(&check.sig);
+    }
+    let seed = tagged_hash("BIP0340/batch", &seed_data);
+
+    let mut s_sum = BigUint::zero();
+    let mut points: Vec<PublicKey> = Vec::new();
+
+    for (index, check) in checks.iter().enumerate() {
+        let (r_point, p_point) = match (lift_x(&check.sig[..32]), lift_x(&check.pubkey)) {
+            (Some(r_point), Some(p_point)) => (r_point, p_point),
+            _ => return false,
+        };
+        let s = BigUint::from_bytes_be(&check.sig[32..]);
+        if s >= order {
+            return false;
+        }
+
+        let mut challenge = check.sig[..32].to_vec();
+        challenge.extend_from_slice(&check.pubkey);
+        challenge.extend_from_slice(&check.sighash);
+        let e = BigUint::from_bytes_be(&tagged_hash("BIP0340/challenge", &challenge)) % &order;
+
+        let a = match index {
+            0 => BigUint::one(),
+            _ => {
+                let mut preimage = seed.to_vec();
+                preimage.extend((index as u32).to_le_bytes());
+                BigUint::from_bytes_be(&single_sha256(&preimage)) % &order
+            }
+        };
+
+        s_sum = (s_sum + &a * s) % &order;
+
+        // A ZERO FACTOR ADDS NOTHING, AND CAN'T BE PASSED TO mul_tweak
+        for (point, factor) in [(r_point, a.clone()), (p_point, (&a * e) % &order)] {
+            if factor.is_zero() {
+                continue;
+            }
+            let scalar = match Scalar::from_be_bytes(to_bytes32(&factor)) {
+                Ok(scalar) => scalar,
+                Err(_) => return false,
+            };
+            match point.mul_tweak(&secp, &scalar) {
+                Ok(point) => points.push(point),
+                Err(_) => return false,
+            }
+        }
+    }
+
+    // BOTH SIDES AT INFINITY IS SO UNLIKELY THAT THE SIGNATURES ARE JUST CHECKED ONE BY ONE
+    let lhs = match SecretKey::from_slice(&to_bytes32(&s_sum)) {
+        Ok(s_sum) => PublicKey::from_secret_key(&secp, &s_sum),
+        Err(_) => return false,
+    };
+    let rhs = match PublicKey::combine_keys(&points.iter().collect::<Vec<&PublicKey>>()) {
+        Ok(rhs) => rhs,
+        Err(_) => return false,
+    };
+    lhs == rhs
+}
+
+// THE INPUT OF THE FIRST INVALID SIGNATURE, NONE IF ALL ARE VALID
+// CACHED SIGNATURES ARE SKIPPED AND VALID ONES ARE CACHED
+// A FAILED BATCH IS CHECKED AGAIN ONE BY ONE TO FIND THE INPUT
+pub fn verify_schnorr_checks(checks: &[SchnorrCheck], mode: SchnorrMode) -> Option<usize> {
+    let unchecked: Vec<SchnorrCheck> = checks
+        .iter()
+        .filter(|check| {
+            !signature_cache().contains(SigKind::Schnorr, &check.sighash, &check.pubkey, &check.sig)
+        })
+        .copied()
+        .collect();
+
+    let cache_all = |checks: &[SchnorrCheck]| {
+        let mut cache = signature_cache();
+        for check in checks.iter() {
+            cache.insert(SigKind::Schnorr, &check.sighash, &check.pubkey, &check.sig);
+        }
+    };
+
+    if mode == SchnorrMode::Batch && verify_schnorr_batch(&unchecked) {
+        cache_all(&unchecked);
+        return None;
+    }
+
+    for (index, check) in unchecked.iter().enumerate() {
+        if !verify_schnorr(check) {
+            cache_all(&unchecked[..index]);
+            return Some(check.input);
+        }
+    }
+    cache_all(&unchecked);
+    None
+}
+
+// THE CONTROL BLOCK'S MERKLE PATH TAKES THE LEAF TO THE ROOT, AND THE INTERNAL KEY TWEAKED
+// WITH THE ROOT MUST BE THE OUTPUT KEY
+fn verify_taproot_commitment(control: &[u8], output_key: &[u8], leaf_hash: &[u8; 32]) -> bool {
+    let (internal_key, output_key) = match (
+        XOnlyPublicKey::from_slice(&control[1..TAPROOT_CONTROL_BASE_SIZE]),
+        XOnlyPublicKey::from_slice(output_key),
+    ) {
+        (Ok(internal_key), Ok(output_key)) => (internal_key, output_key),
+        _ => return false,
+    };
+
+    // EACH BRANCH HASHES ITS TWO CHILDREN IN SORTED ORDER
+    let mut node = *leaf_hash;
+    for sibling in control[TAPROOT_CONTROL_BASE_SIZE..].chunks(TAPROOT_CONTROL_NODE_SIZE) {
+        let branch = match node.as_slice() < sibling {
+            true => [&node[..], sibling].concat(),
+            false => [sibling, &node[..]].concat(),
+        };
+        node = tagged_hash("TapBranch", &branch);
+    }
+
+    let tweak = tagged_hash(
+        "TapTweak",
+        &[&control[1..TAPROOT_CONTROL_BASE_SIZE], &node[..]].concat(),
+    );
+    let tweak = match Scalar::from_be_bytes(tweak) {
+        Ok(tweak) => tweak,
+        Err(_) => return false,
+    };
+    let parity = match control[0] & 1 {
+        0 => Parity::Even,
+        _ => Parity::Odd,
+    };
+
+    internal_key.tweak_add_check(&Secp256k1::verification_only(), &output_key, parity, tweak)
+}
+
+// BIP341: ONE ITEM IS A KEY PATH SIGNATURE, MORE ARE THE SCRIPT INPUTS, THE SCRIPT AND THE CONTROL
+// BLOCK. THE SIGNATURES ARE ADDED TO `checks` INSTEAD OF BEING VERIFIED
+fn verify_taproot_spend(
+    mut witness: Vec<Vec<u8>>,
+    output_key: &[u8],
+    tx: &Transaction,
+    cache: &SighashCache,
+    tx_input_index: usize,
+    flags: VerifyFlags,
+    checks: &mut Vec<SchnorrCheck>,
+) -> ScriptResult<()> {
+    if witness.is_empty() {
+        return Err(ScriptError::WitnessProgramWitnessEmpty);
+    }
+
+    // THE WHOLE WITNESS, ANNEX INCLUDED, PAYS FOR THE SIGNATURES A TAPSCRIPT CHECKS
+    let mut witness_size = compact_size(witness.len() as u64).len();
+    for item in witness.iter() {
+        witness_size += compact_size(item.len() as u64).len() + item.len();
+    }
+
+    let annex = match witness.last() {
+        Some(last) if witness.len() >= 2 && last.first() == Some(&ANNEX_TAG) => witness.pop(),
+        _ => None,
+    };
+
+    if witness.len() == 1 {
+        let check = schnorr_check(
+            tx,
+            cache,
+            tx_input_index,
+            output_key,
+            &witness[0],
+            annex.as_deref(),
+            None,
+        )?;
+        checks.push(check);
+        return Ok(());
+    }
+
+    let (control, script) = match (witness.pop(), witness.pop()) {
+        (Some(control), Some(script)) => (control, script),
+        _ => return Err(ScriptError::WitnessProgramWitnessEmpty),
+    };
+    let max_control_size =
+        TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * TAPROOT_CONTROL_MAX_NODE_COUNT;
+    if control.len() < TAPROOT_CONTROL_BASE_SIZE
+        || control.len() > max_control_size
+        || !(control.len() - TAPROOT_CONTROL_BASE_SIZE).is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+    {
+        return Err(ScriptError::TaprootWrongControlSize);
+    }
+
+    let leaf_version = control[0] & TAPROOT_LEAF_MASK;
+    let leaf_hash = tapleaf_hash(leaf_version, &script);
+    if !verify_taproot_commitment(&control, output_key, &leaf_hash) {
+        return Err(ScriptError::WitnessProgramMismatch);
+    }
+
+    // OTHER LEAF VERSIONS ARE ANYONE CAN SPEND UNTIL A SOFT FORK GIVES THEM A MEANING
+    if leaf_version != TAPROOT_LEAF_TAPSCRIPT {
+        if flags.contains(VerifyFlags::DISCOURAGE_UPGRADABLE_TAPROOT_VERSION) {
+            return Err(ScriptError::DiscourageUpgradableTaprootVersion);
+        }
+        return Ok(());
+    }
+
+    let mut tapscript = Tapscript {
+        leaf_hash,
+        annex,
+        codesep_pos: u32::MAX,
+        validation_weight_left: VALIDATION_WEIGHT_OFFSET + witness_size as i64,
+        checks: Vec::new(),
+    };
+    verify_tapscript(
+        witness,
+        &script,
+        tx,
+        cache,
+        tx_input_index,
+        flags,
+        &mut tapscript,
+    )?;
+    checks.extend(tapscript.checks);
+    Ok(())
+}
+
+// INPUT VERIFICATION FOR P2TR, THE SIGNATURES ARE LEFT IN `checks` FOR THE CALLER TO VERIFY
+pub fn input_verification_p2tr(
+    tx_input_index: usize,
+    tx: &Transaction,
+    cache: &SighashCache,
+    flags: VerifyFlags,
+    checks: &mut Vec<SchnorrCheck>,
+) -> Result<bool> {
+    let mut witness: Vec<Vec<u8>> = Vec::new();
+    for item in tx.vin[tx_input_index].witness.clone().unwrap_or_default() {
+        witness.push(hex::decode(&item)?);
+    }
+
+    let script_pubkey = tx.vin[tx_input_index].prevout.script_pubkey()?;
+    let output_key = match script_pubkey.witness_program() {
+        Some((1, program)) if program.len() == 32 => program.to_vec(),
+        _ => return Ok(false),
+    };
+
+    // BEFORE BIP341 A V1 WITNESS PROGRAM IS ANYONE CAN SPEND
+    if !flags.contains(VerifyFlags::TAPROOT) {
+        return Ok(true);
+    }
+
+    encoding_error_or_ok(verify_taproot_spend(
+        witness,
+        &output_key,
+        tx,
+        cache,
+        tx_input_index,
+        flags,
+        checks,
+    ))
+}
+
+// ENTRY POINT OF `schnorr-bench`: EVERY VALID TAPROOT SIGNATURE OF THE MEMPOOL, CHECKED ONE BY ONE
+// AND THEN AS ONE BATCH, WITHOUT THE SIGNATURE CACHE
+pub fn schnorr_bench(mempool_dir: &str) -> Result<()> {
+    let mut checks: Vec<SchnorrCheck> = Vec::new();
+
+    for entry in WalkDir::new(mempool_dir).into_iter().filter_map(|e| e.ok()) {
+        if !entry.path().is_file() {
+            continue;
+        }
+        let tx: Transaction = match serde_json::from_str(&fs::read_to_string(entry.path())?) {
+            Ok(tx) => tx,
+            Err(_) => continue,
+        };
+        let all_p2tr = tx.vin.iter().all(|input| {
+            input
+                .prevout
+                .script_pubkey()
+                .map(|script_pubkey| script_pubkey.script_type() == ScriptType::P2tr)
+                .unwrap_or(false)
+        });
+        if !all_p2tr {
+            continue;
+        }
+
+        let cache = SighashCache::new(&tx)?;
+        for input_index in 0..tx.vin.len() {
+            let mut input_checks = Vec::new();
+            let verified = input_verification_p2tr(
+                input_index,
+                &tx,
+                &cache,
+                VerifyFlags::CONSENSUS,
+                &mut input_checks,
+            );
+            if let Ok(true) = verified {
+                checks.extend(input_checks);
+            }
+        }
+    }
+    checks.retain(verify_schnorr);
+
+    let start = Instant::now();
+    let serial = checks.iter().all(verify_schnorr);
+    let serial_time = start.elapsed();
+
+    let start = Instant::now();
+    let batch = verify_schnorr_batch(&checks);
+    let batch_time = start.elapsed();
+
+    println!("SCHNORR SIGNATURES: {}", checks.len());
+    println!("SERIAL: {:?} ({})", serial_time, serial);
+    println!("BATCH: {:?} ({})", batch_time, batch);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::validation_checks::{verify_flags::VerifyFlags, verify_tx};
+
+    use super::*;
+
+    // TWO KEY PATH INPUTS
+    const KEY_PATH_TX: &str =
+        "./mempool/001035505afbf143e51bd667099190943a38eee20092bb691e72eaa44992b2f7.json";
+    // <sig> <pubkey OP_CHECKSIG ..> <control block>
+    const SCRIPT_PATH_TX: &str =
+        "./mempool/0026c0aa204a6da8916bf5849cff17d3c81b1a2b6f035045b5dc3263d8a448e2.json";
+
+    fn checks_of(path: &str) -> Result<(Transaction, Vec<SchnorrCheck>)> {
+        let tx: Transaction = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let cache = SighashCache::new(&tx)?;
+
+        let mut checks = Vec::new();
+        for input_index in 0..tx.vin.len() {
+            let verified = input_verification_p2tr(
+                input_index,
+                &tx,
+                &cache,
+                VerifyFlags::STANDARD,
+                &mut checks,
+            )?;
+            assert!(verified);
+        }
+        Ok((tx, checks))
+    }
+
+    #[test]
+    fn schnorr_batch_test() -> Result<()> {
+        let (tx, mut checks) = checks_of(KEY_PATH_TX)?;
+        assert!(checks.len() > 1);
+        assert!(checks.iter().all(verify_schnorr));
+        assert!(verify_schnorr_batch(&checks));
+        assert!(verify_tx(
+            tx.clone(),
+            VerifyFlags::STANDARD,
+            SchnorrMode::Serial
+        )?);
+        assert!(verify_tx(tx, VerifyFlags::STANDARD, SchnorrMode::Batch)?);
+
+        // ONE BAD SIGNATURE FAILS THE BATCH, AND THE ONE BY ONE PASS NAMES ITS INPUT
+        checks[1].sig[63] ^= 0x01;
+        assert!(!verify_schnorr_batch(&checks));
+        assert_eq!(
+            verify_schnorr_checks(&checks, SchnorrMode::Batch),
+            Some(checks[1].input)
+        );
+        assert_eq!(
+            verify_schnorr_checks(&checks, SchnorrMode::Serial),
+            Some(checks[1].input)
+        );
+
+        // SIGNATURES IN THE SAME ORDER BUT SWAPPED BETWEEN MESSAGES
+        checks[1].sig[63] ^= 0x01;
+        let (first, second) = (checks[0].sig, checks[1].sig);
+        checks[0].sig = second;
+        checks[1].sig = first;
+        assert!(!verify_schnorr_batch(&checks));
+        Ok(())
+    }
+
+    #[test]
+    fn script_path_test() -> Result<()> {
+        let (tx, checks) = checks_of(SCRIPT_PATH_TX)?;
+        assert!(!checks.is_empty());
+        assert!(verify_schnorr_batch(&checks));
+
+        // A CHANGED CONTROL BLOCK DOESN'T COMMIT TO THE OUTPUT KEY
+        let mut tampered = tx.clone();
+        let witness = tampered.vin[0].witness.as_mut().unwrap();
+        let control = witness.last_mut().unwrap();
+        let flipped = if control.ends_with('0') { "1" } else { "0" };
+        control.replace_range(control.len() - 1.., flipped);
+        let cache = SighashCache::new(&tampered)?;
+        assert_eq!(
+            verify_taproot_spend(
+                tampered.vin[0]
+                    .witness
+                    .clone()
+                    .unwrap()
+                    .iter()
+                    .map(|item| hex::decode(item).unwrap())
+                    .collect(),
+                tampered.vin[0]
+                    .prevout
+                    .script_pubkey()?
+                    .witness_program()
+                    .unwrap()
+                    .1,
+                &tampered,
+                &cache,
+                0,
+                VerifyFlags::STANDARD,
+                &mut Vec::new(),
+            ),
+            Err(ScriptError::WitnessProgramMismatch)
+        );
+        Ok(())
+    }
+}
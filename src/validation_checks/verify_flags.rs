@@ -0,0 +1,140 @@
+// WHICH SCRIPT RULES TO ENFORCE, WITH THE BITS OF BITCOIN CORE'S SCRIPT_VERIFY_* FLAGS
+// A BLOCK ONLY HAS TO FOLLOW THE CONSENSUS RULES, A TX RELAYED FROM THE MEMPOOL ALSO THE POLICY ONES
+
+use std::ops::{BitOr, BitOrAssign};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct VerifyFlags(u32);
+
+impl VerifyFlags {
+    pub const NONE: VerifyFlags = VerifyFlags(0);
+
+    // BIP16: RUN THE REDEEM SCRIPT OF A P2SH OUTPUT
+    pub const P2SH: VerifyFlags = VerifyFlags(1 << 0);
+    // SIGNATURES ARE STRICT DER WITH A DEFINED SIGHASH TYPE, KEYS ARE COMPRESSED OR UNCOMPRESSED
+    pub const STRICTENC: VerifyFlags = VerifyFlags(1 << 1);
+    // BIP66: SIGNATURES ARE STRICT DER
+    pub const DERSIG: VerifyFlags = VerifyFlags(1 << 2);
+    // THE S VALUE OF A SIGNATURE IS AT MOST HALF THE CURVE ORDER
+    pub const LOW_S: VerifyFlags = VerifyFlags(1 << 3);
+    // BIP147: THE EXTRA ITEM POPPED BY OP_CHECKMULTISIG IS EMPTY
+    pub const NULLDUMMY: VerifyFlags = VerifyFlags(1 << 4);
+    // THE SCRIPT SIG ONLY PUSHES DATA
+    pub const SIGPUSHONLY: VerifyFlags = VerifyFlags(1 << 5);
+    // PUSHES USE THEIR SHORTEST OPCODE AND NUMERIC OPERANDS THEIR SHORTEST ENCODING
+    pub const MINIMALDATA: VerifyFlags = VerifyFlags(1 << 6);
+    // OP_NOP1 AND OP_NOP4..OP_NOP10 ARE KEPT FOR SOFT FORKS AND FAIL THE SCRIPT
+    pub const DISCOURAGE_UPGRADABLE_NOPS: VerifyFlags = VerifyFlags(1 << 7);
+    // A REDEEM SCRIPT LEAVES EXACTLY ONE ITEM ON THE STACK
+    pub const CLEANSTACK: VerifyFlags = VerifyFlags(1 << 8);
+    // BIP65: OP_CHECKLOCKTIMEVERIFY INSTEAD OF OP_NOP2
+    pub const CHECKLOCKTIMEVERIFY: VerifyFlags = VerifyFlags(1 << 9);
+    // BIP112: OP_CHECKSEQUENCEVERIFY INSTEAD OF OP_NOP3
+    pub const CHECKSEQUENCEVERIFY: VerifyFlags = VerifyFlags(1 << 10);
+    // BIP141: CHECK THE WITNESS OF SEGWIT OUTPUTS
+    pub const WITNESS: VerifyFlags = VerifyFlags(1 << 11);
+    // WITNESS VERSIONS ABOVE THE KNOWN ONES ARE KEPT FOR SOFT FORKS AND FAIL THE SPEND
+    pub const DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM: VerifyFlags = VerifyFlags(1 << 12);
+    // OP_IF AND OP_NOTIF IN A SEGWIT V0 SCRIPT ONLY TAKE AN EMPTY ITEM OR 0x01
+    pub const MINIMALIF: VerifyFlags = VerifyFlags(1 << 13);
+    // A FAILED SIGNATURE CHECK MUST HAVE BEEN GIVEN AN EMPTY SIGNATURE
+    pub const NULLFAIL: VerifyFlags = VerifyFlags(1 << 14);
+    // SEGWIT V0 SCRIPTS ONLY TAKE COMPRESSED KEYS
+    pub const WITNESS_PUBKEYTYPE: VerifyFlags = VerifyFlags(1 << 15);
+    // LEGACY SCRIPTS DON'T USE OP_CODESEPARATOR
+    pub const CONST_SCRIPTCODE: VerifyFlags = VerifyFlags(1 << 16);
+    // BIP341: CHECK THE WITNESS OF TAPROOT OUTPUTS
+    pub const TAPROOT: VerifyFlags = VerifyFlags(1 << 17);
+    // TAPROOT LEAF VERSIONS OTHER THAN TAPSCRIPT ARE KEPT FOR SOFT FORKS AND FAIL THE SPEND
+    pub const DISCOURAGE_UPGRADABLE_TAPROOT_VERSION: VerifyFlags = VerifyFlags(1 << 18);
+    // OP_SUCCESSX IN A TAPSCRIPT IS KEPT FOR SOFT FORKS AND FAILS THE SCRIPT
+    pub const DISCOURAGE_OP_SUCCESS: VerifyFlags = VerifyFlags(1 << 19);
+    // TAPSCRIPT KEYS THAT AREN'T 32 BYTES ARE KEPT FOR SOFT FORKS AND FAIL THE SCRIPT
+    pub const DISCOURAGE_UPGRADABLE_PUBKEYTYPE: VerifyFlags = VerifyFlags(1 << 20);
+
+    // RULES EVERY BLOCK MUST FOLLOW, FOR BLOCK VALIDATION
+    pub const CONSENSUS: VerifyFlags = VerifyFlags::P2SH
+        .union(VerifyFlags::DERSIG)
+        .union(VerifyFlags::NULLDUMMY)
+        .union(VerifyFlags::CHECKLOCKTIMEVERIFY)
+        .union(VerifyFlags::CHECKSEQUENCEVERIFY)
+        .union(VerifyFlags::WITNESS)
+        .union(VerifyFlags::TAPROOT);
+
+    // RULES A TX MUST ALSO FOLLOW TO BE ACCEPTED INTO THE MEMPOOL
+    pub const STANDARD: VerifyFlags = VerifyFlags::CONSENSUS
+        .union(VerifyFlags::STRICTENC)
+        .union(VerifyFlags::LOW_S)
+        .union(VerifyFlags::SIGPUSHONLY)
+        .union(VerifyFlags::MINIMALDATA)
+        .union(VerifyFlags::DISCOURAGE_UPGRADABLE_NOPS)
+        .union(VerifyFlags::CLEANSTACK)
+        .union(VerifyFlags::DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM)
+        .union(VerifyFlags::MINIMALIF)
+        .union(VerifyFlags::NULLFAIL)
+        .union(VerifyFlags::WITNESS_PUBKEYTYPE)
+        .union(VerifyFlags::CONST_SCRIPTCODE)
+        .union(VerifyFlags::DISCOURAGE_UPGRADABLE_TAPROOT_VERSION)
+        .union(VerifyFlags::DISCOURAGE_OP_SUCCESS)
+        .union(VerifyFlags::DISCOURAGE_UPGRADABLE_PUBKEYTYPE);
+
+    pub const fn union(self, other: VerifyFlags) -> VerifyFlags {
+        VerifyFlags(self.0 | other.0)
+    }
+
+    pub const fn difference(self, other: VerifyFlags) -> VerifyFlags {
+        VerifyFlags(self.0 & !other.0)
+    }
+
+    // EVERY FLAG OF `other` IS SET
+    pub const fn contains(self, other: VerifyFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    // ANY FLAG OF `other` IS SET
+    pub const fn intersects(self, other: VerifyFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl BitOr for VerifyFlags {
+    type Output = VerifyFlags;
+
+    fn bitor(self, other: VerifyFlags) -> VerifyFlags {
+        self.union(other)
+    }
+}
+
+impl BitOrAssign for VerifyFlags {
+    fn bitor_assign(&mut self, other: VerifyFlags) {
+        *self = self.union(other);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_flags_test() {
+        assert!(VerifyFlags::STANDARD.contains(VerifyFlags::CONSENSUS));
+        assert!(VerifyFlags::STANDARD.contains(VerifyFlags::LOW_S | VerifyFlags::STRICTENC));
+        assert!(!VerifyFlags::CONSENSUS.contains(VerifyFlags::LOW_S));
+        assert!(!VerifyFlags::CONSENSUS.intersects(VerifyFlags::LOW_S | VerifyFlags::STRICTENC));
+
+        let policy = VerifyFlags::STANDARD.difference(VerifyFlags::CONSENSUS);
+        assert!(policy.contains(VerifyFlags::CLEANSTACK | VerifyFlags::NULLFAIL));
+        assert!(policy.contains(VerifyFlags::SIGPUSHONLY));
+        assert!(!policy.intersects(VerifyFlags::P2SH | VerifyFlags::WITNESS));
+
+        let mut flags = VerifyFlags::NONE;
+        flags |= VerifyFlags::P2SH;
+        flags |= VerifyFlags::DERSIG;
+        assert_eq!(flags.bits(), 5);
+        assert!(VerifyFlags::CONSENSUS.contains(flags));
+    }
+}